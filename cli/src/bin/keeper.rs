@@ -0,0 +1,31 @@
+use std::{io::stdout, time::Duration};
+
+use anyhow::{anyhow, Result};
+use clap::Parser;
+use dogetrack_cli::{
+    keeper::{Keeper, KeeperCli, ResultsDir},
+    Operator,
+};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{commitment_config::CommitmentConfig, signature::read_keypair_file};
+
+fn main() -> Result<()> {
+    let cli = KeeperCli::parse();
+
+    let rpc = RpcClient::new_with_commitment(cli.url.clone(), CommitmentConfig::confirmed());
+    let signer = read_keypair_file(&cli.keypair)
+        .map_err(|err| anyhow!("Failed to read keypair {}: {}", cli.keypair, err))?;
+    let results = ResultsDir(cli.results_dir.clone());
+
+    Keeper {
+        operator: Operator {
+            rpc: &rpc,
+            signer: &signer,
+            dry_run: cli.dry_run,
+        },
+        results: &results,
+        retention: cli.retention,
+        stuck_after: cli.stuck_after,
+    }
+    .run(Duration::from_secs(cli.interval), &mut stdout())
+}
@@ -0,0 +1,452 @@
+//! Long-running maintenance for the doge game program. Each pass flushes stale racers out of
+//! lobbies that never filled, settles started races once their results are published, closes
+//! race records past their retention period and reports races left waiting on results.
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::{ErrorKind, Write},
+    path::PathBuf,
+    str::FromStr,
+    thread,
+    time::Duration,
+};
+
+use anyhow::{anyhow, bail, Result};
+use clap::Parser;
+use dogetrack_sdk::{
+    client::{fetch, fetch_all, Rpc},
+    dogegamecontract::{
+        self,
+        constants::{LOBBY_STATE_VERSION, STALE_RACERS_FLUSH_COOLDOWN},
+        state::{DogeRacerState, LobbyState, RaceState, SeasonState, SeasonsState},
+    },
+    instructions::{self, ConcludeRaceArgs, SeasonKeys},
+    pda,
+};
+use serde::Deserialize;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{fetch_doge_holder, fetch_doge_keys, fetch_unsettled_racers, race_age, Operator};
+
+#[derive(Parser)]
+#[clap(
+    name = "dogetrack-keeper",
+    about = "Flushes stale racers, settles finished races and closes old race records"
+)]
+pub struct KeeperCli {
+    /// RPC endpoint of the cluster
+    #[clap(
+        long,
+        short = 'u',
+        default_value = "https://api.mainnet-beta.solana.com"
+    )]
+    pub url: String,
+
+    /// Keypair signing admin instructions, must be the program authority
+    #[clap(long, short = 'k', default_value = "contractpk.json")]
+    pub keypair: String,
+
+    /// Simulate transactions instead of sending them
+    #[clap(long)]
+    pub dry_run: bool,
+
+    /// Directory the race server publishes results to, as `<lobby>-<race number>.json`
+    #[clap(long)]
+    pub results_dir: PathBuf,
+
+    /// Seconds between passes
+    #[clap(long, default_value_t = 30)]
+    pub interval: u64,
+
+    /// Seconds a settled race record is kept before it is closed
+    #[clap(long, default_value_t = 7 * 24 * 60 * 60)]
+    pub retention: u64,
+
+    /// Seconds a started race can wait on its results before it is reported stuck
+    #[clap(long, default_value_t = STALE_RACERS_FLUSH_COOLDOWN)]
+    pub stuck_after: u64,
+}
+
+// Result of a race run off chain, ready to be settled
+#[derive(Clone, Debug, PartialEq)]
+pub struct RaceOutcome {
+    pub race_started: u64,
+    // Doge racer accounts, winner first
+    pub finishing_order: Vec<Pubkey>,
+    // Winning time in milliseconds, 0 when unknown
+    pub winning_time: u64,
+}
+
+// Where finished races are looked up
+pub trait RaceResults {
+    fn race_outcome(&self, lobby: &Pubkey, race_number: u64) -> Result<Option<RaceOutcome>>;
+}
+
+impl RaceResults for HashMap<(Pubkey, u64), RaceOutcome> {
+    fn race_outcome(&self, lobby: &Pubkey, race_number: u64) -> Result<Option<RaceOutcome>> {
+        Ok(self.get(&(*lobby, race_number)).cloned())
+    }
+}
+
+// Results the race server writes out as JSON files, one per race
+pub struct ResultsDir(pub PathBuf);
+
+#[derive(Deserialize)]
+struct ResultFile {
+    race_started: u64,
+    finishing_order: Vec<String>,
+    #[serde(default)]
+    winning_time: u64,
+}
+
+pub fn parse_race_outcome(json: &str) -> Result<RaceOutcome> {
+    let file: ResultFile = serde_json::from_str(json)?;
+    let finishing_order = file
+        .finishing_order
+        .iter()
+        .map(|racer| Pubkey::from_str(racer).map_err(|err| anyhow!("{}: {}", racer, err)))
+        .collect::<Result<_>>()?;
+
+    Ok(RaceOutcome {
+        race_started: file.race_started,
+        finishing_order,
+        winning_time: file.winning_time,
+    })
+}
+
+impl RaceResults for ResultsDir {
+    fn race_outcome(&self, lobby: &Pubkey, race_number: u64) -> Result<Option<RaceOutcome>> {
+        let path = self.0.join(format!("{}-{}.json", lobby, race_number));
+        let json = match fs::read_to_string(&path) {
+            Ok(json) => json,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+
+        parse_race_outcome(&json)
+            .map(Some)
+            .map_err(|err| anyhow!("{}: {}", path.display(), err))
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Report {
+    pub flushed: usize,
+    pub settled: usize,
+    pub closed: usize,
+    pub stuck: usize,
+    pub failed: usize,
+}
+
+pub struct Keeper<'a> {
+    pub operator: Operator<'a>,
+    pub results: &'a dyn RaceResults,
+    // Seconds a settled race record is kept before it is closed
+    pub retention: u64,
+    // Seconds a started race can wait on its results before it is reported stuck
+    pub stuck_after: u64,
+}
+
+impl Keeper<'_> {
+    // Passes never end on a failed action, the next pass retries it
+    pub fn run(&self, interval: Duration, out: &mut dyn Write) -> Result<()> {
+        loop {
+            match self.tick(out) {
+                Ok(report) => writeln!(
+                    out,
+                    "Flushed {} racers, settled {} races, closed {} race records, {} stuck races, {} failures",
+                    report.flushed, report.settled, report.closed, report.stuck, report.failed
+                )?,
+                Err(err) => writeln!(out, "Pass failed: {}", err)?,
+            }
+            thread::sleep(interval);
+        }
+    }
+
+    pub fn tick(&self, out: &mut dyn Write) -> Result<Report> {
+        let rpc = self.operator.rpc;
+        let now = rpc.get_unix_timestamp()? as u64;
+        let lobbies: HashMap<Pubkey, LobbyState> =
+            fetch_all::<LobbyState>(rpc, &dogegamecontract::id())?
+                .into_iter()
+                .collect();
+        let mut report = Report::default();
+
+        for (address, racer) in fetch_all::<DogeRacerState>(rpc, &dogegamecontract::id())? {
+            let lobby = match lobbies.get(&racer.current_lobby_race) {
+                Some(lobby) if !lobby.race_started && lobby.version == LOBBY_STATE_VERSION => lobby,
+                _ => continue,
+            };
+            let waiting = now.saturating_sub(racer.last_joined_timestamp);
+            if racer.last_joined_timestamp == 0 || waiting < STALE_RACERS_FLUSH_COOLDOWN {
+                continue;
+            }
+
+            writeln!(
+                out,
+                "Flushing doge racer {} from lobby {}, waiting {}s",
+                address, racer.current_lobby_race, waiting
+            )?;
+            match self.flush_stale_racer(&racer, lobby, out) {
+                Ok(()) => report.flushed += 1,
+                Err(err) => {
+                    writeln!(out, "Failed to flush doge racer {}: {}", address, err)?;
+                    report.failed += 1;
+                }
+            }
+        }
+
+        let season = active_season(rpc, now)?;
+        for (address, lobby) in &lobbies {
+            if !lobby.race_started {
+                continue;
+            }
+
+            let unsettled = fetch_unsettled_racers(rpc, address, lobby)?;
+            let age = match race_age(now, &unsettled) {
+                Some(age) => age,
+                None => continue,
+            };
+
+            let outcome = match self.results.race_outcome(address, lobby.race_count) {
+                Ok(outcome) => outcome,
+                Err(err) => {
+                    writeln!(
+                        out,
+                        "Failed to read results of lobby {} race {}: {}",
+                        address, lobby.race_count, err
+                    )?;
+                    report.failed += 1;
+                    continue;
+                }
+            };
+
+            match outcome {
+                Some(outcome) => {
+                    writeln!(out, "Settling lobby {} race {}", address, lobby.race_count)?;
+                    match self.settle_race(lobby, &outcome, &unsettled, season, out) {
+                        Ok(()) => report.settled += 1,
+                        Err(err) => {
+                            writeln!(
+                                out,
+                                "Failed to settle lobby {} race {}: {}",
+                                address, lobby.race_count, err
+                            )?;
+                            report.failed += 1;
+                        }
+                    }
+                }
+                None if age >= self.stuck_after => {
+                    writeln!(
+                        out,
+                        "{} race {} started, {}/{} racers unsettled for {}s with no results",
+                        address,
+                        lobby.race_count,
+                        unsettled.len(),
+                        lobby.max_players,
+                        age
+                    )?;
+                    report.stuck += 1;
+                }
+                None => {}
+            }
+        }
+
+        for (address, race) in fetch_all::<RaceState>(rpc, &dogegamecontract::id())? {
+            let settled_for = now.saturating_sub(race.settled_at);
+            if race.settled_at == 0 || settled_for < self.retention {
+                continue;
+            }
+
+            writeln!(
+                out,
+                "Closing race record {} of lobby {} race {}, settled {}s ago",
+                address, race.lobby_account, race.race_number, settled_for
+            )?;
+            let instruction =
+                instructions::admin_close_race_state(&self.operator.authority(), &address);
+            match self.operator.submit(&[instruction], out) {
+                Ok(()) => report.closed += 1,
+                Err(err) => {
+                    writeln!(out, "Failed to close race record {}: {}", address, err)?;
+                    report.failed += 1;
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    fn flush_stale_racer(
+        &self,
+        racer: &DogeRacerState,
+        lobby: &LobbyState,
+        out: &mut dyn Write,
+    ) -> Result<()> {
+        let rpc = self.operator.rpc;
+        let instruction = instructions::flush_stale_racer(
+            &self.operator.authority(),
+            &fetch_doge_keys(rpc, racer)?,
+            &fetch_doge_holder(rpc, racer)?,
+            &racer.doge_holder_entry_fee_token,
+            lobby,
+        );
+
+        self.operator.submit(&[instruction], out)
+    }
+
+    // Records the race, then records and settles every racer not settled yet, one transaction
+    // each. Racers settled by an earlier, interrupted pass have already left the lobby and are
+    // skipped
+    fn settle_race(
+        &self,
+        lobby: &LobbyState,
+        outcome: &RaceOutcome,
+        unsettled: &[(Pubkey, DogeRacerState)],
+        season: Option<SeasonKeys>,
+        out: &mut dyn Write,
+    ) -> Result<()> {
+        let rpc = self.operator.rpc;
+        let seated: Vec<&Pubkey> = lobby
+            .racers
+            .iter()
+            .filter(|racer| racer.ne(&&Pubkey::default()))
+            .collect();
+        if outcome.finishing_order.len() != seated.len()
+            || seated
+                .iter()
+                .any(|racer| !outcome.finishing_order.contains(racer))
+        {
+            bail!("Finishing order does not match the lobby's racers");
+        }
+
+        let mut finishing_order = Vec::new();
+        for address in &outcome.finishing_order {
+            let racer = fetch::<DogeRacerState>(rpc, address)?
+                .ok_or_else(|| anyhow!("Doge racer {} not found", address))?;
+            finishing_order.push(fetch_doge_keys(rpc, &racer)?);
+        }
+
+        // Racers settle at the placements the race record holds. An interrupted pass may have
+        // written it already, along with some racers' results
+        let race_data = pda::race_data_address(&instructions::lobby_key(lobby), lobby.race_count).0;
+        let recorded: Vec<Pubkey> = match fetch::<RaceState>(rpc, &race_data)? {
+            Some(race) => race
+                .results
+                .iter()
+                .filter(|result| result.recorded)
+                .map(|result| result.doge_racer)
+                .collect(),
+            None => {
+                let instruction = instructions::record_race(
+                    &self.operator.authority(),
+                    lobby,
+                    &finishing_order,
+                    outcome.race_started,
+                    outcome.winning_time,
+                );
+                self.operator.submit(&[instruction], out)?;
+                Vec::new()
+            }
+        };
+
+        // The lobby resets once the last of them settled, whichever that is. Settling in
+        // finishing order pays the winner out first
+        let mut racers: Vec<_> = unsettled.iter().collect();
+        racers.sort_by_key(|(address, _)| {
+            outcome
+                .finishing_order
+                .iter()
+                .position(|racer| racer.eq(address))
+        });
+
+        for (address, racer) in racers {
+            let placement = outcome
+                .finishing_order
+                .iter()
+                .position(|racer| racer.eq(address))
+                .ok_or_else(|| anyhow!("Doge racer {} is not in the finishing order", address))?;
+            writeln!(out, "  {} placed {}", address, placement + 1)?;
+
+            if !recorded.contains(address) {
+                let instruction = instructions::record_racer_result(
+                    &self.operator.authority(),
+                    lobby,
+                    &finishing_order[placement],
+                    &racer.doge_holder_entry_fee_token,
+                    season,
+                );
+                self.operator.submit(&[instruction], out)?;
+            }
+
+            let instruction = instructions::conclude_race(&ConcludeRaceArgs {
+                authority: self.operator.authority(),
+                lobby,
+                doge: finishing_order[placement],
+                doge_holder: fetch_doge_holder(rpc, racer)?,
+                referrer: Some(racer.referrer).filter(|referrer| referrer.ne(&Pubkey::default())),
+                season,
+            });
+            self.operator.submit(&[instruction], out)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Seasons never overlap and ids only grow, so the active season, if any, is the newest one
+// that has started
+fn active_season(rpc: &dyn Rpc, now: u64) -> Result<Option<SeasonKeys>> {
+    let seasons = match fetch::<SeasonsState>(rpc, &pda::seasons_address().0)? {
+        Some(seasons) => seasons,
+        None => return Ok(None),
+    };
+
+    for season_id in (0..seasons.season_count).rev() {
+        let season = match fetch::<SeasonState>(rpc, &pda::season_address(season_id).0)? {
+            Some(season) => season,
+            None => continue,
+        };
+        if now < season.start_time {
+            continue;
+        }
+
+        let is_active = !season.closed && now < season.end_time;
+        return Ok(if is_active {
+            Some(SeasonKeys {
+                season_id,
+                prize_mint: season.prize_mint,
+            })
+        } else {
+            None
+        });
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_race_outcomes() {
+        let winner = Pubkey::new_unique();
+        let loser = Pubkey::new_unique();
+        let json = format!(
+            r#"{{"race_started": 1650000000, "finishing_order": ["{}", "{}"], "winning_time": 61234}}"#,
+            winner, loser
+        );
+
+        assert_eq!(
+            parse_race_outcome(&json).unwrap(),
+            RaceOutcome {
+                race_started: 1650000000,
+                finishing_order: vec![winner, loser],
+                winning_time: 61234,
+            }
+        );
+        assert!(parse_race_outcome(r#"{"race_started": 1, "finishing_order": ["doge"]}"#).is_err());
+    }
+}
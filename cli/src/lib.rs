@@ -0,0 +1,416 @@
+//! Admin and operator commands for the doge game program. Everything goes through the SDK's
+//! `Rpc` so the same commands run against a cluster or an in-process bank.
+
+pub mod keeper;
+
+use std::io::Write;
+
+use anyhow::{anyhow, bail, Result};
+use clap::{Args, Parser, Subcommand};
+use dogetrack_sdk::{
+    client::{fetch, fetch_all, Rpc},
+    doge_o::DogeStats,
+    dogegamecontract::{
+        self,
+        constants::STALE_RACERS_FLUSH_COOLDOWN,
+        state::{DogeRacerState, EntryFeeRequirements, LobbyState},
+    },
+    instructions, DogeKeys,
+};
+use solana_sdk::{
+    instruction::Instruction, program_pack::Pack, pubkey::Pubkey, signer::Signer,
+    transaction::Transaction,
+};
+
+#[derive(Parser)]
+#[clap(
+    name = "dogetrack",
+    about = "Admin and operator tooling for the doge game program"
+)]
+pub struct Cli {
+    /// RPC endpoint of the cluster
+    #[clap(
+        long,
+        short = 'u',
+        default_value = "https://api.mainnet-beta.solana.com"
+    )]
+    pub url: String,
+
+    /// Keypair signing admin instructions, must be the program authority
+    #[clap(long, short = 'k', default_value = "contractpk.json")]
+    pub keypair: String,
+
+    /// Simulate transactions instead of sending them
+    #[clap(long)]
+    pub dry_run: bool,
+
+    #[clap(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Set the entry fee bounds lobbies in a mint are created with
+    InitEntryFeeRequirements {
+        #[clap(long)]
+        mint: Pubkey,
+        #[clap(flatten)]
+        requirements: EntryFeeRequirementsArgs,
+    },
+    /// Replace the entry fee bounds of a mint
+    UpdateEntryFeeRequirements {
+        #[clap(long)]
+        mint: Pubkey,
+        #[clap(flatten)]
+        requirements: EntryFeeRequirementsArgs,
+    },
+    /// Refund a racer waiting in a lobby past the stale racer cooldown
+    FlushStaleRacer {
+        #[clap(long)]
+        doge_racer: Pubkey,
+    },
+    /// Close a lobby, returning its track and balances to the track holder
+    AdminCloseLobby {
+        #[clap(long)]
+        lobby: Pubkey,
+    },
+    /// Close a doge racer account
+    AdminCloseDogeRacer {
+        #[clap(long)]
+        doge_racer: Pubkey,
+    },
+    /// Close a race record
+    AdminCloseRaceState {
+        #[clap(long)]
+        race_data: Pubkey,
+    },
+    /// Close a mint's entry fee requirements
+    AdminCloseEntryFeeRequirements {
+        #[clap(long)]
+        mint: Pubkey,
+    },
+    /// List every lobby
+    ListLobbies,
+    /// List doges currently in a lobby
+    ListRacers {
+        /// Only list racers in this lobby
+        #[clap(long)]
+        lobby: Option<Pubkey>,
+    },
+    /// List started races with racers left unsettled
+    StuckRaces {
+        /// Seconds since the last racer joined before a race counts as stuck
+        #[clap(long, default_value_t = STALE_RACERS_FLUSH_COOLDOWN)]
+        min_age: u64,
+    },
+}
+
+// Fees are in ENTRY_FEE_DECIMALS precision, as the program stores them
+#[derive(Args)]
+pub struct EntryFeeRequirementsArgs {
+    #[clap(long)]
+    pub min_fee: u64,
+    #[clap(long)]
+    pub max_class_1_fee: u64,
+    #[clap(long)]
+    pub max_class_2_fee: u64,
+    #[clap(long)]
+    pub max_class_3_fee: u64,
+    #[clap(long)]
+    pub max_class_4_fee: u64,
+    #[clap(long)]
+    pub max_class_5_fee: u64,
+}
+
+impl From<&EntryFeeRequirementsArgs> for EntryFeeRequirements {
+    fn from(args: &EntryFeeRequirementsArgs) -> Self {
+        EntryFeeRequirements {
+            min_fee: args.min_fee,
+            max_class_1_fee: args.max_class_1_fee,
+            max_class_2_fee: args.max_class_2_fee,
+            max_class_3_fee: args.max_class_3_fee,
+            max_class_4_fee: args.max_class_4_fee,
+            max_class_5_fee: args.max_class_5_fee,
+        }
+    }
+}
+
+pub struct Operator<'a> {
+    pub rpc: &'a dyn Rpc,
+    pub signer: &'a dyn Signer,
+    pub dry_run: bool,
+}
+
+impl Operator<'_> {
+    pub(crate) fn authority(&self) -> Pubkey {
+        self.signer.pubkey()
+    }
+
+    pub(crate) fn submit(&self, instructions: &[Instruction], out: &mut dyn Write) -> Result<()> {
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.authority()),
+            &[self.signer],
+            self.rpc.get_latest_blockhash()?,
+        );
+
+        if !self.dry_run {
+            let signature = self.rpc.send_transaction(&transaction)?;
+            writeln!(out, "Signature: {}", signature)?;
+            return Ok(());
+        }
+
+        let simulation = self.rpc.simulate_transaction(&transaction)?;
+        for log in &simulation.logs {
+            writeln!(out, "  {}", log)?;
+        }
+
+        match simulation.error {
+            Some(error) => bail!("Simulation failed: {}", error),
+            None => {
+                writeln!(out, "Simulation succeeded")?;
+                Ok(())
+            }
+        }
+    }
+
+    fn fetch_lobby(&self, lobby: &Pubkey) -> Result<LobbyState> {
+        fetch::<LobbyState>(self.rpc, lobby)?.ok_or_else(|| anyhow!("Lobby {} not found", lobby))
+    }
+}
+
+pub fn run(command: &Command, operator: &Operator, out: &mut dyn Write) -> Result<()> {
+    let authority = operator.authority();
+
+    match command {
+        Command::InitEntryFeeRequirements { mint, requirements } => operator.submit(
+            &[instructions::init_entry_fee_requirements(
+                &authority,
+                mint,
+                requirements.into(),
+            )],
+            out,
+        ),
+        Command::UpdateEntryFeeRequirements { mint, requirements } => operator.submit(
+            &[instructions::update_entry_fee_requirements(
+                &authority,
+                mint,
+                requirements.into(),
+            )],
+            out,
+        ),
+        Command::FlushStaleRacer { doge_racer } => flush_stale_racer(operator, doge_racer, out),
+        Command::AdminCloseLobby { lobby } => {
+            let lobby_state = operator.fetch_lobby(lobby)?;
+            operator.submit(
+                &[instructions::admin_close_lobby(&authority, &lobby_state)],
+                out,
+            )
+        }
+        Command::AdminCloseDogeRacer { doge_racer } => operator.submit(
+            &[instructions::admin_close_doge_racer(&authority, doge_racer)],
+            out,
+        ),
+        Command::AdminCloseRaceState { race_data } => operator.submit(
+            &[instructions::admin_close_race_state(&authority, race_data)],
+            out,
+        ),
+        Command::AdminCloseEntryFeeRequirements { mint } => operator.submit(
+            &[instructions::admin_close_entry_fee_requirements(
+                &authority, mint,
+            )],
+            out,
+        ),
+        Command::ListLobbies => list_lobbies(operator, out),
+        Command::ListRacers { lobby } => list_racers(operator, lobby.as_ref(), out),
+        Command::StuckRaces { min_age } => stuck_races(operator, *min_age, out),
+    }
+}
+
+fn flush_stale_racer(operator: &Operator, doge_racer: &Pubkey, out: &mut dyn Write) -> Result<()> {
+    let rpc = operator.rpc;
+    let racer = fetch::<DogeRacerState>(rpc, doge_racer)?
+        .ok_or_else(|| anyhow!("Doge racer {} not found", doge_racer))?;
+
+    if racer.current_lobby_race.eq(&Pubkey::default()) {
+        bail!("Doge racer {} is not in a lobby", doge_racer);
+    }
+
+    let now = rpc.get_unix_timestamp()? as u64;
+    let waiting = now.saturating_sub(racer.last_joined_timestamp);
+    if waiting < STALE_RACERS_FLUSH_COOLDOWN {
+        bail!(
+            "Doge racer {} joined {}s ago and can be flushed after {}s",
+            doge_racer,
+            waiting,
+            STALE_RACERS_FLUSH_COOLDOWN
+        );
+    }
+
+    let lobby = operator.fetch_lobby(&racer.current_lobby_race)?;
+    let doge = fetch_doge_keys(rpc, &racer)?;
+    let doge_holder = fetch_doge_holder(rpc, &racer)?;
+
+    writeln!(
+        out,
+        "Flushing doge racer {} from lobby {}, waiting {}s",
+        doge_racer, racer.current_lobby_race, waiting
+    )?;
+
+    operator.submit(
+        &[instructions::flush_stale_racer(
+            &operator.authority(),
+            &doge,
+            &doge_holder,
+            &racer.doge_holder_entry_fee_token,
+            &lobby,
+        )],
+        out,
+    )
+}
+
+pub(crate) fn fetch_doge_keys(rpc: &dyn Rpc, racer: &DogeRacerState) -> Result<DogeKeys> {
+    let doge_stats = fetch::<DogeStats>(rpc, &racer.doge_o_pda)?
+        .ok_or_else(|| anyhow!("Doge stats {} not found", racer.doge_o_pda))?;
+
+    Ok(DogeKeys::new(
+        doge_stats.doge_mint,
+        doge_stats.init_authority,
+    ))
+}
+
+// The holder is whoever owns the token account the entry fee was paid from
+pub(crate) fn fetch_doge_holder(rpc: &dyn Rpc, racer: &DogeRacerState) -> Result<Pubkey> {
+    let entry_fee_token = rpc
+        .get_account(&racer.doge_holder_entry_fee_token)?
+        .ok_or_else(|| {
+            anyhow!(
+                "Entry fee token {} not found",
+                racer.doge_holder_entry_fee_token
+            )
+        })?;
+
+    Ok(spl_token::state::Account::unpack(&entry_fee_token.data)?.owner)
+}
+
+// Racers seated in `lobby` that have not been settled or flushed out of it yet
+pub(crate) fn fetch_unsettled_racers(
+    rpc: &dyn Rpc,
+    address: &Pubkey,
+    lobby: &LobbyState,
+) -> Result<Vec<(Pubkey, DogeRacerState)>> {
+    let mut unsettled = Vec::new();
+    for racer_address in lobby
+        .racers
+        .iter()
+        .filter(|racer| racer.ne(&&Pubkey::default()))
+    {
+        if let Some(racer) = fetch::<DogeRacerState>(rpc, racer_address)? {
+            if racer.current_lobby_race.eq(address) {
+                unsettled.push((*racer_address, racer));
+            }
+        }
+    }
+
+    Ok(unsettled)
+}
+
+// Seconds since the last unsettled racer joined, none once every racer has settled
+pub(crate) fn race_age(now: u64, unsettled: &[(Pubkey, DogeRacerState)]) -> Option<u64> {
+    unsettled
+        .iter()
+        .map(|(_, racer)| now.saturating_sub(racer.last_joined_timestamp))
+        .min()
+}
+
+fn filled_seats(lobby: &LobbyState) -> usize {
+    lobby
+        .racers
+        .iter()
+        .filter(|racer| racer.ne(&&Pubkey::default()))
+        .count()
+}
+
+fn list_lobbies(operator: &Operator, out: &mut dyn Write) -> Result<()> {
+    let lobbies = fetch_all::<LobbyState>(operator.rpc, &dogegamecontract::id())?;
+
+    for (address, lobby) in &lobbies {
+        writeln!(
+            out,
+            "{} \"{}\" track {} entry fee {} of {} racers {}/{} started {} races {}",
+            address,
+            lobby.lobby_data.name,
+            lobby.track_keys.track_mint,
+            lobby.lobby_data.entry_fee,
+            lobby.track_keys.entry_fee_mint,
+            filled_seats(lobby),
+            lobby.max_players,
+            lobby.race_started,
+            lobby.race_count
+        )?;
+    }
+    writeln!(out, "{} lobbies", lobbies.len())?;
+
+    Ok(())
+}
+
+fn list_racers(operator: &Operator, lobby: Option<&Pubkey>, out: &mut dyn Write) -> Result<()> {
+    let racers: Vec<_> = fetch_all::<DogeRacerState>(operator.rpc, &dogegamecontract::id())?
+        .into_iter()
+        .filter(|(_, racer)| match lobby {
+            Some(lobby) => racer.current_lobby_race.eq(lobby),
+            None => racer.current_lobby_race.ne(&Pubkey::default()),
+        })
+        .collect();
+
+    for (address, racer) in &racers {
+        writeln!(
+            out,
+            "{} lobby {} joined at {} wins {} losses {}",
+            address,
+            racer.current_lobby_race,
+            racer.last_joined_timestamp,
+            racer.total_wins,
+            racer.total_losses
+        )?;
+    }
+    writeln!(out, "{} racers", racers.len())?;
+
+    Ok(())
+}
+
+// A started race is stuck while some of its racers are still waiting on settlement
+fn stuck_races(operator: &Operator, min_age: u64, out: &mut dyn Write) -> Result<()> {
+    let rpc = operator.rpc;
+    let now = rpc.get_unix_timestamp()? as u64;
+    let mut stuck = 0;
+
+    for (address, lobby) in fetch_all::<LobbyState>(rpc, &dogegamecontract::id())? {
+        if !lobby.race_started {
+            continue;
+        }
+
+        let unsettled = fetch_unsettled_racers(rpc, &address, &lobby)?;
+        let age = match race_age(now, &unsettled) {
+            Some(age) if age >= min_age => age,
+            _ => continue,
+        };
+
+        stuck += 1;
+        writeln!(
+            out,
+            "{} race {} started, {}/{} racers unsettled for {}s",
+            address,
+            lobby.race_count,
+            unsettled.len(),
+            lobby.max_players,
+            age
+        )?;
+        for (racer, _) in unsettled {
+            writeln!(out, "  {}", racer)?;
+        }
+    }
+    writeln!(out, "{} stuck races", stuck)?;
+
+    Ok(())
+}
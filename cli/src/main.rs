@@ -0,0 +1,25 @@
+use std::io::stdout;
+
+use anyhow::{anyhow, Result};
+use clap::Parser;
+use dogetrack_cli::{run, Cli, Operator};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{commitment_config::CommitmentConfig, signature::read_keypair_file};
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    let rpc = RpcClient::new_with_commitment(cli.url.clone(), CommitmentConfig::confirmed());
+    let signer = read_keypair_file(&cli.keypair)
+        .map_err(|err| anyhow!("Failed to read keypair {}: {}", cli.keypair, err))?;
+
+    run(
+        &cli.command,
+        &Operator {
+            rpc: &rpc,
+            signer: &signer,
+            dry_run: cli.dry_run,
+        },
+        &mut stdout(),
+    )
+}
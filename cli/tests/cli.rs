@@ -0,0 +1,252 @@
+//! End to end runs of the CLI against the game program in an in-process bank.
+
+mod common;
+
+use anchor_spl::associated_token::get_associated_token_address;
+use anyhow::Result;
+use clap::Parser;
+use common::{fetch, token_balance, BankRpc, Impersonated, World, ENTRY_FEE};
+use dogetrack_cli::{run, Cli, Operator};
+use dogetrack_sdk::{
+    client::Rpc,
+    doge_o::get_authority,
+    dogegamecontract::{
+        self,
+        constants::{ENTRY_FEE_REQUIREMENTS_VERSION, SOL_NETWORK_FEE},
+        state::{
+            DogeRacerState, EntryFeeRequirements, EntryFeeRequirementsState, LobbyState, RaceState,
+        },
+        utils::get_wsol_mint,
+    },
+    pda,
+};
+use solana_sdk::pubkey::Pubkey;
+
+fn dogetrack<S: AsRef<str>>(rpc: &BankRpc, args: &[S]) -> Result<String> {
+    let cli = Cli::try_parse_from(
+        std::iter::once("dogetrack").chain(args.iter().map(|arg| arg.as_ref())),
+    )?;
+    let mut out = Vec::new();
+
+    run(
+        &cli.command,
+        &Operator {
+            rpc,
+            signer: &Impersonated(get_authority()),
+            dry_run: cli.dry_run,
+        },
+        &mut out,
+    )?;
+
+    Ok(String::from_utf8(out)?)
+}
+
+#[test]
+fn flush_stale_racer_refunds_the_racer_and_frees_the_seat() {
+    let mut world = World::new();
+    let racer = world.add_racer(1);
+    world.add_lobby(false, &[&racer]);
+    let lobby = world.lobby();
+    let entry_fee_mint = world.entry_fee_mint;
+    let rpc = world.start();
+
+    let output = dogetrack(
+        &rpc,
+        &[
+            "flush-stale-racer",
+            "--doge-racer",
+            &racer.doge_racer.to_string(),
+        ],
+    )
+    .unwrap();
+    assert!(output.contains("Signature"));
+
+    let holder = &racer.doge_holder;
+    assert_eq!(
+        token_balance(&rpc, &get_associated_token_address(holder, &entry_fee_mint)),
+        ENTRY_FEE
+    );
+    assert_eq!(
+        token_balance(
+            &rpc,
+            &get_associated_token_address(holder, &get_wsol_mint())
+        ),
+        SOL_NETWORK_FEE
+    );
+
+    let racer_state = fetch::<DogeRacerState>(&rpc, &racer.doge_racer).unwrap();
+    assert_eq!(racer_state.current_lobby_race, Pubkey::default());
+    assert_eq!(racer_state.last_joined_timestamp, 0);
+
+    let lobby_state = fetch::<LobbyState>(&rpc, &lobby).unwrap();
+    assert!(lobby_state
+        .racers
+        .iter()
+        .all(|racer| racer.eq(&Pubkey::default())));
+}
+
+#[test]
+fn dry_run_simulates_without_committing() {
+    let mut world = World::new();
+    let racer = world.add_racer(1);
+    world.add_lobby(false, &[&racer]);
+    let lobby = world.lobby();
+    let rpc = world.start();
+
+    let output = dogetrack(
+        &rpc,
+        &[
+            "--dry-run",
+            "flush-stale-racer",
+            "--doge-racer",
+            &racer.doge_racer.to_string(),
+        ],
+    )
+    .unwrap();
+    assert!(output.contains("Simulation succeeded"));
+
+    let racer_state = fetch::<DogeRacerState>(&rpc, &racer.doge_racer).unwrap();
+    assert_eq!(racer_state.current_lobby_race, lobby);
+}
+
+#[test]
+fn flush_stale_racer_waits_for_the_cooldown() {
+    let mut world = World::new();
+    let racer = world.add_racer(u32::MAX.into());
+    world.add_lobby(false, &[&racer]);
+    let rpc = world.start();
+
+    let error = dogetrack(
+        &rpc,
+        &[
+            "flush-stale-racer",
+            "--doge-racer",
+            &racer.doge_racer.to_string(),
+        ],
+    )
+    .unwrap_err();
+    assert!(error.to_string().contains("can be flushed after"));
+}
+
+#[test]
+fn lists_lobbies_racers_and_stuck_races() {
+    let mut world = World::new();
+    let first = world.add_racer(1);
+    let second = world.add_racer(1);
+    world.add_lobby(true, &[&first, &second]);
+    let lobby = world.lobby().to_string();
+    let rpc = world.start();
+
+    let lobbies = dogetrack(&rpc, &["list-lobbies"]).unwrap();
+    assert!(lobbies.contains(&lobby));
+    assert!(lobbies.contains("racers 2/2 started true"));
+
+    let racers = dogetrack(&rpc, &["list-racers", "--lobby", &lobby]).unwrap();
+    assert!(racers.contains(&first.doge_racer.to_string()));
+    assert!(racers.contains("2 racers"));
+
+    let stuck = dogetrack(&rpc, &["stuck-races"]).unwrap();
+    assert!(stuck.contains(&format!("{} race 0 started, 2/2 racers unsettled", lobby)));
+    assert!(stuck.contains("1 stuck races"));
+}
+
+fn entry_fee_requirements_args(command: &str, mint: &Pubkey, fees: [u64; 6]) -> Vec<String> {
+    let flags = [
+        "--min-fee",
+        "--max-class-1-fee",
+        "--max-class-2-fee",
+        "--max-class-3-fee",
+        "--max-class-4-fee",
+        "--max-class-5-fee",
+    ];
+
+    let mut args = vec![command.to_string(), "--mint".to_string(), mint.to_string()];
+    for (flag, fee) in flags.iter().zip(fees) {
+        args.push(flag.to_string());
+        args.push(fee.to_string());
+    }
+
+    args
+}
+
+// Creating accounts through CPIs needs the program built for BPF, so the record is seeded and
+// `init-entry-fee-requirements` is left to the dry runs against a cluster
+#[test]
+fn manages_entry_fee_requirements() {
+    let mut world = World::new();
+    let mint = world.entry_fee_mint;
+    let (address, bump) = pda::entry_fee_requirements_address(&mint);
+    world.add_anchor_account(
+        address,
+        dogegamecontract::id(),
+        &EntryFeeRequirementsState {
+            bump,
+            entry_fee_mint: mint,
+            entry_fee_requirements: EntryFeeRequirements {
+                min_fee: 100,
+                max_class_1_fee: 200,
+                max_class_2_fee: 300,
+                max_class_3_fee: 400,
+                max_class_4_fee: 500,
+                max_class_5_fee: 600,
+            },
+            version: ENTRY_FEE_REQUIREMENTS_VERSION,
+        },
+    );
+    let rpc = world.start();
+
+    dogetrack(
+        &rpc,
+        &entry_fee_requirements_args(
+            "update-entry-fee-requirements",
+            &mint,
+            [50, 200, 300, 400, 500, 1000],
+        ),
+    )
+    .unwrap();
+    let state = fetch::<EntryFeeRequirementsState>(&rpc, &address).unwrap();
+    assert_eq!(state.entry_fee_requirements.min_fee, 50);
+    assert_eq!(state.entry_fee_requirements.max_class_5_fee, 1000);
+
+    dogetrack(
+        &rpc,
+        &[
+            "admin-close-entry-fee-requirements",
+            "--mint",
+            &mint.to_string(),
+        ],
+    )
+    .unwrap();
+    assert!(rpc.get_account(&address).unwrap().is_none());
+}
+
+#[test]
+fn closes_race_records_and_doge_racers() {
+    let mut world = World::new();
+    let racer = world.add_racer(1);
+    let race_data = Pubkey::new_unique();
+    world.add_anchor_account(race_data, dogegamecontract::id(), &RaceState::default());
+    let rpc = world.start();
+
+    dogetrack(
+        &rpc,
+        &[
+            "admin-close-race-state",
+            "--race-data",
+            &race_data.to_string(),
+        ],
+    )
+    .unwrap();
+    assert!(rpc.get_account(&race_data).unwrap().is_none());
+
+    dogetrack(
+        &rpc,
+        &[
+            "admin-close-doge-racer",
+            "--doge-racer",
+            &racer.doge_racer.to_string(),
+        ],
+    )
+    .unwrap();
+    assert!(rpc.get_account(&racer.doge_racer).unwrap().is_none());
+}
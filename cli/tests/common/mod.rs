@@ -0,0 +1,527 @@
+//! In-process bank harness shared by the end to end tests.
+
+#![allow(dead_code)]
+
+use std::{sync::Once, time::Duration};
+
+use anchor_lang::{AccountDeserialize, AccountSerialize, AnchorSerialize};
+use anchor_spl::associated_token::get_associated_token_address;
+use anyhow::{bail, Result};
+use dogetrack_sdk::{
+    accounts::decode,
+    client::{Rpc, Simulation},
+    doge_o::{self, get_authority, DogeStats},
+    dogegamecontract::{
+        self,
+        constants::{LOBBY_STATE_VERSION, SOL_NETWORK_FEE},
+        state::{DogeRacerState, LobbyState},
+        utils::get_wsol_mint,
+    },
+    pda, DogeKeys,
+};
+use mpl_token_metadata::state::{Data, Key, Metadata};
+use solana_program_test::{processor, BanksClient, BanksClientError, ProgramTest};
+use solana_sdk::{
+    account::Account,
+    account_info::AccountInfo,
+    commitment_config::CommitmentLevel,
+    entrypoint::ProgramResult,
+    hash::Hash,
+    instruction::Instruction,
+    program_option::COption,
+    program_pack::Pack,
+    program_stubs::{set_syscall_stubs, SyscallStubs},
+    pubkey::Pubkey,
+    signature::Signature,
+    signer::{Signer, SignerError},
+    system_program,
+    transaction::{Transaction, TransactionError},
+};
+use spl_token::state::{Account as TokenAccount, AccountState, Mint};
+use tokio::runtime::Runtime;
+
+pub const ENTRY_FEE: u64 = 100;
+
+// Signs as the program authority without its key. Bank loopback sends skip signature checks
+pub struct Impersonated(pub Pubkey);
+
+impl Signer for Impersonated {
+    fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
+        Ok(self.0)
+    }
+
+    fn try_sign_message(&self, _: &[u8]) -> Result<Signature, SignerError> {
+        let bytes = [
+            Pubkey::new_unique().to_bytes(),
+            Pubkey::new_unique().to_bytes(),
+        ]
+        .concat();
+        Ok(Signature::new(&bytes))
+    }
+
+    fn is_interactive(&self) -> bool {
+        false
+    }
+}
+
+struct NoStubs;
+
+impl SyscallStubs for NoStubs {}
+
+// Natively loaded programs can only invoke a program whose account is among the passed account
+// infos, which anchor's token and system CPIs leave out. Passes the missing program along
+struct WithProgramAccounts {
+    inner: Box<dyn SyscallStubs>,
+    programs: Vec<(Pubkey, Account)>,
+}
+
+impl SyscallStubs for WithProgramAccounts {
+    fn sol_log(&self, message: &str) {
+        self.inner.sol_log(message)
+    }
+
+    fn sol_log_compute_units(&self) {
+        self.inner.sol_log_compute_units()
+    }
+
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        let program = self.programs.iter().find(|(address, _)| {
+            address.eq(&instruction.program_id)
+                && !account_infos.iter().any(|info| info.key.eq(address))
+        });
+        let (address, account) = match program {
+            Some(program) => program,
+            None => {
+                return self
+                    .inner
+                    .sol_invoke_signed(instruction, account_infos, signers_seeds)
+            }
+        };
+
+        // Test processes are short lived, leaking the copies keeps the lifetimes simple
+        let mut account_infos = account_infos.to_vec();
+        account_infos.push(AccountInfo::new(
+            Box::leak(Box::new(*address)),
+            false,
+            false,
+            Box::leak(Box::new(account.lamports)),
+            Box::leak(account.data.clone().into_boxed_slice()),
+            Box::leak(Box::new(account.owner)),
+            account.executable,
+            account.rent_epoch,
+        ));
+
+        self.inner
+            .sol_invoke_signed(instruction, &account_infos, signers_seeds)
+    }
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.inner.sol_get_clock_sysvar(var_addr)
+    }
+
+    fn sol_get_epoch_schedule_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.inner.sol_get_epoch_schedule_sysvar(var_addr)
+    }
+
+    fn sol_get_fees_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.inner.sol_get_fees_sysvar(var_addr)
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.inner.sol_get_rent_sysvar(var_addr)
+    }
+
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        self.inner.sol_get_return_data()
+    }
+
+    fn sol_set_return_data(&self, data: &[u8]) {
+        self.inner.sol_set_return_data(data)
+    }
+
+    fn sol_log_data(&self, fields: &[&[u8]]) {
+        self.inner.sol_log_data(fields)
+    }
+
+    fn sol_get_processed_sibling_instruction(&self, index: usize) -> Option<Instruction> {
+        self.inner.sol_get_processed_sibling_instruction(index)
+    }
+
+    fn sol_get_stack_height(&self) -> u64 {
+        self.inner.sol_get_stack_height()
+    }
+}
+
+pub struct BankRpc {
+    runtime: Runtime,
+    banks: BanksClient,
+    // Banks clients cannot scan program accounts, so scans cover the accounts the test added
+    known_accounts: Vec<Pubkey>,
+}
+
+impl Rpc for BankRpc {
+    fn get_account(&self, address: &Pubkey) -> Result<Option<Account>> {
+        // Loopback sends land in the working bank
+        let mut banks = self.banks.clone();
+        Ok(self
+            .runtime
+            .block_on(banks.get_account_with_commitment(*address, CommitmentLevel::Processed))?)
+    }
+
+    fn get_program_accounts(
+        &self,
+        program_id: &Pubkey,
+        prefix: &[u8],
+    ) -> Result<Vec<(Pubkey, Account)>> {
+        let mut accounts = Vec::new();
+        for address in &self.known_accounts {
+            if let Some(account) = self.get_account(address)? {
+                if account.owner.eq(program_id) && account.data.starts_with(prefix) {
+                    accounts.push((*address, account));
+                }
+            }
+        }
+
+        Ok(accounts)
+    }
+
+    fn get_latest_blockhash(&self) -> Result<Hash> {
+        let mut banks = self.banks.clone();
+        Ok(self.runtime.block_on(banks.get_latest_blockhash())?)
+    }
+
+    fn send_transaction(&self, transaction: &Transaction) -> Result<Signature> {
+        let signature = transaction.signatures[0];
+        let payer = transaction.message.account_keys[0];
+        let payer_lamports = |rpc: &Self| -> Result<Option<u64>> {
+            Ok(rpc.get_account(&payer)?.map(|account| account.lamports))
+        };
+        let paid_from = payer_lamports(self)?;
+
+        let mut banks = self.banks.clone();
+        self.runtime
+            .block_on(banks.send_transaction(transaction.clone()))?;
+
+        for _ in 0..500 {
+            let mut banks = self.banks.clone();
+            if let Some(status) = self
+                .runtime
+                .block_on(banks.get_transaction_status(signature))?
+            {
+                if let Some(err) = status.err {
+                    bail!("Transaction failed: {}", err);
+                }
+
+                // The status can show up a moment before the account writes, the fee leaving
+                // the payer marks them as landed
+                while payer_lamports(self)? == paid_from {
+                    std::thread::sleep(Duration::from_millis(1));
+                }
+                return Ok(signature);
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        bail!("Transaction {} was dropped", signature)
+    }
+
+    // Preflight simulates without checking signatures, then rejects the impersonated signature
+    // before anything is committed
+    fn simulate_transaction(&self, transaction: &Transaction) -> Result<Simulation> {
+        let mut banks = self.banks.clone();
+        let result = self
+            .runtime
+            .block_on(banks.process_transaction_with_preflight(transaction.clone()));
+
+        match result {
+            Err(BanksClientError::TransactionError(TransactionError::SignatureFailure)) => {
+                Ok(Simulation {
+                    error: None,
+                    logs: Vec::new(),
+                })
+            }
+            Err(BanksClientError::SimulationError { err, logs, .. }) => Ok(Simulation {
+                error: Some(err.to_string()),
+                logs,
+            }),
+            Err(err) => Err(err.into()),
+            Ok(()) => bail!("Simulated transaction was committed"),
+        }
+    }
+}
+
+pub struct World {
+    pub program_test: ProgramTest,
+    pub known_accounts: Vec<Pubkey>,
+    pub track_holder: Pubkey,
+    pub track_mint: Pubkey,
+    pub entry_fee_mint: Pubkey,
+}
+
+pub struct Racer {
+    pub doge: DogeKeys,
+    pub doge_racer: Pubkey,
+    pub doge_holder: Pubkey,
+}
+
+impl World {
+    pub fn new() -> Self {
+        let mut program_test = ProgramTest::new(
+            "dogegamecontract",
+            dogegamecontract::id(),
+            processor!(dogegamecontract::entry),
+        );
+        // The bundled token program crashes the interpreter here, run the native one instead
+        program_test.add_program(
+            "spl_token",
+            spl_token::id(),
+            processor!(spl_token::processor::Processor::process),
+        );
+        program_test.add_account(
+            get_authority(),
+            Account::new(100_000_000_000, 0, &solana_sdk::system_program::id()),
+        );
+
+        let mut world = Self {
+            program_test,
+            known_accounts: Vec::new(),
+            track_holder: Pubkey::new_unique(),
+            track_mint: Pubkey::new_unique(),
+            entry_fee_mint: Pubkey::new_unique(),
+        };
+        world.add_mint(get_wsol_mint(), 9, u64::MAX);
+        world.add_mint(world.track_mint, 0, 1);
+        world.add_mint(world.entry_fee_mint, 2, 1_000_000);
+
+        world
+    }
+
+    pub fn add_account(&mut self, address: Pubkey, owner: Pubkey, data: Vec<u8>) {
+        self.program_test.add_account(
+            address,
+            Account {
+                lamports: 1_000_000_000,
+                data,
+                owner,
+                ..Account::default()
+            },
+        );
+        self.known_accounts.push(address);
+    }
+
+    pub fn add_anchor_account<T: AccountSerialize>(
+        &mut self,
+        address: Pubkey,
+        owner: Pubkey,
+        state: &T,
+    ) {
+        let mut data = Vec::new();
+        state.try_serialize(&mut data).unwrap();
+        self.add_account(address, owner, data);
+    }
+
+    pub fn add_mint(&mut self, address: Pubkey, decimals: u8, supply: u64) {
+        let mut data = vec![0; Mint::LEN];
+        Mint::pack(
+            Mint {
+                mint_authority: COption::None,
+                supply,
+                decimals,
+                is_initialized: true,
+                freeze_authority: COption::None,
+            },
+            &mut data,
+        )
+        .unwrap();
+        self.add_account(address, spl_token::id(), data);
+    }
+
+    pub fn add_token_account(&mut self, address: Pubkey, mint: Pubkey, owner: Pubkey, amount: u64) {
+        let mut data = vec![0; TokenAccount::LEN];
+        TokenAccount::pack(
+            TokenAccount {
+                mint,
+                owner,
+                amount,
+                state: AccountState::Initialized,
+                ..TokenAccount::default()
+            },
+            &mut data,
+        )
+        .unwrap();
+        self.add_account(address, spl_token::id(), data);
+    }
+
+    pub fn lobby(&self) -> Pubkey {
+        pda::lobby_address(&self.track_holder, &self.track_mint).0
+    }
+
+    // A lobby with a seat per racer, two at least, holding the entry fees of `racers`
+    pub fn add_lobby(&mut self, race_started: bool, racers: &[&Racer]) {
+        let seats = racers.len().max(2);
+        let (lobby, bump) = pda::lobby_address(&self.track_holder, &self.track_mint);
+        let lobby_entry_fee_token = Pubkey::new_unique();
+        let lobby_wsol_token = Pubkey::new_unique();
+        self.add_token_account(
+            lobby_entry_fee_token,
+            self.entry_fee_mint,
+            lobby,
+            ENTRY_FEE * racers.len() as u64,
+        );
+        self.add_token_account(
+            lobby_wsol_token,
+            get_wsol_mint(),
+            lobby,
+            SOL_NETWORK_FEE * racers.len() as u64,
+        );
+
+        let mut lobby_state = LobbyState {
+            bump,
+            max_players: seats as u8,
+            race_started,
+            version: LOBBY_STATE_VERSION,
+            racers: vec![Pubkey::default(); seats],
+            ..LobbyState::default()
+        };
+        for (seat, racer) in racers.iter().enumerate() {
+            lobby_state.racers[seat] = racer.doge_racer;
+        }
+        lobby_state.lobby_data.entry_fee = ENTRY_FEE;
+        lobby_state.lobby_data.name = "Doge Downs".to_string();
+        lobby_state.track_keys.track_mint = self.track_mint;
+        lobby_state.track_keys.entry_fee_mint = self.entry_fee_mint;
+        lobby_state.track_keys.track_holder = self.track_holder;
+        lobby_state.track_keys.lobby_entry_fee_token = lobby_entry_fee_token;
+        lobby_state.track_keys.lobby_wsol_token = lobby_wsol_token;
+
+        self.add_anchor_account(lobby, dogegamecontract::id(), &lobby_state);
+    }
+
+    // A registered doge sitting in the lobby since `joined_at`
+    pub fn add_racer(&mut self, joined_at: u64) -> Racer {
+        let lobby = self.lobby();
+        self.add_racer_in(lobby, joined_at)
+    }
+
+    // A registered doge that already settled its race and left the lobby
+    pub fn add_settled_racer(&mut self) -> Racer {
+        self.add_racer_in(Pubkey::default(), 0)
+    }
+
+    fn add_racer_in(&mut self, current_lobby_race: Pubkey, joined_at: u64) -> Racer {
+        let doge = DogeKeys::new(Pubkey::new_unique(), Pubkey::new_unique());
+        let doge_holder = Pubkey::new_unique();
+        let (doge_racer, bump) = pda::doge_racer_address(&doge.doge_mint, &doge.doge_stats());
+
+        self.add_mint(doge.doge_mint, 0, 1);
+        self.add_account(
+            doge.doge_metadata(),
+            mpl_token_metadata::id(),
+            Metadata {
+                key: Key::MetadataV1,
+                update_authority: doge.init_authority,
+                mint: doge.doge_mint,
+                data: Data {
+                    name: "Doge".to_string(),
+                    symbol: String::new(),
+                    uri: String::new(),
+                    seller_fee_basis_points: 0,
+                    creators: None,
+                },
+                primary_sale_happened: false,
+                is_mutable: true,
+                edition_nonce: None,
+                token_standard: None,
+                collection: None,
+                uses: None,
+            }
+            .try_to_vec()
+            .unwrap(),
+        );
+        self.add_anchor_account(
+            doge.doge_stats(),
+            doge_o::id(),
+            &DogeStats {
+                doge_mint: doge.doge_mint,
+                doge_metadata: doge.doge_metadata(),
+                init_authority: doge.init_authority,
+                ..DogeStats::default()
+            },
+        );
+
+        let doge_holder_entry_fee_token =
+            get_associated_token_address(&doge_holder, &self.entry_fee_mint);
+        self.add_token_account(
+            doge_holder_entry_fee_token,
+            self.entry_fee_mint,
+            doge_holder,
+            0,
+        );
+        self.add_token_account(
+            get_associated_token_address(&doge_holder, &get_wsol_mint()),
+            get_wsol_mint(),
+            doge_holder,
+            0,
+        );
+
+        self.add_anchor_account(
+            doge_racer,
+            dogegamecontract::id(),
+            &DogeRacerState {
+                bump,
+                doge_o_pda: doge.doge_stats(),
+                current_lobby_race,
+                last_joined_timestamp: joined_at,
+                doge_holder_entry_fee_token,
+                ..DogeRacerState::default()
+            },
+        );
+
+        Racer {
+            doge,
+            doge_racer,
+            doge_holder,
+        }
+    }
+
+    pub fn start(self) -> BankRpc {
+        let runtime = Runtime::new().unwrap();
+        let (mut banks, _, _) = runtime.block_on(self.program_test.start());
+
+        // Program test installs its stubs on the first start, wrap them once they are in place
+        static WRAP_STUBS: Once = Once::new();
+        WRAP_STUBS.call_once(|| {
+            let programs = [system_program::id(), spl_token::id()]
+                .iter()
+                .map(|address| {
+                    let account = runtime.block_on(banks.get_account(*address));
+                    (*address, account.unwrap().unwrap())
+                })
+                .collect();
+            let inner = set_syscall_stubs(Box::new(NoStubs));
+            set_syscall_stubs(Box::new(WithProgramAccounts { inner, programs }));
+        });
+
+        BankRpc {
+            runtime,
+            banks,
+            known_accounts: self.known_accounts,
+        }
+    }
+}
+
+pub fn token_balance(rpc: &BankRpc, address: &Pubkey) -> u64 {
+    let account = rpc.get_account(address).unwrap().unwrap();
+    TokenAccount::unpack(&account.data).unwrap().amount
+}
+
+pub fn fetch<T: AccountDeserialize>(rpc: &BankRpc, address: &Pubkey) -> Option<T> {
+    rpc.get_account(address)
+        .unwrap()
+        .map(|account| decode::<T>(&account.data).unwrap())
+}
@@ -0,0 +1,344 @@
+//! Keeper passes against the game program in an in-process bank.
+
+mod common;
+
+use std::{cell::RefCell, collections::HashMap};
+
+use anchor_lang::{AnchorDeserialize, InstructionData};
+use anchor_spl::associated_token::get_associated_token_address;
+use anyhow::Result;
+use common::{fetch, token_balance, BankRpc, Impersonated, World, ENTRY_FEE};
+use dogetrack_cli::{
+    keeper::{Keeper, RaceOutcome, RaceResults, Report},
+    Operator,
+};
+use dogetrack_sdk::{
+    client::{Rpc, Simulation},
+    doge_o::get_authority,
+    dogegamecontract::{
+        self,
+        instruction::{ConcludeRace, RecordRace, RecordRacerResult},
+        state::{LobbyState, RaceResult, RaceState},
+    },
+    pda,
+};
+use solana_sdk::{
+    account::Account, hash::Hash, pubkey::Pubkey, signature::Signature, transaction::Transaction,
+};
+
+// Records the keeper's transactions instead of sending them. Settlement creates accounts through
+// CPIs, which the natively loaded program can't do
+struct Recorder<'a> {
+    bank: &'a BankRpc,
+    sent: RefCell<Vec<Transaction>>,
+}
+
+impl Rpc for Recorder<'_> {
+    fn get_account(&self, address: &Pubkey) -> Result<Option<Account>> {
+        self.bank.get_account(address)
+    }
+
+    fn get_program_accounts(
+        &self,
+        program_id: &Pubkey,
+        prefix: &[u8],
+    ) -> Result<Vec<(Pubkey, Account)>> {
+        self.bank.get_program_accounts(program_id, prefix)
+    }
+
+    fn get_latest_blockhash(&self) -> Result<Hash> {
+        self.bank.get_latest_blockhash()
+    }
+
+    fn send_transaction(&self, transaction: &Transaction) -> Result<Signature> {
+        self.sent.borrow_mut().push(transaction.clone());
+        Ok(transaction.signatures[0])
+    }
+
+    fn simulate_transaction(&self, transaction: &Transaction) -> Result<Simulation> {
+        self.bank.simulate_transaction(transaction)
+    }
+}
+
+fn keeper_pass(rpc: &dyn Rpc, results: &dyn RaceResults, retention: u64) -> (Report, String) {
+    let keeper = Keeper {
+        operator: Operator {
+            rpc,
+            signer: &Impersonated(get_authority()),
+            dry_run: false,
+        },
+        results,
+        retention,
+        stuck_after: 60,
+    };
+    let mut out = Vec::new();
+    let report = keeper.tick(&mut out).unwrap();
+
+    (report, String::from_utf8(out).unwrap())
+}
+
+fn no_results() -> HashMap<(Pubkey, u64), RaceOutcome> {
+    HashMap::new()
+}
+
+#[test]
+fn flushes_stale_racers_and_closes_expired_race_records() {
+    let mut world = World::new();
+    let racer = world.add_racer(1);
+    world.add_lobby(false, &[&racer]);
+    let lobby = world.lobby();
+    let entry_fee_mint = world.entry_fee_mint;
+
+    let expired = Pubkey::new_unique();
+    let recent = Pubkey::new_unique();
+    for (address, settled_at) in [(expired, 1), (recent, u32::MAX as u64)] {
+        world.add_anchor_account(
+            address,
+            dogegamecontract::id(),
+            &RaceState {
+                lobby_account: lobby,
+                settled_at,
+                ..RaceState::default()
+            },
+        );
+    }
+    let rpc = world.start();
+
+    let (report, output) = keeper_pass(&rpc, &no_results(), 60);
+    assert_eq!(
+        report,
+        Report {
+            flushed: 1,
+            closed: 1,
+            ..Report::default()
+        },
+        "{}",
+        output
+    );
+
+    assert_eq!(
+        token_balance(
+            &rpc,
+            &get_associated_token_address(&racer.doge_holder, &entry_fee_mint)
+        ),
+        ENTRY_FEE
+    );
+    let lobby_state = fetch::<LobbyState>(&rpc, &lobby).unwrap();
+    assert!(lobby_state
+        .racers
+        .iter()
+        .all(|seat| seat.eq(&Pubkey::default())));
+    assert!(rpc.get_account(&expired).unwrap().is_none());
+    assert!(rpc.get_account(&recent).unwrap().is_some());
+
+    // Nothing is left to do on the next pass
+    assert_eq!(keeper_pass(&rpc, &no_results(), 60).0, Report::default());
+}
+
+#[test]
+fn reports_started_races_without_results() {
+    let mut world = World::new();
+    let first = world.add_racer(1);
+    let second = world.add_racer(1);
+    world.add_lobby(true, &[&first, &second]);
+    let lobby = world.lobby();
+    let rpc = world.start();
+
+    let (report, output) = keeper_pass(&rpc, &no_results(), 60);
+    assert_eq!(
+        report,
+        Report {
+            stuck: 1,
+            ..Report::default()
+        }
+    );
+    assert!(output.contains(&format!("{} race 0 started, 2/2 racers unsettled", lobby)));
+}
+
+#[test]
+fn settles_races_once_results_are_published() {
+    let mut world = World::new();
+    let loser = world.add_racer(1);
+    let winner = world.add_racer(1);
+    world.add_lobby(true, &[&loser, &winner]);
+    let lobby = world.lobby();
+    let bank = world.start();
+
+    let mut results = no_results();
+    results.insert(
+        (lobby, 0),
+        RaceOutcome {
+            race_started: 1_650_000_000,
+            finishing_order: vec![winner.doge_racer, loser.doge_racer],
+            winning_time: 61_250,
+        },
+    );
+    let rpc = Recorder {
+        bank: &bank,
+        sent: RefCell::new(Vec::new()),
+    };
+
+    let (report, output) = keeper_pass(&rpc, &results, 60);
+    assert_eq!(
+        report,
+        Report {
+            settled: 1,
+            ..Report::default()
+        },
+        "{}",
+        output
+    );
+
+    // The race is recorded once, then every racer is recorded and settled in finishing order,
+    // whichever seat they sit in
+    let sent = rpc.sent.borrow();
+    assert_eq!(sent.len(), 5);
+    let instruction_account = |transaction: &Transaction, index: usize| {
+        let instruction = &transaction.message.instructions[0];
+        transaction.message.account_keys[instruction.accounts[index] as usize]
+    };
+
+    let record = &sent[0].message.instructions[0];
+    let data = RecordRace::try_from_slice(&record.data[8..]).unwrap();
+    assert_eq!(data.race_started, 1_650_000_000);
+    assert_eq!(data.winning_time, 61_250);
+    let finishing_order: Vec<Pubkey> = (9..record.accounts.len())
+        .step_by(2)
+        .map(|index| instruction_account(&sent[0], index))
+        .collect();
+    assert_eq!(finishing_order, vec![winner.doge_racer, loser.doge_racer]);
+
+    let settled: Vec<Pubkey> = sent[1..]
+        .chunks(2)
+        .map(|transactions| {
+            let (result, conclude) = (&transactions[0], &transactions[1]);
+            RecordRacerResult::try_from_slice(&result.message.instructions[0].data[8..]).unwrap();
+            ConcludeRace::try_from_slice(&conclude.message.instructions[0].data[8..]).unwrap();
+            assert_eq!(
+                instruction_account(result, 1),
+                instruction_account(conclude, 2)
+            );
+
+            instruction_account(conclude, 2)
+        })
+        .collect();
+    assert_eq!(settled, vec![winner.doge_racer, loser.doge_racer]);
+}
+
+// The doge racer a record racer result or conclude race transaction settles
+fn settled_racer(transaction: &Transaction) -> Pubkey {
+    let instruction = &transaction.message.instructions[0];
+    let index = if instruction.data == (RecordRacerResult {}).data() {
+        1
+    } else {
+        2
+    };
+    transaction.message.account_keys[instruction.accounts[index] as usize]
+}
+
+fn is_conclude_race(transaction: &Transaction) -> bool {
+    transaction.message.instructions[0].data == (ConcludeRace {}).data()
+}
+
+#[test]
+fn settles_racers_in_finishing_order() {
+    let mut world = World::new();
+    let first = world.add_racer(1);
+    let second = world.add_racer(1);
+    let third = world.add_racer(1);
+    world.add_lobby(true, &[&first, &second, &third]);
+    let lobby = world.lobby();
+    let bank = world.start();
+
+    let finishing_order = vec![second.doge_racer, third.doge_racer, first.doge_racer];
+    let mut results = no_results();
+    results.insert(
+        (lobby, 0),
+        RaceOutcome {
+            race_started: 1_650_000_000,
+            finishing_order: finishing_order.clone(),
+            winning_time: 61_250,
+        },
+    );
+    let rpc = Recorder {
+        bank: &bank,
+        sent: RefCell::new(Vec::new()),
+    };
+
+    let (report, output) = keeper_pass(&rpc, &results, 60);
+    assert_eq!(report.settled, 1, "{}", output);
+
+    let sent = rpc.sent.borrow();
+    assert_eq!(sent.len(), 7);
+    let concluded: Vec<Pubkey> = sent
+        .iter()
+        .filter(|transaction| is_conclude_race(transaction))
+        .map(settled_racer)
+        .collect();
+    assert_eq!(concluded, finishing_order);
+}
+
+#[test]
+fn resumes_a_partially_settled_race() {
+    let mut world = World::new();
+    let winner = world.add_settled_racer();
+    let second = world.add_racer(1);
+    let third = world.add_racer(1);
+    world.add_lobby(true, &[&third, &second, &winner]);
+    let lobby = world.lobby();
+
+    // An earlier pass recorded the race, settled the winner and recorded the second racer
+    let result = |racer: &Pubkey, recorded: bool, settled: bool| RaceResult {
+        doge_racer: *racer,
+        recorded,
+        settled,
+        ..RaceResult::default()
+    };
+    world.add_anchor_account(
+        pda::race_data_address(&lobby, 0).0,
+        dogegamecontract::id(),
+        &RaceState {
+            lobby_account: lobby,
+            settled_at: u32::MAX.into(),
+            results: vec![
+                result(&winner.doge_racer, true, true),
+                result(&second.doge_racer, true, false),
+                result(&third.doge_racer, false, false),
+            ],
+            ..RaceState::default()
+        },
+    );
+    let bank = world.start();
+
+    let mut results = no_results();
+    results.insert(
+        (lobby, 0),
+        RaceOutcome {
+            race_started: 1_650_000_000,
+            finishing_order: vec![winner.doge_racer, second.doge_racer, third.doge_racer],
+            winning_time: 61_250,
+        },
+    );
+    let rpc = Recorder {
+        bank: &bank,
+        sent: RefCell::new(Vec::new()),
+    };
+
+    let (report, output) = keeper_pass(&rpc, &results, 60);
+    assert_eq!(report.settled, 1, "{}", output);
+
+    // The race isn't recorded again and the second racer's result isn't either
+    let sent = rpc.sent.borrow();
+    let steps: Vec<(bool, Pubkey)> = sent
+        .iter()
+        .map(|transaction| (is_conclude_race(transaction), settled_racer(transaction)))
+        .collect();
+    assert_eq!(
+        steps,
+        vec![
+            (true, second.doge_racer),
+            (false, third.doge_racer),
+            (true, third.doge_racer),
+        ]
+    );
+}
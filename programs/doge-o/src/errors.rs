@@ -22,4 +22,40 @@ pub enum DogeError {
 
     #[msg("DogeO in maintenance")]
     MaintenanceMode,
+
+    #[msg("Invalid upgrade pricing tiers")]
+    InvalidUpgradePricing,
+
+    #[msg("Invalid doges supplied for rating update")]
+    InvalidRatingUpdate,
+
+    #[msg("Invalid placement for experience award")]
+    InvalidExperienceAward,
+
+    #[msg("Respec must keep the total stat points")]
+    InvalidRespec,
+
+    #[msg("Doge respec is on cooldown")]
+    RespecCooldown,
+
+    #[msg("Invalid stat roll")]
+    InvalidStatRoll,
+
+    #[msg("Invalid upgrade limits")]
+    InvalidUpgradeLimits,
+
+    #[msg("Doge upgrade is on cooldown")]
+    UpgradeCooldown,
+
+    #[msg("Doge upgrade exceeds the points allowed this period")]
+    UpgradePeriodCapExceeded,
+
+    #[msg("Doge cannot be changed while in an active lobby")]
+    DogeRacing,
+
+    #[msg("Invalid doge racer account")]
+    InvalidDogeRacer,
+
+    #[msg("Slot hash for the stat roll is no longer available")]
+    StatRollExpired,
 }
@@ -0,0 +1,100 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::DogeError;
+use crate::rarity::Rarity;
+use crate::stats::MAX_STAT;
+
+pub const MAX_LEVEL: u8 = 20;
+
+// Every level raises the per stat cap by this many points
+pub const STAT_CAP_PER_LEVEL: u8 = 5;
+
+pub const MAX_LEVELED_STAT: u8 = MAX_STAT + MAX_LEVEL * STAT_CAP_PER_LEVEL;
+
+// Reaching level `n` takes LEVEL_XP_STEP * (1 + 2 + .. + n) experience
+pub const LEVEL_XP_STEP: u64 = 100;
+
+pub const BASE_RACE_XP: u64 = 10;
+
+pub const XP_PER_OPPONENT_BEATEN: u64 = 20;
+
+pub fn level_for_experience(experience: u64) -> u8 {
+    let mut level = 0u8;
+    let mut required_experience = 0u64;
+
+    while level < MAX_LEVEL {
+        required_experience =
+            required_experience.saturating_add(LEVEL_XP_STEP.saturating_mul((level + 1).into()));
+
+        if experience < required_experience {
+            break;
+        }
+        level += 1;
+    }
+
+    level
+}
+
+// Per stat cap of a doge, its rarity ceiling raised by every level gained. Doges without a
+// recorded rarity keep the MAX_STAT ceiling they had before rarities
+pub fn stat_cap(level: u8, rarity: Option<Rarity>) -> u8 {
+    let ceiling = rarity.map_or(MAX_STAT, |rarity| rarity.stat_ceiling());
+    ceiling + level.min(MAX_LEVEL) * STAT_CAP_PER_LEVEL
+}
+
+// Experience for a finish at `placement` (0 is the winner). Beating more doges earns more,
+// scaled up by the lobby's class, which sets the entry fee bounds of the lobby
+pub fn race_experience(placement: u8, total_racers: u8, lobby_class: u8) -> Result<u64> {
+    require!(placement < total_racers, DogeError::InvalidExperienceAward);
+
+    let opponents_beaten = (total_racers - placement - 1) as u64;
+
+    XP_PER_OPPONENT_BEATEN
+        .checked_mul(opponents_beaten)
+        .and_then(|experience| experience.checked_add(BASE_RACE_XP))
+        .and_then(|experience| experience.checked_mul(1 + lobby_class as u64))
+        .ok_or_else(|| error!(DogeError::InvalidExperienceAward))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_take_a_growing_amount_of_experience() {
+        assert_eq!(level_for_experience(0), 0);
+        assert_eq!(level_for_experience(99), 0);
+        assert_eq!(level_for_experience(100), 1);
+        assert_eq!(level_for_experience(299), 1);
+        assert_eq!(level_for_experience(300), 2);
+        assert_eq!(level_for_experience(600), 3);
+    }
+
+    #[test]
+    fn levels_stop_at_the_max_level() {
+        // LEVEL_XP_STEP * (1 + 2 + .. + 20)
+        let max_level_experience = LEVEL_XP_STEP * 210;
+
+        assert_eq!(
+            level_for_experience(max_level_experience - 1),
+            MAX_LEVEL - 1
+        );
+        assert_eq!(level_for_experience(max_level_experience), MAX_LEVEL);
+        assert_eq!(level_for_experience(u64::MAX), MAX_LEVEL);
+    }
+
+    #[test]
+    fn stat_caps_rise_with_levels() {
+        assert_eq!(stat_cap(0, None), MAX_STAT);
+        assert_eq!(stat_cap(2, None), MAX_STAT + 2 * STAT_CAP_PER_LEVEL);
+        assert_eq!(stat_cap(u8::MAX, None), MAX_LEVELED_STAT);
+    }
+
+    #[test]
+    fn race_experience_rewards_beaten_opponents_and_the_lobby_class() {
+        assert_eq!(race_experience(0, 5, 1).unwrap(), (10 + 4 * 20) * 2);
+        assert_eq!(race_experience(4, 5, 1).unwrap(), 10 * 2);
+        assert_eq!(race_experience(0, 5, 5).unwrap(), (10 + 4 * 20) * 6);
+        assert!(race_experience(5, 5, 1).is_err());
+    }
+}
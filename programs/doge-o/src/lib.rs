@@ -1,14 +1,30 @@
 use std::str::FromStr;
 
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hash;
+use anchor_lang::solana_program::program::{invoke, set_return_data};
+use anchor_lang::solana_program::system_instruction;
+use anchor_lang::Discriminator;
 use anchor_spl::token::{self, Token};
 
 pub mod errors;
+pub mod level;
+pub mod limits;
 pub mod metadata;
+pub mod pricing;
+pub mod rarity;
+pub mod rating;
+pub mod roll;
 pub mod stats;
 
 use crate::errors::*;
+use crate::level::*;
+use crate::limits::*;
 use crate::metadata::Metadata;
+use crate::pricing::*;
+use crate::rarity::*;
+use crate::rating::*;
+use crate::roll::*;
 use crate::stats::*;
 use anchor_spl::token::Burn;
 use anchor_spl::token::{Mint, TokenAccount};
@@ -24,7 +40,30 @@ pub const AUTHORITY: &str = "4YH9irnaCnm12PvSbsssYLxkPGuLS2Ho3q3aehm847xe";
 
 pub const MAINTENANCE_MODE: bool = false;
 
-pub const DOGE_STATS_SIZE: usize = 8 + 1 + 1 + 1 + 1 + 1 + 32 + 32 + 32;
+pub const DOGE_STATS_VERSION: u8 = 1;
+
+// Zeroed space left after the current layout so new fields don't need a realloc
+pub const DOGE_STATS_RESERVED_SIZE: usize = 46;
+
+pub const DOGE_STATS_SIZE: usize = 8 + // discriminator
+    1 + // agility
+    1 + // speed
+    1 + // endurance
+    1 + // doge_bump
+    1 + // win_percentage
+    32 + // doge_mint
+    32 + // init_authority
+    32 + // doge_metadata
+    2 + // win_percentage_bps
+    2 + // rating
+    8 + // experience
+    1 + // level
+    8 + // last_respec_at
+    1 + // version
+    8 + // last_upgrade_at
+    8 + // upgrade_period_started_at
+    2 + // upgrade_period_points
+    DOGE_STATS_RESERVED_SIZE;
 
 pub fn get_dtrk() -> Pubkey {
     Pubkey::from_str(DTRK_MINT_ADDRESS).unwrap()
@@ -46,7 +85,12 @@ pub mod doge_o {
     pub fn init_stats(ctx: Context<InitStats>, stats: Stats) -> Result<()> {
         ctx.accounts.validate_doge_metadata()?;
 
-        require!(stats.validate(), DogeError::InvalidStats);
+        // Doges without a recorded rarity keep the MAX_STAT ceiling, like on upgrades
+        let rarity = load_doge_rarity(&ctx.accounts.doge_rarity)?;
+        require!(
+            stats.validate_with_cap(stat_cap(0, rarity)),
+            DogeError::InvalidStats
+        );
 
         msg!(
             "Initializing Doge Stats for mint {}",
@@ -57,16 +101,108 @@ pub mod doge_o {
         msg!("Speed: {}", stats.speed);
         msg!("Agility: {}", stats.agility);
 
-        let doge_stats = &mut ctx.accounts.doge_stats;
+        let doge_mint = ctx.accounts.doge_mint.key();
+        let doge_metadata = ctx.accounts.doge_metadata.key();
+        let init_authority = ctx.accounts.init_authority.key();
+
+        ctx.accounts.doge_stats.initialize(
+            &stats,
+            *ctx.bumps.get("doge_stats").unwrap(),
+            doge_mint,
+            doge_metadata,
+            init_authority,
+        );
+
+        Ok(())
+    }
+
+    pub fn init_doge_rarity(ctx: Context<InitDogeRarity>, rarity: Rarity) -> Result<()> {
+        let doge_rarity = &mut ctx.accounts.doge_rarity;
+
+        doge_rarity.bump = *ctx.bumps.get("doge_rarity").unwrap();
+        doge_rarity.doge_mint = ctx.accounts.doge_mint.key();
+        doge_rarity.rarity = rarity;
+
+        msg!(
+            "Doge {} rarity {:?}",
+            doge_rarity.doge_mint.to_string(),
+            rarity
+        );
+        Ok(())
+    }
 
-        doge_stats.agility = stats.agility;
-        doge_stats.endurance = stats.endurance;
-        doge_stats.speed = stats.speed;
-        doge_stats.win_percentage = 0;
-        doge_stats.doge_bump = *ctx.bumps.get("doge_stats").unwrap();
-        doge_stats.doge_mint = ctx.accounts.doge_mint.key();
-        doge_stats.doge_metadata = ctx.accounts.doge_metadata.key();
-        doge_stats.init_authority = ctx.accounts.init_authority.key();
+    pub fn update_doge_rarity(ctx: Context<UpdateDogeRarity>, rarity: Rarity) -> Result<()> {
+        ctx.accounts.doge_rarity.rarity = rarity;
+
+        msg!(
+            "Doge {} rarity {:?}",
+            ctx.accounts.doge_rarity.doge_mint.to_string(),
+            rarity
+        );
+        Ok(())
+    }
+
+    // First half of a stat roll. The seed stays secret until the next slot hash exists
+    pub fn commit_stat_roll(ctx: Context<CommitStatRoll>, seed_hash: [u8; 32]) -> Result<()> {
+        let stat_roll = &mut ctx.accounts.stat_roll;
+
+        stat_roll.bump = *ctx.bumps.get("stat_roll").unwrap();
+        stat_roll.doge_mint = ctx.accounts.doge_mint.key();
+        stat_roll.seed_hash = seed_hash;
+        stat_roll.commit_slot = Clock::get()?.slot;
+        stat_roll.revealed = false;
+
+        msg!("Stat roll committed at slot {}", stat_roll.commit_slot);
+        Ok(())
+    }
+
+    // Initializes doge stats from the committed seed, the hash of the first slot after the
+    // commit and the doge mint
+    pub fn roll_stats(ctx: Context<RollStats>, seed: [u8; 32]) -> Result<()> {
+        validate_doge_metadata(&ctx.accounts.doge_mint.key(), &ctx.accounts.doge_metadata)?;
+
+        let stat_roll = &mut ctx.accounts.stat_roll;
+        require!(
+            hash(&seed).to_bytes().eq(&stat_roll.seed_hash),
+            DogeError::InvalidStatRoll
+        );
+
+        let (roll_slot, slot_hash) =
+            roll_slot_hash(&ctx.accounts.slot_hashes, stat_roll.commit_slot)?;
+
+        let doge_mint = ctx.accounts.doge_mint.key();
+        let rarity = ctx.accounts.doge_rarity.rarity;
+        let stats = derive_stats(&seed, &slot_hash, &doge_mint, rarity);
+        require!(
+            stats.validate_with_cap(rarity.stat_ceiling()),
+            DogeError::InvalidStats
+        );
+
+        stat_roll.revealed = true;
+        stat_roll.seed = seed;
+        stat_roll.roll_slot = roll_slot;
+        stat_roll.slot_hash = slot_hash;
+
+        msg!(
+            "Rolled {:?} doge {} at slot {}",
+            rarity,
+            doge_mint.to_string(),
+            roll_slot
+        );
+        msg!("Endurance: {}", stats.endurance);
+        msg!("Speed: {}", stats.speed);
+        msg!("Agility: {}", stats.agility);
+
+        let doge_metadata = ctx.accounts.doge_metadata.key();
+        let init_authority = ctx.accounts.init_authority.key();
+
+        ctx.accounts.doge_stats.initialize(
+            &stats,
+            *ctx.bumps.get("doge_stats").unwrap(),
+            doge_mint,
+            doge_metadata,
+            init_authority,
+        );
 
         Ok(())
     }
@@ -87,50 +223,383 @@ pub mod doge_o {
             old_stats.agility
         );
 
-        // Validating stats between 1 - 100
-        require!(new_stats.validate(), DogeError::InvalidUpdateStats);
+        // Validating raised stats between 1 and the doge's level cap
+        let rarity = load_doge_rarity(&ctx.accounts.doge_rarity)?;
+        require!(
+            old_stats.validate_raised_with_cap(
+                &new_stats,
+                stat_cap(ctx.accounts.doge_stats.level, rarity)
+            ),
+            DogeError::InvalidUpdateStats
+        );
+
+        ctx.accounts.check_doge_not_racing()?;
+
+        // Cooldown and per period cap
+        let gained_points = old_stats.gained_points(&new_stats);
+        if gained_points > 0 {
+            let current_timestamp = Clock::get()?.unix_timestamp as u64;
+            let upgrade_limits = &ctx.accounts.upgrade_limits;
+            ctx.accounts.doge_stats.record_upgrade(
+                gained_points,
+                current_timestamp,
+                upgrade_limits,
+            )?;
+        }
 
         // Cross checking costs
-        let fees = old_stats.calculate_cost(&new_stats)?;
-        let fees_dtrk = 10u64
-            .checked_pow((ctx.accounts.dtrk_mint.decimals) as u32)
-            .and_then(|res| res.checked_mul(fees))
+        let fees_dtrk = old_stats.calculate_cost(&new_stats, &ctx.accounts.upgrade_pricing)?;
+        let fees = fees_dtrk
+            .checked_div(10u64.pow((ctx.accounts.dtrk_mint.decimals) as u32))
             .ok_or(DogeError::InvalidUpdateStats)?;
 
         let token_balance = ctx.accounts.dtrk_token.amount;
         require!(token_balance >= fees_dtrk, DogeError::InsufficientTokens);
 
-        if fees > 0 {
-            msg!("Required DTRK for upgrade {}", fees);
+        // Free pricing tiers skip the burn, the upgrade still applies
+        if fees_dtrk > 0 {
+            msg!(
+                "Required DTRK for upgrade {} ({} base units)",
+                fees,
+                fees_dtrk
+            );
             token::burn(ctx.accounts.token_burn_context(), fees_dtrk)?;
+        }
+
+        msg!(
+            "Upgrading for doge {}",
+            ctx.accounts.doge_mint.key().to_string()
+        );
+
+        grow_doge_stats(
+            &mut ctx.accounts.doge_stats,
+            &ctx.accounts.doge_holder.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+        let doge_stats = &mut ctx.accounts.doge_stats;
+
+        doge_stats.endurance = new_stats.endurance;
+        doge_stats.speed = new_stats.speed;
+        doge_stats.agility = new_stats.agility;
+
+        msg!(
+            "New Endurance {} Agility {} Speed {}",
+            doge_stats.endurance,
+            doge_stats.agility,
+            doge_stats.speed
+        );
+
+        Ok(())
+    }
+
+    // Shares the upgrade accounts so the fee goes through the same DTRK burn
+    pub fn respec_doge(ctx: Context<UpgradeDoge>, new_stats: Stats) -> Result<()> {
+        require!(!MAINTENANCE_MODE, DogeError::MaintenanceMode);
+        ctx.accounts.check_doge_not_racing()?;
+
+        let current_timestamp = Clock::get()?.unix_timestamp as u64;
+        require!(
+            ctx.accounts
+                .upgrade_limits
+                .is_respec_ready(ctx.accounts.doge_stats.last_respec_at, current_timestamp),
+            DogeError::RespecCooldown
+        );
+
+        let old_stats = Stats {
+            speed: ctx.accounts.doge_stats.speed,
+            endurance: ctx.accounts.doge_stats.endurance,
+            agility: ctx.accounts.doge_stats.agility,
+        };
+
+        let rarity = load_doge_rarity(&ctx.accounts.doge_rarity)?;
+        require!(
+            old_stats.validate_raised_with_cap(
+                &new_stats,
+                stat_cap(ctx.accounts.doge_stats.level, rarity)
+            ),
+            DogeError::InvalidUpdateStats
+        );
+
+        let fees_dtrk =
+            old_stats.calculate_respec_cost(&new_stats, &ctx.accounts.upgrade_pricing)?;
+
+        let token_balance = ctx.accounts.dtrk_token.amount;
+        require!(token_balance >= fees_dtrk, DogeError::InsufficientTokens);
+
+        if fees_dtrk > 0 {
+            msg!("Required DTRK for respec {} base units", fees_dtrk);
+            token::burn(ctx.accounts.token_burn_context(), fees_dtrk)?;
+        }
+
+        grow_doge_stats(
+            &mut ctx.accounts.doge_stats,
+            &ctx.accounts.doge_holder.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+        let doge_stats = &mut ctx.accounts.doge_stats;
+
+        doge_stats.endurance = new_stats.endurance;
+        doge_stats.speed = new_stats.speed;
+        doge_stats.agility = new_stats.agility;
+        doge_stats.last_respec_at = current_timestamp;
+
+        msg!(
+            "Respec Endurance {} Agility {} Speed {}",
+            doge_stats.endurance,
+            doge_stats.agility,
+            doge_stats.speed
+        );
+
+        Ok(())
+    }
+
+    pub fn quote_upgrade(ctx: Context<QuoteUpgrade>, new_stats: Stats) -> Result<()> {
+        let old_stats = Stats {
+            speed: ctx.accounts.doge_stats.speed,
+            endurance: ctx.accounts.doge_stats.endurance,
+            agility: ctx.accounts.doge_stats.agility,
+        };
+
+        let rarity = load_doge_rarity(&ctx.accounts.doge_rarity)?;
+        require!(
+            old_stats.validate_raised_with_cap(
+                &new_stats,
+                stat_cap(ctx.accounts.doge_stats.level, rarity)
+            ),
+            DogeError::InvalidUpdateStats
+        );
+
+        let quote = old_stats.quote(
+            &new_stats,
+            &ctx.accounts.upgrade_pricing,
+            ctx.accounts.dtrk_mint.decimals,
+        )?;
+
+        msg!(
+            "Upgrade quote {} DTRK ({} base units)",
+            quote.total_dtrk,
+            quote.total_base_units
+        );
 
+        set_return_data(&quote.try_to_vec()?);
+
+        Ok(())
+    }
+
+    // Reads the doge's wins and losses straight from its racer account in the game program
+    pub fn set_win_percentage(ctx: Context<SetWinPercentage>) -> Result<()> {
+        require!(!MAINTENANCE_MODE, DogeError::MaintenanceMode);
+        let game_program = ctx.accounts.game_config.game_program;
+        require!(
+            ctx.accounts.doge_racer.key().eq(&get_doge_racer_address(
+                &ctx.accounts.doge_mint.key(),
+                &ctx.accounts.doge_stats.key(),
+                &game_program
+            )),
+            DogeError::InvalidDogeRacer
+        );
+        let (total_wins, total_losses) =
+            get_doge_racer_record(&ctx.accounts.doge_racer, &game_program)?;
+
+        grow_doge_stats(
+            &mut ctx.accounts.doge_stats,
+            &ctx.accounts.authority.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+        let doge_o_stats = &mut ctx.accounts.doge_stats;
+
+        let win_pct_bps = win_percentage_bps(total_wins, total_losses)?;
+        doge_o_stats.win_percentage_bps = win_pct_bps;
+        doge_o_stats.win_percentage = (win_pct_bps / 100) as u8;
+
+        msg!(
+            "Win percentage {} bps from {} wins and {} losses",
+            win_pct_bps,
+            total_wins,
+            total_losses
+        );
+        Ok(())
+    }
+
+    pub fn award_experience(
+        ctx: Context<AwardExperience>,
+        placement: u8,
+        total_racers: u8,
+        lobby_class: u8,
+    ) -> Result<()> {
+        require!(!MAINTENANCE_MODE, DogeError::MaintenanceMode);
+        grow_doge_stats(
+            &mut ctx.accounts.doge_stats,
+            &ctx.accounts.authority.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+        let doge_stats = &mut ctx.accounts.doge_stats;
+
+        let experience = race_experience(placement, total_racers, lobby_class)?;
+        doge_stats.experience = doge_stats.experience.saturating_add(experience);
+
+        let level = level_for_experience(doge_stats.experience);
+        if level > doge_stats.level {
             msg!(
-                "Upgrading for doge {}",
-                ctx.accounts.doge_mint.key().to_string()
+                "Doge {} levelled up to {}",
+                doge_stats.doge_mint.to_string(),
+                level
             );
+        }
+        doge_stats.level = level;
+
+        msg!("Awarded {} XP, total {}", experience, doge_stats.experience);
+        Ok(())
+    }
+
+    // Remaining accounts are the race's doge stats in finishing order
+    pub fn update_ratings<'info>(
+        ctx: Context<'_, '_, '_, 'info, UpdateRatings<'info>>,
+    ) -> Result<()> {
+        require!(!MAINTENANCE_MODE, DogeError::MaintenanceMode);
 
-            let doge_stats = &mut ctx.accounts.doge_stats;
+        let doge_stats_infos = ctx.remaining_accounts;
+        let has_duplicates = doge_stats_infos.iter().enumerate().any(|(index, info)| {
+            doge_stats_infos[..index]
+                .iter()
+                .any(|other| other.key().eq(&info.key()))
+        });
+        require!(!has_duplicates, DogeError::InvalidRatingUpdate);
+
+        let mut doge_stats_accounts = doge_stats_infos
+            .iter()
+            .map(Account::<DogeStats>::try_from)
+            .collect::<Result<Vec<_>>>()?;
 
-            doge_stats.endurance = new_stats.endurance;
-            doge_stats.speed = new_stats.speed;
-            doge_stats.agility = new_stats.agility;
+        let ratings: Vec<u16> = doge_stats_accounts
+            .iter()
+            .map(|doge_stats| doge_stats.rating)
+            .collect();
+        let new_ratings = rating::update_ratings(&ratings)?;
 
+        for (doge_stats, new_rating) in doge_stats_accounts.iter_mut().zip(new_ratings) {
+            grow_doge_stats(
+                doge_stats,
+                &ctx.accounts.authority.to_account_info(),
+                &ctx.accounts.system_program.to_account_info(),
+            )?;
             msg!(
-                "New Endurance {} Agility {} Speed {}",
-                doge_stats.endurance,
-                doge_stats.agility,
-                doge_stats.speed
+                "Doge {} rating {} -> {}",
+                doge_stats.doge_mint.to_string(),
+                effective_rating(doge_stats.rating),
+                new_rating
             );
+            doge_stats.rating = new_rating;
+            doge_stats.exit(ctx.program_id)?;
         }
 
         Ok(())
     }
 
-    pub fn set_win_percentage(ctx: Context<SetWinPercentage>, win_pct: u8) -> Result<()> {
-        require!(!MAINTENANCE_MODE, DogeError::MaintenanceMode);
-        let doge_o_stats = &mut ctx.accounts.doge_stats;
-        require!(win_pct <= 100, DogeError::InvalidWinPercentage);
-        doge_o_stats.win_percentage = win_pct;
+    // Grows a doge stats account to the current layout in place. Existing fields keep
+    // their values and fields added since the account was created start zeroed
+    pub fn migrate_doge_stats(ctx: Context<MigrateDogeStats>) -> Result<()> {
+        msg!(
+            "Migrating doge stats {} from version {} to {}",
+            ctx.accounts.doge_stats.doge_mint.to_string(),
+            ctx.accounts.doge_stats.version,
+            DOGE_STATS_VERSION
+        );
+
+        grow_doge_stats(
+            &mut ctx.accounts.doge_stats,
+            &ctx.accounts.authority.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )
+    }
+
+    pub fn init_upgrade_pricing(
+        ctx: Context<InitUpgradePricing>,
+        tiers: Vec<PricingTier>,
+        respec_fee_bps: u16,
+    ) -> Result<()> {
+        require!(
+            UpgradePricing::validate_tiers(&tiers)
+                && UpgradePricing::validate_respec_fee(respec_fee_bps),
+            DogeError::InvalidUpgradePricing
+        );
+
+        let upgrade_pricing = &mut ctx.accounts.upgrade_pricing;
+        upgrade_pricing.bump = *ctx.bumps.get("upgrade_pricing").unwrap();
+        upgrade_pricing.tiers = tiers;
+        upgrade_pricing.respec_fee_bps = respec_fee_bps;
+
+        Ok(())
+    }
+
+    pub fn update_upgrade_pricing(
+        ctx: Context<UpdateUpgradePricing>,
+        new_tiers: Vec<PricingTier>,
+        respec_fee_bps: u16,
+    ) -> Result<()> {
+        require!(
+            UpgradePricing::validate_tiers(&new_tiers)
+                && UpgradePricing::validate_respec_fee(respec_fee_bps),
+            DogeError::InvalidUpgradePricing
+        );
+
+        ctx.accounts.upgrade_pricing.tiers = new_tiers;
+        ctx.accounts.upgrade_pricing.respec_fee_bps = respec_fee_bps;
+
+        Ok(())
+    }
+
+    pub fn init_upgrade_limits(
+        ctx: Context<InitUpgradeLimits>,
+        cooldown: u64,
+        period: u64,
+        max_points_per_period: u16,
+        respec_cooldown: u64,
+    ) -> Result<()> {
+        let upgrade_limits = &mut ctx.accounts.upgrade_limits;
+        upgrade_limits.bump = *ctx.bumps.get("upgrade_limits").unwrap();
+        upgrade_limits.cooldown = cooldown;
+        upgrade_limits.period = period;
+        upgrade_limits.max_points_per_period = max_points_per_period;
+        upgrade_limits.respec_cooldown = respec_cooldown;
+
+        require!(upgrade_limits.validate(), DogeError::InvalidUpgradeLimits);
+
+        Ok(())
+    }
+
+    pub fn update_upgrade_limits(
+        ctx: Context<UpdateUpgradeLimits>,
+        cooldown: u64,
+        period: u64,
+        max_points_per_period: u16,
+        respec_cooldown: u64,
+    ) -> Result<()> {
+        let upgrade_limits = &mut ctx.accounts.upgrade_limits;
+        upgrade_limits.cooldown = cooldown;
+        upgrade_limits.period = period;
+        upgrade_limits.max_points_per_period = max_points_per_period;
+        upgrade_limits.respec_cooldown = respec_cooldown;
+
+        require!(upgrade_limits.validate(), DogeError::InvalidUpgradeLimits);
+
+        Ok(())
+    }
+
+    pub fn init_game_config(ctx: Context<InitGameConfig>, game_program: Pubkey) -> Result<()> {
+        let game_config = &mut ctx.accounts.game_config;
+        game_config.bump = *ctx.bumps.get("game_config").unwrap();
+        game_config.game_program = game_program;
+
+        Ok(())
+    }
+
+    pub fn update_game_config(
+        ctx: Context<UpdateGameConfig>,
+        game_program: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.game_config.game_program = game_program;
+
         Ok(())
     }
 
@@ -141,6 +610,15 @@ pub mod doge_o {
         doge_stats_account.speed = 0;
         doge_stats_account.endurance = 0;
         doge_stats_account.win_percentage = 0;
+        doge_stats_account.win_percentage_bps = 0;
+        doge_stats_account.rating = 0;
+        doge_stats_account.experience = 0;
+        doge_stats_account.level = 0;
+        doge_stats_account.last_respec_at = 0;
+        doge_stats_account.version = 0;
+        doge_stats_account.last_upgrade_at = 0;
+        doge_stats_account.upgrade_period_started_at = 0;
+        doge_stats_account.upgrade_period_points = 0;
         doge_stats_account.doge_metadata = Pubkey::default();
         doge_stats_account.doge_mint = Pubkey::default();
         doge_stats_account.init_authority = Pubkey::default();
@@ -177,6 +655,9 @@ pub struct InitStats<'info> {
         bump
     )]
     pub doge_stats: Account<'info, DogeStats>,
+    /// CHECK: Doge's rarity PDA, loaded in the handler. Uninitialized for doges without a rarity
+    #[account(seeds = [b"rarity", doge_mint.key().as_ref()], bump)]
+    pub doge_rarity: UncheckedAccount<'info>,
     pub doge_mint: Account<'info, Mint>,
     #[account(
         constraint = doge_metadata.update_authority.eq(&init_authority.key()),
@@ -196,35 +677,197 @@ impl<'info> InitStats<'info> {
     }
 
     pub fn validate_doge_metadata(&self) -> Result<()> {
-        let (expected_metadata, _) = Pubkey::find_program_address(
-            &[
-                PREFIX.as_bytes(),
-                Metadata::owner().as_ref(),
-                self.doge_mint.key().as_ref(),
-            ],
-            &Metadata::owner(),
-        );
+        validate_doge_metadata(&self.doge_mint.key(), &self.doge_metadata)
+    }
+}
 
-        if self.doge_metadata.key().ne(&expected_metadata) {
-            return err!(DogeError::InvalidDogeMetadata);
-        }
+pub fn validate_doge_metadata(doge_mint: &Pubkey, doge_metadata: &Account<Metadata>) -> Result<()> {
+    let (expected_metadata, _) = Pubkey::find_program_address(
+        &[
+            PREFIX.as_bytes(),
+            Metadata::owner().as_ref(),
+            doge_mint.as_ref(),
+        ],
+        &Metadata::owner(),
+    );
 
-        if let Some(creators) = &self.doge_metadata.data.creators {
-            let verified_creator = creators
-                .iter()
-                .find(|&creator| creator.address.eq(&get_verified_creator()) && creator.verified);
+    if doge_metadata.key().ne(&expected_metadata) {
+        return err!(DogeError::InvalidDogeMetadata);
+    }
 
-            if verified_creator.is_none() {
-                return err!(DogeError::InvalidDogeMetadata);
-            }
+    if let Some(creators) = &doge_metadata.data.creators {
+        let verified_creator = creators
+            .iter()
+            .find(|&creator| creator.address.eq(&get_verified_creator()) && creator.verified);
 
-            Ok(())
-        } else {
+        if verified_creator.is_none() {
             return err!(DogeError::InvalidDogeMetadata);
         }
+
+        Ok(())
+    } else {
+        return err!(DogeError::InvalidDogeMetadata);
     }
 }
 
+#[derive(Accounts)]
+pub struct InitDogeRarity<'info> {
+    #[account(mut, address = get_authority())]
+    pub authority: Signer<'info>,
+    #[account(
+        init,
+        payer = authority,
+        space = DOGE_RARITY_SIZE,
+        seeds = [b"rarity", doge_mint.key().as_ref()],
+        bump
+    )]
+    pub doge_rarity: Account<'info, DogeRarity>,
+    pub doge_mint: Account<'info, Mint>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateDogeRarity<'info> {
+    #[account(address = get_authority())]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"rarity", doge_rarity.doge_mint.as_ref()],
+        bump = doge_rarity.bump
+    )]
+    pub doge_rarity: Account<'info, DogeRarity>,
+}
+
+#[derive(Accounts)]
+pub struct CommitStatRoll<'info> {
+    #[account(mut, address = get_authority())]
+    pub authority: Signer<'info>,
+    #[account(
+        init,
+        payer = authority,
+        space = STAT_ROLL_SIZE,
+        seeds = [b"statroll", doge_mint.key().as_ref()],
+        bump
+    )]
+    pub stat_roll: Account<'info, StatRoll>,
+    pub doge_mint: Account<'info, Mint>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RollStats<'info> {
+    #[account(mut, address = get_authority())]
+    pub init_authority: Signer<'info>,
+    #[account(
+        init,
+        payer = init_authority,
+        space = DOGE_STATS_SIZE,
+        seeds = [b"dogeo", init_authority.key().as_ref(), doge_mint.key().as_ref()],
+        bump
+    )]
+    pub doge_stats: Account<'info, DogeStats>,
+    #[account(
+        mut,
+        constraint = !stat_roll.revealed @ DogeError::InvalidStatRoll,
+        seeds = [b"statroll", doge_mint.key().as_ref()],
+        bump = stat_roll.bump
+    )]
+    pub stat_roll: Account<'info, StatRoll>,
+    #[account(seeds = [b"rarity", doge_mint.key().as_ref()], bump = doge_rarity.bump)]
+    pub doge_rarity: Account<'info, DogeRarity>,
+    pub doge_mint: Account<'info, Mint>,
+    #[account(
+        constraint = doge_metadata.update_authority.eq(&init_authority.key()),
+        constraint = doge_metadata.mint.eq(&doge_mint.key())
+    )]
+    pub doge_metadata: Account<'info, Metadata>,
+    /// CHECK: Address checked, read raw in the handler
+    #[account(address = anchor_lang::solana_program::sysvar::slot_hashes::ID)]
+    pub slot_hashes: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitUpgradePricing<'info> {
+    #[account(mut, address = get_authority())]
+    pub authority: Signer<'info>,
+    #[account(
+        init,
+        payer = authority,
+        space = UPGRADE_PRICING_SIZE,
+        seeds = [b"upgradepricing"],
+        bump
+    )]
+    pub upgrade_pricing: Account<'info, UpgradePricing>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateUpgradePricing<'info> {
+    #[account(address = get_authority())]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"upgradepricing"],
+        bump = upgrade_pricing.bump
+    )]
+    pub upgrade_pricing: Account<'info, UpgradePricing>,
+}
+
+#[derive(Accounts)]
+pub struct InitUpgradeLimits<'info> {
+    #[account(mut, address = get_authority())]
+    pub authority: Signer<'info>,
+    #[account(
+        init,
+        payer = authority,
+        space = UPGRADE_LIMITS_SIZE,
+        seeds = [b"upgradelimits"],
+        bump
+    )]
+    pub upgrade_limits: Account<'info, UpgradeLimits>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateUpgradeLimits<'info> {
+    #[account(address = get_authority())]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"upgradelimits"],
+        bump = upgrade_limits.bump
+    )]
+    pub upgrade_limits: Account<'info, UpgradeLimits>,
+}
+
+#[derive(Accounts)]
+pub struct InitGameConfig<'info> {
+    #[account(mut, address = get_authority())]
+    pub authority: Signer<'info>,
+    #[account(
+        init,
+        payer = authority,
+        space = GAME_CONFIG_SIZE,
+        seeds = [b"gameconfig"],
+        bump
+    )]
+    pub game_config: Account<'info, GameConfig>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateGameConfig<'info> {
+    #[account(address = get_authority())]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"gameconfig"],
+        bump = game_config.bump
+    )]
+    pub game_config: Account<'info, GameConfig>,
+}
+
 #[derive(Accounts)]
 pub struct UpgradeDoge<'info> {
     #[account(mut,
@@ -235,6 +878,17 @@ pub struct UpgradeDoge<'info> {
         bump = doge_stats.doge_bump
     )]
     pub doge_stats: Account<'info, DogeStats>,
+    #[account(seeds = [b"upgradepricing"], bump = upgrade_pricing.bump)]
+    pub upgrade_pricing: Account<'info, UpgradePricing>,
+    #[account(seeds = [b"upgradelimits"], bump = upgrade_limits.bump)]
+    pub upgrade_limits: Account<'info, UpgradeLimits>,
+    #[account(seeds = [b"gameconfig"], bump = game_config.bump)]
+    pub game_config: Account<'info, GameConfig>,
+    /// CHECK: Doge's rarity PDA, loaded in the handler. Uninitialized for doges without a rarity
+    #[account(seeds = [b"rarity", doge_mint.key().as_ref()], bump)]
+    pub doge_rarity: UncheckedAccount<'info>,
+    /// CHECK: Game program's racer PDA for this doge, validated in the handler. May be uninitialized
+    pub doge_racer: UncheckedAccount<'info>,
     #[account(
         mut,
         constraint = dtrk_token.owner.eq(&doge_holder.key()),
@@ -258,9 +912,30 @@ pub struct UpgradeDoge<'info> {
     #[account(mut)]
     pub doge_holder: Signer<'info>,
     pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
 }
 
 impl<'info> UpgradeDoge<'info> {
+    pub fn check_doge_not_racing(&self) -> Result<()> {
+        let game_program = self.game_config.game_program;
+        let expected_doge_racer = get_doge_racer_address(
+            &self.doge_mint.key(),
+            &self.doge_stats.key(),
+            &game_program,
+        );
+
+        require!(
+            self.doge_racer.key().eq(&expected_doge_racer),
+            DogeError::InvalidDogeRacer
+        );
+        require!(
+            !is_doge_racing(&self.doge_racer, &game_program)?,
+            DogeError::DogeRacing
+        );
+
+        Ok(())
+    }
+
     pub fn token_burn_context(&self) -> CpiContext<'_, '_, '_, 'info, Burn<'info>> {
         CpiContext::new(
             self.token_program.to_account_info(),
@@ -273,6 +948,58 @@ impl<'info> UpgradeDoge<'info> {
     }
 }
 
+#[derive(Accounts)]
+pub struct QuoteUpgrade<'info> {
+    #[account(
+        has_one = init_authority,
+        has_one = doge_mint,
+        seeds = [b"dogeo", init_authority.key().as_ref(), doge_mint.key().as_ref()], 
+        bump = doge_stats.doge_bump
+    )]
+    pub doge_stats: Account<'info, DogeStats>,
+    #[account(seeds = [b"upgradepricing"], bump = upgrade_pricing.bump)]
+    pub upgrade_pricing: Account<'info, UpgradePricing>,
+    /// CHECK: Doge's rarity PDA, loaded in the handler. Uninitialized for doges without a rarity
+    #[account(seeds = [b"rarity", doge_mint.key().as_ref()], bump)]
+    pub doge_rarity: UncheckedAccount<'info>,
+    #[account(constraint = dtrk_mint.key().eq(&get_dtrk()))]
+    pub dtrk_mint: Account<'info, Mint>,
+    pub doge_mint: Account<'info, Mint>,
+    pub init_authority: SystemAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateDogeStats<'info> {
+    #[account(mut, address = get_authority())]
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub doge_stats: Account<'info, DogeStats>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AwardExperience<'info> {
+    #[account(mut, address = get_authority())]
+    pub authority: Signer<'info>,
+    pub init_authority: SystemAccount<'info>,
+    #[account(mut,
+        has_one = init_authority,
+        has_one = doge_mint,
+        seeds = [b"dogeo", init_authority.key().as_ref(), doge_mint.key().as_ref()], 
+        bump = doge_stats.doge_bump
+    )]
+    pub doge_stats: Account<'info, DogeStats>,
+    pub doge_mint: Account<'info, Mint>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateRatings<'info> {
+    #[account(mut, address = get_authority())]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
 #[derive(Accounts)]
 pub struct SetWinPercentage<'info> {
     #[account(mut, address = get_authority())]
@@ -286,10 +1013,49 @@ pub struct SetWinPercentage<'info> {
     )]
     pub doge_stats: Account<'info, DogeStats>,
     pub doge_mint: Account<'info, Mint>,
+    #[account(seeds = [b"gameconfig"], bump = game_config.bump)]
+    pub game_config: Account<'info, GameConfig>,
+    /// CHECK: Game program's racer PDA for this doge, validated in the handler
+    pub doge_racer: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
 }
 
-#[account]
-#[derive(Default)]
+// Grows doge stats created with an older, shorter layout to the current size and version.
+// Their missing fields already read as zero, so only the size and rent need topping up
+pub fn grow_doge_stats<'info>(
+    doge_stats: &mut Account<'info, DogeStats>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    let doge_stats_info = doge_stats.to_account_info();
+    msg!("Doge stats data size {}", doge_stats_info.data_len());
+
+    if doge_stats_info.data_len() < DOGE_STATS_SIZE {
+        let lamports_to_send = Rent::get()?
+            .minimum_balance(DOGE_STATS_SIZE)
+            .saturating_sub(doge_stats_info.lamports());
+
+        if lamports_to_send > 0 {
+            invoke(
+                &system_instruction::transfer(payer.key, doge_stats_info.key, lamports_to_send),
+                &[
+                    payer.clone(),
+                    doge_stats_info.clone(),
+                    system_program.clone(),
+                ],
+            )?;
+        }
+
+        doge_stats_info.realloc(DOGE_STATS_SIZE, true)?;
+    }
+
+    doge_stats.version = DOGE_STATS_VERSION;
+    Ok(())
+}
+
+// Not an `#[account]`, so that doge stats created before fields were appended to the layout
+// keep deserializing. See the `AccountDeserialize` impl below
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
 pub struct DogeStats {
     pub agility: u8,
     pub speed: u8,
@@ -299,4 +1065,268 @@ pub struct DogeStats {
     pub doge_mint: Pubkey,
     pub init_authority: Pubkey,
     pub doge_metadata: Pubkey,
+    pub win_percentage_bps: u16,
+    // Elo rating, 0 until the doge settles its first rated race
+    pub rating: u16,
+    pub experience: u64,
+    // Derived from experience, raises the per stat cap
+    pub level: u8,
+    pub last_respec_at: u64,
+    pub version: u8,
+    pub last_upgrade_at: u64,
+    pub upgrade_period_started_at: u64,
+    // Stat points gained since `upgrade_period_started_at`
+    pub upgrade_period_points: u16,
+}
+
+impl DogeStats {
+    // Enforces the upgrade cooldown and period cap, then books the gained points
+    pub fn record_upgrade(
+        &mut self,
+        gained_points: u16,
+        current_timestamp: u64,
+        upgrade_limits: &UpgradeLimits,
+    ) -> Result<()> {
+        require!(
+            self.last_upgrade_at == 0
+                || current_timestamp
+                    >= self.last_upgrade_at.saturating_add(upgrade_limits.cooldown),
+            DogeError::UpgradeCooldown
+        );
+
+        if current_timestamp
+            >= self
+                .upgrade_period_started_at
+                .saturating_add(upgrade_limits.period)
+        {
+            self.upgrade_period_started_at = current_timestamp;
+            self.upgrade_period_points = 0;
+        }
+
+        let period_points = self.upgrade_period_points.saturating_add(gained_points);
+        require!(
+            period_points <= upgrade_limits.max_points_per_period,
+            DogeError::UpgradePeriodCapExceeded
+        );
+
+        self.upgrade_period_points = period_points;
+        self.last_upgrade_at = current_timestamp;
+        Ok(())
+    }
+
+    pub fn initialize(
+        &mut self,
+        stats: &Stats,
+        doge_bump: u8,
+        doge_mint: Pubkey,
+        doge_metadata: Pubkey,
+        init_authority: Pubkey,
+    ) {
+        self.agility = stats.agility;
+        self.endurance = stats.endurance;
+        self.speed = stats.speed;
+        self.win_percentage = 0;
+        self.win_percentage_bps = 0;
+        self.rating = INITIAL_RATING;
+        self.experience = 0;
+        self.level = 0;
+        self.last_respec_at = 0;
+        self.version = DOGE_STATS_VERSION;
+        self.last_upgrade_at = 0;
+        self.upgrade_period_started_at = 0;
+        self.upgrade_period_points = 0;
+        self.doge_bump = doge_bump;
+        self.doge_mint = doge_mint;
+        self.doge_metadata = doge_metadata;
+        self.init_authority = init_authority;
+    }
+}
+
+impl Discriminator for DogeStats {
+    fn discriminator() -> [u8; 8] {
+        // First 8 bytes of sha256("account:DogeStats"), as `#[account]` would derive
+        [88, 148, 135, 123, 56, 48, 56, 60]
+    }
+}
+
+impl Owner for DogeStats {
+    fn owner() -> Pubkey {
+        crate::ID
+    }
+}
+
+impl AccountSerialize for DogeStats {
+    fn try_serialize<W: std::io::Write>(&self, writer: &mut W) -> Result<()> {
+        if writer.write_all(&Self::discriminator()).is_err() {
+            return Err(ErrorCode::AccountDidNotSerialize.into());
+        }
+
+        if AnchorSerialize::serialize(self, writer).is_err() {
+            return Err(ErrorCode::AccountDidNotSerialize.into());
+        }
+        Ok(())
+    }
+}
+
+impl AccountDeserialize for DogeStats {
+    fn try_deserialize(buf: &mut &[u8]) -> Result<Self> {
+        if buf.len() < 8 {
+            return Err(ErrorCode::AccountDiscriminatorNotFound.into());
+        }
+        if buf[..8].ne(&Self::discriminator()) {
+            return Err(ErrorCode::AccountDiscriminatorMismatch.into());
+        }
+        Self::try_deserialize_unchecked(buf)
+    }
+
+    // Accounts on an older layout are shorter, the fields appended since read as zero
+    fn try_deserialize_unchecked(buf: &mut &[u8]) -> Result<Self> {
+        let mut data = buf[8..].to_vec();
+        if data.len() < DOGE_STATS_SIZE - 8 {
+            data.resize(DOGE_STATS_SIZE - 8, 0);
+        }
+
+        AnchorDeserialize::deserialize(&mut data.as_slice())
+            .map_err(|_| ErrorCode::AccountDidNotDeserialize.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Layout every doge stats account had before fields were appended
+    fn legacy_doge_stats(doge_mint: &Pubkey) -> Vec<u8> {
+        let mut data = DogeStats::discriminator().to_vec();
+        data.extend_from_slice(&[40, 50, 60, 254, 33]);
+        data.extend_from_slice(doge_mint.as_ref());
+        data.extend_from_slice(get_authority().as_ref());
+        data.extend_from_slice(Pubkey::new_unique().as_ref());
+        data
+    }
+
+    #[test]
+    fn legacy_doge_stats_deserialize_with_zeroed_new_fields() {
+        let doge_mint = Pubkey::new_unique();
+        let data = legacy_doge_stats(&doge_mint);
+        assert_eq!(data.len(), 8 + 5 + 32 * 3);
+
+        let doge_stats = DogeStats::try_deserialize(&mut data.as_slice()).unwrap();
+        assert_eq!(
+            (doge_stats.agility, doge_stats.speed, doge_stats.endurance),
+            (40, 50, 60)
+        );
+        assert_eq!(doge_stats.doge_bump, 254);
+        assert_eq!(doge_stats.win_percentage, 33);
+        assert_eq!(doge_stats.doge_mint, doge_mint);
+        assert_eq!(doge_stats.init_authority, get_authority());
+        assert_eq!(doge_stats.version, 0);
+        assert_eq!(doge_stats.rating, 0);
+        assert_eq!(effective_rating(doge_stats.rating), INITIAL_RATING);
+        assert_eq!((doge_stats.experience, doge_stats.level), (0, 0));
+    }
+
+    #[test]
+    fn doge_stats_round_trip_at_current_size() {
+        let mut doge_stats = DogeStats::default();
+        doge_stats.initialize(
+            &Stats {
+                speed: 10,
+                agility: 20,
+                endurance: 30,
+            },
+            255,
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            get_authority(),
+        );
+        doge_stats.experience = 1234;
+
+        let mut data = vec![0u8; DOGE_STATS_SIZE];
+        doge_stats.try_serialize(&mut data.as_mut_slice()).unwrap();
+        let decoded = DogeStats::try_deserialize(&mut data.as_slice()).unwrap();
+
+        assert_eq!(decoded.version, DOGE_STATS_VERSION);
+        assert_eq!(decoded.rating, INITIAL_RATING);
+        assert_eq!(decoded.experience, 1234);
+        assert_eq!(decoded.speed, 10);
+
+        let mut short = vec![0u8; DOGE_STATS_SIZE - DOGE_STATS_RESERVED_SIZE - 1];
+        assert!(doge_stats.try_serialize(&mut short.as_mut_slice()).is_err());
+    }
+
+    #[test]
+    fn rejects_other_accounts() {
+        let mut data = legacy_doge_stats(&Pubkey::new_unique());
+        data[0] ^= 1;
+        assert!(DogeStats::try_deserialize(&mut data.as_slice()).is_err());
+        assert!(DogeStats::try_deserialize(&mut &data[..4]).is_err());
+    }
+
+    const NOW: u64 = 1_650_000_000;
+
+    fn upgrade_limits() -> UpgradeLimits {
+        UpgradeLimits {
+            cooldown: 60,
+            period: 1_000,
+            max_points_per_period: 10,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn upgrades_may_hit_the_period_cap_exactly() {
+        let upgrade_limits = upgrade_limits();
+        let mut doge_stats = DogeStats::default();
+
+        doge_stats
+            .record_upgrade(4, NOW + 100, &upgrade_limits)
+            .unwrap();
+        doge_stats
+            .record_upgrade(6, NOW + 160, &upgrade_limits)
+            .unwrap();
+        assert_eq!(doge_stats.upgrade_period_points, 10);
+        assert_eq!(doge_stats.last_upgrade_at, NOW + 160);
+
+        assert!(doge_stats
+            .record_upgrade(1, NOW + 220, &upgrade_limits)
+            .is_err());
+        assert_eq!(doge_stats.upgrade_period_points, 10);
+    }
+
+    #[test]
+    fn upgrades_wait_out_the_cooldown() {
+        let upgrade_limits = upgrade_limits();
+        let mut doge_stats = DogeStats::default();
+
+        doge_stats
+            .record_upgrade(1, NOW + 100, &upgrade_limits)
+            .unwrap();
+        assert!(doge_stats
+            .record_upgrade(1, NOW + 159, &upgrade_limits)
+            .is_err());
+        doge_stats
+            .record_upgrade(1, NOW + 160, &upgrade_limits)
+            .unwrap();
+    }
+
+    #[test]
+    fn upgrade_periods_roll_over() {
+        let upgrade_limits = upgrade_limits();
+        let mut doge_stats = DogeStats::default();
+
+        doge_stats
+            .record_upgrade(10, NOW + 100, &upgrade_limits)
+            .unwrap();
+        // The period opened by the first upgrade still runs 999 seconds later
+        assert!(doge_stats
+            .record_upgrade(1, NOW + 1_099, &upgrade_limits)
+            .is_err());
+
+        doge_stats
+            .record_upgrade(10, NOW + 1_100, &upgrade_limits)
+            .unwrap();
+        assert_eq!(doge_stats.upgrade_period_started_at, NOW + 1_100);
+        assert_eq!(doge_stats.upgrade_period_points, 10);
+    }
 }
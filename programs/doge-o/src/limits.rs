@@ -0,0 +1,130 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::DogeError;
+
+pub const UPGRADE_LIMITS_SIZE: usize = 8 + // discriminator
+    1 + // bump
+    8 + // cooldown
+    8 + // period
+    2 + // max_points_per_period
+    8; // respec_cooldown
+
+pub const GAME_CONFIG_SIZE: usize = 8 + // discriminator
+    1 + // bump
+    32; // game_program
+
+// Offset of `current_lobby_race` in the game program's DogeRacerState (discriminator, bump, doge_o_pda).
+// The game program tests its layout against these offsets
+pub const DOGE_RACER_CURRENT_LOBBY_OFFSET: usize = 8 + 1 + 32;
+
+// Offset of `total_wins` in the game program's DogeRacerState, after current_lobby_race,
+// last_joined_timestamp and doge_holder_entry_fee_token. `total_losses` follows it
+pub const DOGE_RACER_TOTAL_WINS_OFFSET: usize = DOGE_RACER_CURRENT_LOBBY_OFFSET + 32 + 8 + 32;
+
+#[account]
+#[derive(Default)]
+pub struct UpgradeLimits {
+    pub bump: u8,
+    // Seconds between two upgrades of the same doge
+    pub cooldown: u64,
+    // Length in seconds of the window `max_points_per_period` applies to
+    pub period: u64,
+    pub max_points_per_period: u16,
+    // Seconds between two respecs of the same doge
+    pub respec_cooldown: u64,
+}
+
+// Game program owning the doge racer accounts that lock a doge while it races
+#[account]
+#[derive(Default)]
+pub struct GameConfig {
+    pub bump: u8,
+    pub game_program: Pubkey,
+}
+
+impl UpgradeLimits {
+    pub fn validate(&self) -> bool {
+        self.period > 0 && self.max_points_per_period > 0
+    }
+
+    // Doges that never respecced can respec right away
+    pub fn is_respec_ready(&self, last_respec_at: u64, current_timestamp: u64) -> bool {
+        last_respec_at == 0
+            || current_timestamp >= last_respec_at.saturating_add(self.respec_cooldown)
+    }
+}
+
+pub fn get_doge_racer_address(
+    doge_mint: &Pubkey,
+    doge_stats: &Pubkey,
+    game_program: &Pubkey,
+) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"dogeracer", doge_mint.as_ref(), doge_stats.as_ref()],
+        game_program,
+    )
+    .0
+}
+
+// A doge is racing while its racer account points at a lobby. Doges that never
+// registered as racers have no racer account and are free to upgrade
+pub fn is_doge_racing(doge_racer: &AccountInfo, game_program: &Pubkey) -> Result<bool> {
+    if doge_racer.owner.ne(game_program) {
+        return Ok(false);
+    }
+
+    let data = doge_racer.try_borrow_data()?;
+    require!(
+        data.len() >= DOGE_RACER_CURRENT_LOBBY_OFFSET + 32,
+        DogeError::InvalidDogeRacer
+    );
+
+    let current_lobby_race =
+        &data[DOGE_RACER_CURRENT_LOBBY_OFFSET..DOGE_RACER_CURRENT_LOBBY_OFFSET + 32];
+    Ok(current_lobby_race.ne(Pubkey::default().as_ref()))
+}
+
+// Wins and losses the game program has recorded for the doge
+pub fn get_doge_racer_record(
+    doge_racer: &AccountInfo,
+    game_program: &Pubkey,
+) -> Result<(u64, u64)> {
+    require!(
+        doge_racer.owner.eq(game_program),
+        DogeError::InvalidDogeRacer
+    );
+
+    let data = doge_racer.try_borrow_data()?;
+    require!(
+        data.len() >= DOGE_RACER_TOTAL_WINS_OFFSET + 16,
+        DogeError::InvalidDogeRacer
+    );
+
+    let read_u64 = |offset: usize| {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&data[offset..offset + 8]);
+        u64::from_le_bytes(bytes)
+    };
+    Ok((
+        read_u64(DOGE_RACER_TOTAL_WINS_OFFSET),
+        read_u64(DOGE_RACER_TOTAL_WINS_OFFSET + 8),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn respecs_wait_out_the_whole_cooldown() {
+        let upgrade_limits = UpgradeLimits {
+            respec_cooldown: 3_600,
+            ..Default::default()
+        };
+
+        assert!(upgrade_limits.is_respec_ready(0, 1));
+        assert!(!upgrade_limits.is_respec_ready(1_000, 4_599));
+        assert!(upgrade_limits.is_respec_ready(1_000, 4_600));
+        assert!(upgrade_limits.is_respec_ready(u64::MAX, u64::MAX));
+    }
+}
@@ -0,0 +1,165 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::DogeError;
+use crate::level::MAX_LEVELED_STAT;
+use crate::stats::MAX_BPS;
+
+pub const MAX_PRICING_TIERS: usize = 10;
+
+pub const PRICING_TIER_SIZE: usize = 1 + // max_stat
+    8; // cost_per_point
+
+pub const UPGRADE_PRICING_SIZE: usize = 8 + // discriminator
+    1 + // bump
+    4 + PRICING_TIER_SIZE * MAX_PRICING_TIERS + // tiers
+    2; // respec_fee_bps
+
+#[account]
+#[derive(Default)]
+pub struct UpgradePricing {
+    pub bump: u8,
+    pub tiers: Vec<PricingTier>,
+    // Share of the upgrade price of the points a respec moves that it charges
+    pub respec_fee_bps: u16,
+}
+
+// Every stat point up to and including `max_stat` costs `cost_per_point` DTRK base units
+#[derive(AnchorDeserialize, AnchorSerialize, Clone, Debug, Default)]
+pub struct PricingTier {
+    pub max_stat: u8,
+    pub cost_per_point: u64,
+}
+
+impl UpgradePricing {
+    pub fn validate_tiers(tiers: &[PricingTier]) -> bool {
+        !tiers.is_empty()
+            && tiers.len() <= MAX_PRICING_TIERS
+            && tiers
+                .windows(2)
+                .all(|pair| pair[0].max_stat < pair[1].max_stat)
+            && tiers.last().map(|tier| tier.max_stat) == Some(MAX_LEVELED_STAT)
+    }
+
+    pub fn validate_respec_fee(respec_fee_bps: u16) -> bool {
+        respec_fee_bps as u64 <= MAX_BPS
+    }
+
+    // Cost of raising a stat from `old_stat` to `new_stat`, priced by the tier each
+    // gained point lands in
+    pub fn cost_between(&self, old_stat: u8, new_stat: u8) -> Result<u64> {
+        require!(old_stat <= new_stat, DogeError::InvalidUpdateStats);
+        // Points above the highest tier have no price, refuse them rather than give them away
+        let highest_priced_stat = self
+            .tiers
+            .last()
+            .map(|tier| tier.max_stat)
+            .unwrap_or_default();
+        require!(
+            new_stat <= highest_priced_stat,
+            DogeError::InvalidUpgradePricing
+        );
+
+        let mut fees: u64 = 0;
+        let mut tier_floor = 0u8;
+
+        for tier in &self.tiers {
+            let low = old_stat.max(tier_floor);
+            let high = new_stat.min(tier.max_stat);
+
+            if high > low {
+                fees = tier
+                    .cost_per_point
+                    .checked_mul((high - low) as u64)
+                    .and_then(|tier_fees| fees.checked_add(tier_fees))
+                    .ok_or(DogeError::InvalidUpdateStats)?;
+            }
+
+            tier_floor = tier.max_stat;
+        }
+
+        Ok(fees)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 10 per point up to 50, 20 up to 100 and 40 up to the leveled cap
+    fn pricing() -> UpgradePricing {
+        UpgradePricing {
+            bump: 255,
+            tiers: vec![
+                PricingTier {
+                    max_stat: 50,
+                    cost_per_point: 10,
+                },
+                PricingTier {
+                    max_stat: 100,
+                    cost_per_point: 20,
+                },
+                PricingTier {
+                    max_stat: MAX_LEVELED_STAT,
+                    cost_per_point: 40,
+                },
+            ],
+            respec_fee_bps: 5_000,
+        }
+    }
+
+    #[test]
+    fn points_are_priced_by_the_tier_they_land_in() {
+        let pricing = pricing();
+
+        assert_eq!(pricing.cost_between(10, 20).unwrap(), 100);
+        assert_eq!(pricing.cost_between(45, 55).unwrap(), 5 * 10 + 5 * 20);
+        assert_eq!(
+            pricing.cost_between(40, 110).unwrap(),
+            10 * 10 + 50 * 20 + 10 * 40
+        );
+    }
+
+    #[test]
+    fn unchanged_stats_are_free() {
+        assert_eq!(pricing().cost_between(50, 50).unwrap(), 0);
+        assert_eq!(pricing().cost_between(0, 0).unwrap(), 0);
+    }
+
+    #[test]
+    fn lowered_stats_are_refused() {
+        assert!(pricing().cost_between(60, 59).is_err());
+    }
+
+    #[test]
+    fn stats_above_the_highest_tier_are_refused() {
+        let mut pricing = pricing();
+        pricing.tiers.pop();
+
+        assert_eq!(pricing.cost_between(90, 100).unwrap(), 200);
+        assert!(pricing.cost_between(90, 101).is_err());
+    }
+
+    #[test]
+    fn overflowing_costs_are_refused() {
+        let mut pricing = pricing();
+        pricing.tiers[2].cost_per_point = u64::MAX;
+
+        assert_eq!(pricing.cost_between(100, 101).unwrap(), u64::MAX);
+        assert!(pricing.cost_between(100, 102).is_err());
+        assert!(pricing.cost_between(99, 101).is_err());
+    }
+
+    #[test]
+    fn pricing_fits_the_most_tiers() {
+        let pricing = UpgradePricing {
+            bump: 255,
+            tiers: vec![PricingTier::default(); MAX_PRICING_TIERS],
+            respec_fee_bps: 0,
+        };
+
+        assert_eq!(
+            pricing.try_to_vec().unwrap().len(),
+            UPGRADE_PRICING_SIZE - 8
+        );
+    }
+}
@@ -0,0 +1,57 @@
+use anchor_lang::prelude::*;
+
+use crate::stats::MAX_STAT;
+
+pub const DOGE_RARITY_SIZE: usize = 8 + 1 + 32 + 1;
+
+// Mirrors the rarity attribute of the doge's off-chain metadata, which the program can't read
+#[account]
+#[derive(Default)]
+pub struct DogeRarity {
+    pub bump: u8,
+    pub doge_mint: Pubkey,
+    pub rarity: Rarity,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Rarity {
+    #[default]
+    Common,
+    Uncommon,
+    Rare,
+    Epic,
+    Legendary,
+}
+
+// Doges minted before rarities were recorded have no rarity account
+pub fn load_doge_rarity(doge_rarity: &AccountInfo) -> Result<Option<Rarity>> {
+    if doge_rarity.owner.ne(&crate::ID) && doge_rarity.data_is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(Account::<DogeRarity>::try_from(doge_rarity)?.rarity))
+}
+
+impl Rarity {
+    // Highest value any stat can reach before level bonuses
+    pub fn stat_ceiling(&self) -> u8 {
+        match self {
+            Rarity::Common => 80,
+            Rarity::Uncommon => 85,
+            Rarity::Rare => 90,
+            Rarity::Epic => 95,
+            Rarity::Legendary => MAX_STAT,
+        }
+    }
+
+    // Inclusive range every rolled stat falls in
+    pub fn roll_range(&self) -> (u8, u8) {
+        match self {
+            Rarity::Common => (10, 40),
+            Rarity::Uncommon => (20, 50),
+            Rarity::Rare => (30, 60),
+            Rarity::Epic => (40, 70),
+            Rarity::Legendary => (50, 80),
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::DogeError;
+
+pub const INITIAL_RATING: u16 = 1200;
+
+pub const MIN_RATING: u16 = 100;
+
+pub const MAX_RATING: u16 = 4000;
+
+// Maximum rating movement against a single field of opponents
+pub const RATING_K_FACTOR: i64 = 32;
+
+// Bounds the pairwise work done in a single settlement
+pub const MAX_RATED_RACERS: usize = 10;
+
+const SCORE_PRECISION: i64 = 10_000;
+
+const EXPECTED_SCORE_STEP: u16 = 25;
+
+// Expected score in bps of the higher rated doge for rating gaps of 0, 25, .. 800
+const EXPECTED_SCORE_BPS: [u16; 33] = [
+    5000, 5359, 5715, 6063, 6401, 6725, 7034, 7325, 7597, 7850, 8083, 8296, 8490, 8666, 8823, 8965,
+    9091, 9203, 9302, 9390, 9468, 9536, 9595, 9648, 9693, 9733, 9768, 9799, 9825, 9848, 9868, 9886,
+    9901,
+];
+
+// Doges rated before ratings existed carry 0 and start from the initial rating
+pub fn effective_rating(rating: u16) -> u16 {
+    if rating == 0 {
+        INITIAL_RATING
+    } else {
+        rating.clamp(MIN_RATING, MAX_RATING)
+    }
+}
+
+// Elo expected score of `rating` against `opponent` in bps, interpolated from the lookup table
+pub fn expected_score_bps(rating: u16, opponent: u16) -> u16 {
+    let gap = rating.max(opponent) - rating.min(opponent);
+    let max_gap = EXPECTED_SCORE_STEP * (EXPECTED_SCORE_BPS.len() as u16 - 1);
+
+    let favourite_score = if gap >= max_gap {
+        EXPECTED_SCORE_BPS[EXPECTED_SCORE_BPS.len() - 1]
+    } else {
+        let index = (gap / EXPECTED_SCORE_STEP) as usize;
+        let remainder = gap % EXPECTED_SCORE_STEP;
+        let lower = EXPECTED_SCORE_BPS[index];
+        let upper = EXPECTED_SCORE_BPS[index + 1];
+        lower + (upper - lower) * remainder / EXPECTED_SCORE_STEP
+    };
+
+    if rating >= opponent {
+        favourite_score
+    } else {
+        SCORE_PRECISION as u16 - favourite_score
+    }
+}
+
+// New ratings for a race, given current ratings in finishing order. Every pair of doges is
+// scored as a head to head result and the deltas are averaged over the field
+pub fn update_ratings(ratings_in_finishing_order: &[u16]) -> Result<Vec<u16>> {
+    let total_racers = ratings_in_finishing_order.len();
+    require!(
+        (2..=MAX_RATED_RACERS).contains(&total_racers),
+        DogeError::InvalidRatingUpdate
+    );
+
+    let ratings: Vec<u16> = ratings_in_finishing_order
+        .iter()
+        .map(|&rating| effective_rating(rating))
+        .collect();
+
+    let opponents = (total_racers - 1) as i64;
+
+    Ok(ratings
+        .iter()
+        .enumerate()
+        .map(|(position, &rating)| {
+            let score_difference: i64 = ratings
+                .iter()
+                .enumerate()
+                .filter(|&(opponent_position, _)| opponent_position != position)
+                .map(|(opponent_position, &opponent)| {
+                    let actual_score = if position < opponent_position {
+                        SCORE_PRECISION
+                    } else {
+                        0
+                    };
+                    actual_score - expected_score_bps(rating, opponent) as i64
+                })
+                .sum();
+
+            let delta = RATING_K_FACTOR * score_difference / (opponents * SCORE_PRECISION);
+
+            (rating as i64 + delta).clamp(MIN_RATING.into(), MAX_RATING.into()) as u16
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expected_score_is_symmetric() {
+        for (rating, opponent) in [(1200, 1200), (1500, 1210), (900, 2400), (1337, 1349)] {
+            assert_eq!(
+                expected_score_bps(rating, opponent) + expected_score_bps(opponent, rating),
+                10_000
+            );
+        }
+    }
+
+    #[test]
+    fn expected_score_interpolates_and_saturates() {
+        assert_eq!(expected_score_bps(1200, 1200), 5000);
+        assert_eq!(expected_score_bps(1300, 1200), 6401);
+        assert!(expected_score_bps(1210, 1200) > 5000);
+        assert!(expected_score_bps(1210, 1200) < 5359);
+        assert_eq!(expected_score_bps(4000, 100), 9901);
+    }
+
+    #[test]
+    fn unrated_doges_start_from_initial_rating() {
+        assert_eq!(effective_rating(0), INITIAL_RATING);
+        assert_eq!(update_ratings(&[0, 0]).unwrap(), vec![1216, 1184]);
+    }
+
+    #[test]
+    fn winner_gains_and_last_place_loses() {
+        let ratings = update_ratings(&[1200, 1200, 1200, 1200, 1200]).unwrap();
+
+        assert_eq!(ratings, vec![1216, 1208, 1200, 1192, 1184]);
+    }
+
+    #[test]
+    fn upsets_move_ratings_further() {
+        let favourite_wins = update_ratings(&[1600, 1200]).unwrap();
+        let underdog_wins = update_ratings(&[1200, 1600]).unwrap();
+
+        assert!(favourite_wins[0] - 1600 < underdog_wins[0] - 1200);
+        assert!(1200 - favourite_wins[1] < 1600 - underdog_wins[1]);
+    }
+
+    #[test]
+    fn ratings_stay_within_bounds() {
+        assert_eq!(
+            update_ratings(&[MAX_RATING, MAX_RATING]).unwrap()[0],
+            MAX_RATING
+        );
+        assert_eq!(
+            update_ratings(&[MIN_RATING, MIN_RATING]).unwrap()[1],
+            MIN_RATING
+        );
+    }
+
+    #[test]
+    fn rejects_unbounded_fields() {
+        assert!(update_ratings(&[1200]).is_err());
+        assert!(update_ratings(&[1200; MAX_RATED_RACERS + 1]).is_err());
+    }
+}
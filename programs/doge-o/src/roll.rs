@@ -0,0 +1,168 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+
+use crate::errors::DogeError;
+use crate::rarity::Rarity;
+use crate::stats::Stats;
+
+pub const STAT_ROLL_SIZE: usize = 8 + // discriminator
+    1 + // bump
+    32 + // doge_mint
+    32 + // seed_hash
+    8 + // commit_slot
+    1 + // revealed
+    32 + // seed
+    8 + // roll_slot
+    32; // slot_hash
+
+const SLOT_HASH_ENTRY_SIZE: usize = 8 + 32;
+
+// Commitment to a seed, made before the slot hash that gets mixed into the roll exists.
+// Once revealed it holds every input needed to recompute the stats off-chain
+#[account]
+#[derive(Default)]
+pub struct StatRoll {
+    pub bump: u8,
+    pub doge_mint: Pubkey,
+    pub seed_hash: [u8; 32],
+    pub commit_slot: u64,
+    pub revealed: bool,
+    pub seed: [u8; 32],
+    pub roll_slot: u64,
+    pub slot_hash: [u8; 32],
+}
+
+// SlotHashes entry a roll committed at `commit_slot` mixes in: the first slot after the
+// commit that has a hash. Fixing it at commit time keeps the reveal from picking among later
+// hashes. The sysvar only keeps recent slots, so the reveal fails once that entry has aged out
+pub fn roll_slot_hash(slot_hashes: &AccountInfo, commit_slot: u64) -> Result<(u64, [u8; 32])> {
+    let data = slot_hashes.try_borrow_data()?;
+    find_roll_slot_hash(&data, commit_slot)
+}
+
+// Reads the sysvar's (slot, hash) entries in place, newest first, without deserializing it whole
+pub fn find_roll_slot_hash(data: &[u8], commit_slot: u64) -> Result<(u64, [u8; 32])> {
+    require!(data.len() >= 8, DogeError::InvalidStatRoll);
+    let entries = u64::from_le_bytes(data[..8].try_into().unwrap()) as usize;
+    require!(
+        entries > 0 && data.len() >= 8 + entries * SLOT_HASH_ENTRY_SIZE,
+        DogeError::InvalidStatRoll
+    );
+
+    let entry = |index: usize| {
+        let offset = 8 + index * SLOT_HASH_ENTRY_SIZE;
+        let slot = u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap());
+        let hash: [u8; 32] = data[offset + 8..offset + SLOT_HASH_ENTRY_SIZE]
+            .try_into()
+            .unwrap();
+        (slot, hash)
+    };
+
+    // No slot after the commit has a hash yet
+    require!(entry(0).0 > commit_slot, DogeError::InvalidStatRoll);
+    // Unless the oldest entry still reaches back to the commit, the slot after it is gone
+    require!(
+        entry(entries - 1).0 <= commit_slot,
+        DogeError::StatRollExpired
+    );
+
+    let index = (0..entries)
+        .rev()
+        .find(|&index| entry(index).0 > commit_slot)
+        .ok_or(DogeError::InvalidStatRoll)?;
+    Ok(entry(index))
+}
+
+// sha256(seed || slot_hash || doge_mint), two bytes per stat mapped into the rarity range
+pub fn derive_stats(
+    seed: &[u8; 32],
+    slot_hash: &[u8; 32],
+    doge_mint: &Pubkey,
+    rarity: Rarity,
+) -> Stats {
+    let digest = hashv(&[seed, slot_hash, doge_mint.as_ref()]).to_bytes();
+    let (min_stat, max_stat) = rarity.roll_range();
+    let span = (max_stat - min_stat) as u16 + 1;
+
+    let roll = |offset: usize| {
+        let value = u16::from_le_bytes([digest[offset], digest[offset + 1]]);
+        min_stat + (value % span) as u8
+    };
+
+    Stats {
+        endurance: roll(0),
+        speed: roll(2),
+        agility: roll(4),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // SlotHashes sysvar data holding the given slots, newest first
+    fn slot_hashes(slots: &[u64]) -> Vec<u8> {
+        let mut data = (slots.len() as u64).to_le_bytes().to_vec();
+        for slot in slots {
+            data.extend_from_slice(&slot.to_le_bytes());
+            data.extend_from_slice(&[*slot as u8; 32]);
+        }
+        data
+    }
+
+    fn assert_error(result: Result<(u64, [u8; 32])>, expected: DogeError) {
+        assert_eq!(
+            ProgramError::from(result.unwrap_err()),
+            ProgramError::from(Error::from(expected))
+        );
+    }
+
+    #[test]
+    fn rolls_with_the_first_slot_after_the_commit() {
+        let data = slot_hashes(&[110, 109, 108, 105, 104, 103, 101, 100]);
+
+        assert_eq!(find_roll_slot_hash(&data, 100).unwrap(), (101, [101; 32]));
+        assert_eq!(find_roll_slot_hash(&data, 104).unwrap(), (105, [105; 32]));
+    }
+
+    #[test]
+    fn skipped_slots_roll_with_the_next_hash() {
+        let data = slot_hashes(&[110, 109, 108, 105, 104, 103, 101, 100]);
+
+        assert_eq!(find_roll_slot_hash(&data, 101).unwrap(), (103, [103; 32]));
+        assert_eq!(find_roll_slot_hash(&data, 106).unwrap(), (108, [108; 32]));
+    }
+
+    #[test]
+    fn later_reveals_roll_the_same_slot() {
+        let early = slot_hashes(&[103, 102, 101, 100, 99]);
+        let late = slot_hashes(&[140, 130, 120, 110, 103, 102, 101, 100, 99]);
+
+        assert_eq!(
+            find_roll_slot_hash(&early, 100).unwrap(),
+            find_roll_slot_hash(&late, 100).unwrap()
+        );
+    }
+
+    #[test]
+    fn rejects_reveals_before_the_next_slot() {
+        let data = slot_hashes(&[100, 99, 98]);
+
+        assert_error(find_roll_slot_hash(&data, 100), DogeError::InvalidStatRoll);
+    }
+
+    #[test]
+    fn rejects_reveals_once_the_slot_aged_out() {
+        let data = slot_hashes(&[700, 699, 698, 650, 601]);
+
+        assert_error(find_roll_slot_hash(&data, 600), DogeError::StatRollExpired);
+        assert!(find_roll_slot_hash(&data, 601).is_ok());
+    }
+
+    #[test]
+    fn rejects_malformed_sysvar_data() {
+        assert!(find_roll_slot_hash(&[], 1).is_err());
+        assert!(find_roll_slot_hash(&slot_hashes(&[]), 1).is_err());
+        assert!(find_roll_slot_hash(&slot_hashes(&[3, 2])[..50], 1).is_err());
+    }
+}
@@ -1,6 +1,11 @@
 use anchor_lang::prelude::*;
 
 use crate::errors::DogeError;
+use crate::pricing::UpgradePricing;
+
+pub const MAX_STAT: u8 = 100;
+
+pub const MAX_BPS: u64 = 10_000;
 
 #[derive(AnchorDeserialize, AnchorSerialize)]
 pub struct Stats {
@@ -11,25 +16,124 @@ pub struct Stats {
 
 impl Stats {
     pub fn validate(&self) -> bool {
+        self.validate_with_cap(MAX_STAT)
+    }
+
+    // Leveled doges may go past MAX_STAT up to their level's cap
+    pub fn validate_with_cap(&self, max_stat: u8) -> bool {
         self.agility > 0
-            && self.agility <= 100
+            && self.agility <= max_stat
             && self.endurance > 0
-            && self.endurance <= 100
+            && self.endurance <= max_stat
             && self.speed > 0
-            && self.speed <= 100
+            && self.speed <= max_stat
+    }
+
+    // Only stats being raised have to stay within `max_stat`. Stats kept or lowered may sit
+    // above it, so doges from before a lower cap applied can still change their other stats
+    pub fn validate_raised_with_cap(&self, new_stats: &Stats, max_stat: u8) -> bool {
+        [
+            (self.endurance, new_stats.endurance),
+            (self.speed, new_stats.speed),
+            (self.agility, new_stats.agility),
+        ]
+        .iter()
+        .all(|&(old_stat, new_stat)| new_stat > 0 && (new_stat <= old_stat || new_stat <= max_stat))
+    }
+
+    pub fn calculate_cost(&self, new_stats: &Stats, pricing: &UpgradePricing) -> Result<u64> {
+        let (endurance_fees, speed_fees, agility_fees) =
+            self.calculate_cost_breakdown(new_stats, pricing)?;
+
+        let total_fees = endurance_fees
+            .checked_add(speed_fees)
+            .and_then(|fees| fees.checked_add(agility_fees))
+            .ok_or(DogeError::InvalidUpdateStats)?;
+
+        Ok(total_fees)
     }
 
-    pub fn calculate_cost(&self, new_stats: &Stats) -> Result<u64> {
+    // Per stat upgrade cost in DTRK base units as (endurance, speed, agility)
+    pub fn calculate_cost_breakdown(
+        &self,
+        new_stats: &Stats,
+        pricing: &UpgradePricing,
+    ) -> Result<(u64, u64, u64)> {
         if !self.validate_new_stats(new_stats) {
             msg!("Error: Invalid new stats");
             return err!(DogeError::InvalidUpdateStats);
         }
 
-        let total_fees = calculate_dtrk(new_stats.endurance, self.endurance)?
-            + calculate_dtrk(new_stats.agility, self.agility)?
-            + calculate_dtrk(new_stats.speed, self.speed)?;
+        Ok((
+            pricing.cost_between(self.endurance, new_stats.endurance)?,
+            pricing.cost_between(self.speed, new_stats.speed)?,
+            pricing.cost_between(self.agility, new_stats.agility)?,
+        ))
+    }
 
-        Ok(total_fees)
+    pub fn quote(
+        &self,
+        new_stats: &Stats,
+        pricing: &UpgradePricing,
+        dtrk_decimals: u8,
+    ) -> Result<UpgradeQuote> {
+        let (endurance, speed, agility) = self.calculate_cost_breakdown(new_stats, pricing)?;
+        let total_base_units = self.calculate_cost(new_stats, pricing)?;
+
+        Ok(UpgradeQuote {
+            total_dtrk: total_base_units
+                .checked_div(10u64.pow(dtrk_decimals.into()))
+                .ok_or(DogeError::InvalidUpdateStats)?,
+            total_base_units,
+            endurance,
+            speed,
+            agility,
+        })
+    }
+
+    // Points added across all stats, ignoring any that were lowered
+    pub fn gained_points(&self, new_stats: &Stats) -> u16 {
+        new_stats.endurance.saturating_sub(self.endurance) as u16
+            + new_stats.speed.saturating_sub(self.speed) as u16
+            + new_stats.agility.saturating_sub(self.agility) as u16
+    }
+
+    pub fn total_points(&self) -> u16 {
+        self.endurance as u16 + self.speed as u16 + self.agility as u16
+    }
+
+    // Respec fee in DTRK base units, the pricing's respec share of the upgrade price of the
+    // points being moved. Points move between stats without changing the total
+    pub fn calculate_respec_cost(
+        &self,
+        new_stats: &Stats,
+        pricing: &UpgradePricing,
+    ) -> Result<u64> {
+        require!(
+            self.total_points() == new_stats.total_points(),
+            DogeError::InvalidRespec
+        );
+
+        let mut moved_points_cost: u64 = 0;
+        for (old_stat, new_stat) in [
+            (self.endurance, new_stats.endurance),
+            (self.speed, new_stats.speed),
+            (self.agility, new_stats.agility),
+        ] {
+            if new_stat > old_stat {
+                moved_points_cost = pricing
+                    .cost_between(old_stat, new_stat)?
+                    .checked_add(moved_points_cost)
+                    .ok_or(DogeError::InvalidRespec)?;
+            }
+        }
+
+        let respec_cost = (moved_points_cost as u128)
+            .checked_mul(pricing.respec_fee_bps.into())
+            .and_then(|product| product.checked_div(MAX_BPS.into()))
+            .ok_or(DogeError::InvalidRespec)?;
+
+        u64::try_from(respec_cost).map_err(|_| error!(DogeError::InvalidRespec))
     }
 
     pub fn validate_new_stats(&self, new_stats: &Stats) -> bool {
@@ -39,87 +143,132 @@ impl Stats {
     }
 }
 
-fn calculate_dtrk(new_stat: u8, old_stat: u8) -> Result<u64> {
-    let mut old_stat = old_stat;
-    let mut fee_level = fetch_fee_level(old_stat);
+// Upgrade cost returned by `quote_upgrade`. Per stat costs are in DTRK base units
+#[derive(AnchorDeserialize, AnchorSerialize, Clone, Debug, Default)]
+pub struct UpgradeQuote {
+    pub total_dtrk: u64,
+    pub total_base_units: u64,
+    pub endurance: u64,
+    pub speed: u64,
+    pub agility: u64,
+}
 
-    let mut level_diff = new_stat
-        .checked_sub(old_stat)
-        .ok_or(DogeError::InvalidUpdateStats)?;
+// Win percentage in basis points, rounded down. Doges without races sit at 0
+pub fn win_percentage_bps(total_wins: u64, total_losses: u64) -> Result<u16> {
+    let total_races = total_wins
+        .checked_add(total_losses)
+        .ok_or(DogeError::InvalidWinPercentage)?;
 
-    let mut fees: u64 = 0;
+    if total_races == 0 {
+        return Ok(0);
+    }
 
-    while level_diff != 0 {
-        let nearest_checkpoint = fetch_nearest_checkpoint(old_stat);
-        let limit = if fetch_nearest_checkpoint(old_stat).gt(&new_stat) {
-            new_stat
-        } else {
-            nearest_checkpoint
-        };
+    let win_percentage_bps = (total_wins as u128)
+        .checked_mul(MAX_BPS.into())
+        .and_then(|product| product.checked_div(total_races.into()))
+        .ok_or(DogeError::InvalidWinPercentage)?;
 
-        let limit_diff = limit
-            .checked_sub(old_stat)
-            .ok_or(DogeError::InvalidUpdateStats)?;
+    u16::try_from(win_percentage_bps).map_err(|_| error!(DogeError::InvalidWinPercentage))
+}
 
-        let floor_level_diff = if (limit_diff).lt(&20) { limit_diff } else { 20 };
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::MAX_LEVELED_STAT;
+    use crate::pricing::PricingTier;
 
-        if floor_level_diff == 0u8 && old_stat == nearest_checkpoint {
-            fees = fees
-                .checked_add(fetch_fee_level(old_stat + 1) as u64)
-                .ok_or(DogeError::InvalidUpdateStats)?;
-        } else {
-            fees = fees
-                .checked_add(fee_level as u64 * floor_level_diff as u64)
-                .ok_or(DogeError::InvalidUpdateStats)?;
+    fn stats(endurance: u8, speed: u8, agility: u8) -> Stats {
+        Stats {
+            endurance,
+            speed,
+            agility,
         }
+    }
 
-        let conc_floor_level_diff = if floor_level_diff.eq(&0) {
-            1
-        } else {
-            floor_level_diff
-        };
+    #[test]
+    fn raised_stats_stay_within_the_cap() {
+        let old_stats = stats(50, 60, 70);
 
-        old_stat = old_stat
-            .checked_add(conc_floor_level_diff)
-            .ok_or(DogeError::InvalidUpdateStats)?;
+        assert!(old_stats.validate_raised_with_cap(&stats(80, 60, 70), 80));
+        assert!(!old_stats.validate_raised_with_cap(&stats(81, 60, 70), 80));
+        assert!(!old_stats.validate_raised_with_cap(&stats(0, 60, 70), 80));
+    }
 
-        fee_level = fetch_fee_level(old_stat);
-        level_diff = level_diff
-            .checked_sub(conc_floor_level_diff)
-            .ok_or(DogeError::InvalidUpdateStats)?;
+    #[test]
+    fn stats_above_a_lower_cap_are_grandfathered() {
+        let old_stats = stats(50, 95, 90);
+
+        // Upgrading another stat keeps the ones already past the cap
+        assert!(old_stats.validate_raised_with_cap(&stats(60, 95, 90), 80));
+        // Respecs may move points out of them, but not raise them further
+        assert!(old_stats.validate_raised_with_cap(&stats(65, 80, 90), 80));
+        assert!(!old_stats.validate_raised_with_cap(&stats(45, 96, 90), 80));
     }
 
-    Ok(fees)
-}
+    fn pricing(cost_per_point: u64, respec_fee_bps: u16) -> UpgradePricing {
+        UpgradePricing {
+            bump: 255,
+            tiers: vec![PricingTier {
+                max_stat: MAX_LEVELED_STAT,
+                cost_per_point,
+            }],
+            respec_fee_bps,
+        }
+    }
+
+    #[test]
+    fn respec_costs_round_down() {
+        let old_stats = stats(50, 60, 70);
+        let pricing = pricing(3, 5_000);
 
-fn fetch_fee_level(stat: u8) -> u8 {
-    if stat > 1 && stat <= 20 {
-        18u8
-    } else if (21..=40).contains(&stat) {
-        36u8
-    } else if (41..=60).contains(&stat) {
-        72u8
-    } else if (61..=80).contains(&stat) {
-        144u8
-    } else if (81..=100).contains(&stat) {
-        255u8
-    } else {
-        0u8
+        // Half of a 3 unit point rounds down to 1
+        assert_eq!(
+            old_stats
+                .calculate_respec_cost(&stats(51, 59, 70), &pricing)
+                .unwrap(),
+            1
+        );
+        // Only the raised stats are charged, 3 points at 3 units each
+        assert_eq!(
+            old_stats
+                .calculate_respec_cost(&stats(52, 57, 71), &pricing)
+                .unwrap(),
+            4
+        );
     }
-}
 
-fn fetch_nearest_checkpoint(stat: u8) -> u8 {
-    if stat > 1 && stat <= 20 {
-        20u8
-    } else if stat > 20 && stat <= 40 {
-        40u8
-    } else if stat > 40 && stat <= 60 {
-        60u8
-    } else if stat > 60 && stat <= 80 {
-        80u8
-    } else if stat > 80 && stat <= 100 {
-        100u8
-    } else {
-        0u8
+    #[test]
+    fn respec_costs_follow_the_configured_fee() {
+        let old_stats = stats(50, 60, 70);
+        let new_stats = stats(60, 50, 70);
+
+        assert_eq!(
+            old_stats
+                .calculate_respec_cost(&new_stats, &pricing(7, 0))
+                .unwrap(),
+            0
+        );
+        assert_eq!(
+            old_stats
+                .calculate_respec_cost(&new_stats, &pricing(7, 10_000))
+                .unwrap(),
+            70
+        );
+        assert!(!UpgradePricing::validate_respec_fee(10_001));
+    }
+
+    #[test]
+    fn respecs_keep_the_total_points() {
+        let old_stats = stats(50, 60, 70);
+
+        assert!(old_stats
+            .calculate_respec_cost(&stats(51, 60, 70), &pricing(3, 5_000))
+            .is_err());
+        assert_eq!(
+            old_stats
+                .calculate_respec_cost(&old_stats, &pricing(3, 5_000))
+                .unwrap(),
+            0
+        );
     }
 }
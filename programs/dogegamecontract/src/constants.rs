@@ -9,7 +9,8 @@ pub const ENTRY_FEE_REQUIREMENT_SIZE: usize = 1 + // bump
     8 + // min_class_4_fee
     8 + // max_class_4_fee
     8 + // min_class_5_fee
-    8; // max_class_5_fee
+    8 + // max_class_5_fee
+    1; // version
 
 pub fn lobby_account_size(max_players: u8) -> usize {
     let final_max_players = if max_players < 2 { 2 } else { max_players };
@@ -32,7 +33,9 @@ pub fn lobby_account_size(max_players: u8) -> usize {
     32 + // track_holder
     32 + // track_holder_token
     32 + // track_holder_entry_fee_token
-    (4 + (32 * final_max_players as usize)); // racers
+    (4 + (32 * final_max_players as usize)) + // racers
+    1 + // version
+    8; // race_count
 }
 
 pub const DOGE_RACER_SIZE: usize = 1 + // bump
@@ -42,30 +45,187 @@ pub const DOGE_RACER_SIZE: usize = 1 + // bump
     32 + // doge_holder
     32 + // doge_holder_entry_fee_token
     8 + // total_wins
-    8; // total_losses
+    8 + // total_losses
+    32 + // referrer
+    8 + // registered_at
+    TRACK_TYPE_STATS_SIZE * TRACK_TYPE_COUNT + // track_type_stats
+    RECENT_RACE_RESULT_SIZE * RECENT_RESULTS_LEN + // recent_results
+    1 + // recent_results_head
+    8 + // current_win_streak
+    8 + // best_win_streak
+    8; // current_loss_streak
 
-pub const RACE_STATE_SIZE: usize = 1 + // bump
+pub const TRACK_TYPE_COUNT: usize = 4;
+
+pub const TRACK_TYPE_STATS_SIZE: usize = 8 + // races_run
+    8 + // wins
+    8 + // losses
+    8; // podiums
+
+pub const RECENT_RESULTS_LEN: usize = 10;
+
+pub const RECENT_RACE_RESULT_SIZE: usize = 32 + // lobby
+    8 + // race_number
+    1 + // placement
+    8 + // payout
+    8; // timestamp
+
+// Finishing positions counted as a podium
+pub const PODIUM_PLACES: usize = 3;
+
+pub const RACE_RESULT_SIZE: usize = 32 + // doge_racer
+    32 + // doge_stats
+    8 + // payout
+    1 + // endurance
+    1 + // speed
+    1 + // agility
+    2 + // rating
+    1 + // recorded
+    1; // settled
+
+pub fn race_state_size(max_players: u8) -> usize {
+    1 + // bump
     32 + // lobby_account
     32 + // entry_fee_mint
     32 + // track_holder
     32 + // track_mint
     32 + // winner_doge_racer_account
     8 + // race_started_at
-    8 + // dtrk_fee
-    (4 + (32 * 5)); // racers
+    8 + // entry_fee_token_fee
+    (4 + (32 * max_players as usize)) + // racers
+    8 + // race_number
+    1 + // track_type
+    1 + // total_laps
+    8 + // settled_at
+    8 + // track_owner_fee
+    8 + // treasury_fee
+    8 + // burn_amount
+    8 + // network_fee
+    (4 + (RACE_RESULT_SIZE * max_players as usize)) // results
+}
+
+pub const FEE_SCHEDULE_SIZE: usize = 1 + // bump
+    2 + // track_owner_bps
+    2 + // treasury_bps
+    2 + // burn_bps
+    1; // dust_recipient
+
+pub const REFERRAL_CONFIG_SIZE: usize = 1 + // bump
+    2 + // share_bps
+    8 + // max_referred_races
+    8; // duration
+
+pub const REFERRER_SIZE: usize = 1 + // bump
+    32 + // referrer
+    8 + // referred_racers
+    8 + // claimable
+    8 + // total_earned
+    8; // total_claimed
+
+pub const MAX_STAKE_TIERS: usize = 5;
+
+pub const STAKE_TIER_SIZE: usize = 8 + // min_amount
+    8 + // min_lock_duration
+    2; // rake_discount_bps
+
+pub const MAX_TRACK_FEE_MINTS: usize = 5;
+
+pub const TRACK_STATS_SIZE: usize = 1 + // bump
+    32 + // track_mint
+    8 + // races_hosted
+    8 + // unique_racers
+    (4 + ((32 + 8) * MAX_TRACK_FEE_MINTS)) + // entry_fee_volume
+    8 + // best_race_time
+    32 + // best_race_doge_racer
+    8; // best_race_number
+
+pub const TRACK_RACER_SIZE: usize = 1 + // bump
+    32 + // track_mint
+    32 + // doge_racer
+    8; // races_run
+
+pub const MAX_LEADERBOARD_SIZE: usize = 10;
+
+pub const LEADERBOARD_ENTRY_SIZE: usize = 32 + // doge_racer
+    32 + // doge_holder
+    8; // points
+
+pub const SEASONS_STATE_SIZE: usize = 1 + // bump
+    8 + // season_count
+    8; // last_end_time
+
+pub const SEASON_STATE_SIZE: usize = 1 + // bump
+    8 + // season_id
+    8 + // start_time
+    8 + // end_time
+    32 + // prize_mint
+    32 + // prize_vault
+    2 + // rake_share_bps
+    (4 + (2 * MAX_LEADERBOARD_SIZE)) + // prize_split_bps
+    (4 + (LEADERBOARD_ENTRY_SIZE * MAX_LEADERBOARD_SIZE)) + // leaderboard
+    8 + // prize_pool
+    1; // closed
+
+pub const SEASON_RACER_SIZE: usize = 1 + // bump
+    32 + // season
+    32 + // doge_racer
+    32 + // doge_holder
+    8 + // points
+    8 + // races_run
+    8; // wins
+
+pub const MAX_STREAK_MILESTONES: usize = 10;
+
+pub const STREAK_CONFIG_SIZE: usize = 1 + // bump
+    2 + // rake_share_bps
+    (4 + (8 * MAX_STREAK_MILESTONES)); // milestones
+
+pub const STAKE_CONFIG_SIZE: usize = 1 + // bump
+    8 + // total_staked
+    (4 + (STAKE_TIER_SIZE * MAX_STAKE_TIERS)); // tiers
+
+pub const STAKE_POSITION_SIZE: usize = 1 + // bump
+    32 + // owner
+    32 + // vault
+    8 + // amount
+    8 + // locked_at
+    8; // unlock_at
+
+pub const MAX_TREASURY_MINTS: usize = 10;
+
+pub const TREASURY_LEDGER_SIZE: usize = 32 + // mint
+    32 + // vault
+    8 + // fees_collected
+    8 + // rake_collected
+    8 + // residual_swept
+    8 + // withdrawn
+    8; // referral_rewards_paid
+
+pub const TREASURY_STATE_SIZE: usize = 1 + // bump
+    (4 + (TREASURY_LEDGER_SIZE * MAX_TREASURY_MINTS)) + // ledgers
+    8; // referral_rewards_reserved
+
+// Entry fees are expressed in hundredths of a whole token, regardless of the mint decimals
+pub const ENTRY_FEE_DECIMALS: u8 = 2;
+
+// Accounts created before entry fees were decimal aware are on version 0
+pub const LOBBY_STATE_VERSION: u8 = 2;
+pub const ENTRY_FEE_REQUIREMENTS_VERSION: u8 = 1;
 
 pub const SOL_NETWORK_FEE: u64 = 10_000_000; // 0.01 SOL network fee 
-pub const TRACK_OWNER_PCT: u64 = 20; // 20% track owner share from reward pool
+pub const MAX_BPS: u64 = 10_000; // 100% in basis points
 
 pub const NATIVE_MINT: &str = "So11111111111111111111111111111111111111112";
 pub const DTRK_MINT: &str = "DTRK1XRNaL6CxfFWwVLZMxyhiCZnwdP32CgzVDXWy5Td";
 pub const TRACK_VERIFIED_CREATOR: &str = "E4cAAdwFoJJT7uER46U7CZcrKvYhX8G7BEzC19vuFTMa";
-pub const TREASURY_ADDRESS: &str = "GFs7gjW38XBsYbf1G6jkdbiXXkmJ5sKGyakdRmfcFvzN";
 
 // This is the cooldown timer before you can flush a racer from a lobby 
 // pub const STALE_RACERS_FLUSH_COOLDOWN: u64 = 20; // 20 seconds 
 pub const STALE_RACERS_FLUSH_COOLDOWN: u64 = 60 * 30; // 30 minutes
 
+// This is the longest period DTRK can be locked for in a stake position
+pub const MAX_STAKE_LOCK_DURATION: u64 = 60 * 60 * 24 * 365; // 1 year
+
 // This is the cooldown timer before you can close a lobby 
 // pub const COOLDOWN_PERIOD: i64 = 10; // 10 seconds
 pub const COOLDOWN_PERIOD: i64 = 60 * 60 * 24; // 24 hours
@@ -107,6 +107,126 @@ pub enum GameError {
     InvalidEntryFeeMint,
 
     // 6026
-    #[msg("Max players cannot be less than 2")]
+    #[msg("Max players must be between 2 and the number of racers that can be rated")]
     InvalidMaxPlayersCount,
+
+    // 6027
+    #[msg("Treasury vault not found for mint")]
+    TreasuryVaultNotFound,
+
+    // 6028
+    #[msg("Treasury vault already exists for mint")]
+    TreasuryVaultExists,
+
+    // 6029
+    #[msg("Treasury cannot hold any more mints")]
+    TreasuryFull,
+
+    // 6030
+    #[msg("Invalid Treasury token account")]
+    InvalidTreasuryTokenAccount,
+
+    // 6031
+    #[msg("Insufficient Treasury balance")]
+    InsufficientTreasuryBalance,
+
+    // 6032
+    #[msg("Invalid Fee schedule")]
+    InvalidFeeSchedule,
+
+    // 6033
+    #[msg("Reward pool split does not add up to the pool")]
+    InvalidPoolSplit,
+
+    // 6034
+    #[msg("Invalid Referral config")]
+    InvalidReferralConfig,
+
+    // 6035
+    #[msg("Invalid Referrer account")]
+    InvalidReferrer,
+
+    // 6036
+    #[msg("No referral rewards to claim")]
+    NoReferralRewards,
+
+    // 6037
+    #[msg("Invalid Stake config")]
+    InvalidStakeConfig,
+
+    // 6038
+    #[msg("Invalid Stake lock duration")]
+    InvalidStakeLockDuration,
+
+    // 6039
+    #[msg("Stake position is still locked")]
+    StakeLocked,
+
+    // 6040
+    #[msg("Insufficient staked DTRK")]
+    InsufficientStake,
+
+    // 6041
+    #[msg("Invalid Stake position")]
+    InvalidStakePosition,
+
+    // 6042
+    #[msg("Account needs to be migrated")]
+    AccountNotMigrated,
+
+    // 6043
+    #[msg("Entry fee cannot be represented with the mint decimals")]
+    InvalidEntryFeePrecision,
+
+    // 6044
+    #[msg("Finishing order does not match the lobby racers")]
+    InvalidFinishingOrder,
+
+    // 6045
+    #[msg("Invalid race data account")]
+    InvalidRaceData,
+
+    // 6046
+    #[msg("Invalid streak config")]
+    InvalidStreakConfig,
+
+    // 6047
+    #[msg("Invalid season")]
+    InvalidSeason,
+
+    // 6048
+    #[msg("Invalid season account")]
+    InvalidSeasonAccount,
+
+    // 6049
+    #[msg("Season has not ended yet")]
+    SeasonNotEnded,
+
+    // 6050
+    #[msg("Season already closed")]
+    SeasonClosed,
+
+    // 6051
+    #[msg("Invalid track stats account")]
+    InvalidTrackStatsAccount,
+
+    // 6052
+    #[msg("Track stats cannot track more entry fee mints")]
+    TrackStatsFull,
+
+    // 6053
+    #[msg("Racer's result has not been recorded")]
+    RacerResultNotRecorded,
+
+    // 6054
+    #[msg("Racer's result has already been recorded")]
+    RacerResultAlreadyRecorded,
+
+    // 6055
+    #[msg("Racer's result has already been settled")]
+    RacerResultAlreadySettled,
+
+    // 6056
+    #[msg("Treasury has not reserved the referral rewards being claimed")]
+    ReferralRewardsNotReserved,
 }
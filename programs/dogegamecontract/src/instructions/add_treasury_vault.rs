@@ -0,0 +1,88 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::{create, get_associated_token_address, AssociatedToken, Create},
+    token::{Mint, Token},
+};
+use doge_o::get_authority;
+
+use crate::{
+    constants::MAX_TREASURY_MINTS,
+    error::GameError,
+    state::{TreasuryLedger, TreasuryState},
+};
+
+#[derive(Accounts)]
+pub struct AddTreasuryVault<'info> {
+    #[account(
+        mut,
+        address = get_authority()
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"treasury"],
+        bump = treasury_account.bump
+    )]
+    pub treasury_account: Account<'info, TreasuryState>,
+
+    pub mint: Account<'info, Mint>,
+
+    #[account(mut)]
+    /// CHECK: Proper ATA validation is done
+    pub treasury_token: UncheckedAccount<'info>,
+
+    // Sysvar
+    pub rent: Sysvar<'info, Rent>,
+
+    // Programs
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<AddTreasuryVault>) -> Result<()> {
+    let mint = ctx.accounts.mint.key();
+    let treasury_token = &ctx.accounts.treasury_token;
+
+    require!(
+        !ctx.accounts
+            .treasury_account
+            .ledgers
+            .iter()
+            .any(|ledger| ledger.mint.eq(&mint)),
+        GameError::TreasuryVaultExists
+    );
+    require!(
+        ctx.accounts.treasury_account.ledgers.len() < MAX_TREASURY_MINTS,
+        GameError::TreasuryFull
+    );
+    require!(
+        treasury_token
+            .key()
+            .eq(&get_associated_token_address(&ctx.accounts.treasury_account.key(), &mint)),
+        GameError::InvalidTreasuryTokenAccount
+    );
+
+    msg!("Creating Treasury vault for mint {}", mint.to_string());
+    create(CpiContext::new(
+        ctx.accounts.associated_token_program.to_account_info(),
+        Create {
+            payer: ctx.accounts.authority.to_account_info(),
+            associated_token: treasury_token.to_account_info(),
+            authority: ctx.accounts.treasury_account.to_account_info(),
+            mint: ctx.accounts.mint.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+            rent: ctx.accounts.rent.to_account_info(),
+        },
+    ))?;
+
+    ctx.accounts.treasury_account.ledgers.push(TreasuryLedger {
+        mint,
+        vault: treasury_token.key(),
+        ..Default::default()
+    });
+
+    Ok(())
+}
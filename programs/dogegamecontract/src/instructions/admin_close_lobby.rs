@@ -4,7 +4,10 @@ use anchor_spl::token::{
 };
 use doge_o::get_authority;
 
-use crate::{error::GameError, state::LobbyState, utils::get_wsol_mint,
+use crate::{
+    error::GameError,
+    state::{LobbyState, TreasuryState},
+    utils::get_wsol_mint,
 };
 
 #[derive(Accounts)]
@@ -15,8 +18,12 @@ pub struct AdminCloseLobby<'info> {
     )]
     pub authority: Signer<'info>,
 
-    #[account(mut)]
-    pub treasury_account: SystemAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"treasury"],
+        bump = treasury_account.bump
+    )]
+    pub treasury_account: Box<Account<'info, TreasuryState>>,
 
     #[account(mut)]
     pub track_holder: SystemAccount<'info>,
@@ -61,8 +68,8 @@ pub struct AdminCloseLobby<'info> {
 
     #[account(
         mut,
-        constraint = lobby_wsol_token.mint.eq(&wsol_mint.key()),
-        constraint = treasury_wsol_token.owner.eq(&treasury_account.key())
+        constraint = treasury_wsol_token.mint.eq(&wsol_mint.key()),
+        constraint = treasury_wsol_token.owner.eq(&treasury_account.key()) @ GameError::InvalidTreasuryTokenAccount
     )]
     pub treasury_wsol_token: Box<Account<'info, TokenAccount>>,
 
@@ -161,6 +168,20 @@ pub fn handler(ctx: Context<AdminCloseLobby>) -> Result<()> {
             ctx.accounts.transfer_residual_sol_ctx(signer_seeds),
             lobby_sol_balance,
         )?;
+
+        let treasury_wsol_token = ctx.accounts.treasury_wsol_token.key();
+        let treasury_wsol_ledger = ctx
+            .accounts
+            .treasury_account
+            .ledger_mut(&ctx.accounts.wsol_mint.key())?;
+        require!(
+            treasury_wsol_ledger.vault.eq(&treasury_wsol_token),
+            GameError::InvalidTreasuryTokenAccount
+        );
+        treasury_wsol_ledger.residual_swept = treasury_wsol_ledger
+            .residual_swept
+            .checked_add(lobby_sol_balance)
+            .ok_or(GameError::MathOverflow)?;
     }
 
     if lobby_entry_fee_balance != 0 {
@@ -1,10 +1,9 @@
 use anchor_lang::prelude::*;
 use doge_o::get_authority;
 
-use crate::state::RaceState;
+use crate::{error::GameError, state::RaceState, utils::check_discriminator};
 
 #[derive(Accounts)]
-#[instruction(race_started: u64)]
 pub struct AdminCloseRaceState<'info> {
     #[account(
         mut,
@@ -12,14 +11,28 @@ pub struct AdminCloseRaceState<'info> {
     )]
     pub authority: Signer<'info>,
 
+    /// CHECK: Discriminator is validated in the handler. Legacy records predate the current layout
     #[account(
         mut,
-        close = authority,
+        owner = crate::id()
     )]
-    pub race_data_state: Account<'info, RaceState>,
+    pub race_data_state: UncheckedAccount<'info>,
 }
 
-pub fn handler(_: Context<AdminCloseRaceState>, _: u64) -> Result<()> {
+pub fn handler(ctx: Context<AdminCloseRaceState>) -> Result<()> {
     //require!(MAINTENANCE_MODE, GameError::GameNotInMaintenance);
+    let race_data_info = ctx.accounts.race_data_state.to_account_info();
+    let authority_info = ctx.accounts.authority.to_account_info();
+
+    check_discriminator::<RaceState>(&race_data_info)?;
+
+    let lamports = race_data_info.lamports();
+    **authority_info.try_borrow_mut_lamports()? = authority_info
+        .lamports()
+        .checked_add(lamports)
+        .ok_or(GameError::MathOverflow)?;
+    **race_data_info.try_borrow_mut_lamports()? = 0;
+    race_data_info.try_borrow_mut_data()?.fill(0);
+
     Ok(())
 }
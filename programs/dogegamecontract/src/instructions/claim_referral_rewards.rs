@@ -0,0 +1,107 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{transfer, Mint, Token, TokenAccount, Transfer};
+
+use crate::{
+    error::GameError,
+    state::{ReferrerState, TreasuryState},
+    utils::get_wsol_mint,
+};
+
+#[derive(Accounts)]
+pub struct ClaimReferralRewards<'info> {
+    pub referrer: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = referrer,
+        constraint = referrer_account.claimable > 0 @ GameError::NoReferralRewards,
+        seeds = [
+            b"referrer",
+            referrer.key().as_ref(),
+        ],
+        bump = referrer_account.bump
+    )]
+    pub referrer_account: Account<'info, ReferrerState>,
+
+    #[account(
+        mut,
+        seeds = [b"treasury"],
+        bump = treasury_account.bump
+    )]
+    pub treasury_account: Box<Account<'info, TreasuryState>>,
+
+    #[account(address = get_wsol_mint())]
+    pub wsol_mint: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        constraint = treasury_wsol_token.owner.eq(&treasury_account.key()) @ GameError::InvalidTreasuryTokenAccount,
+        constraint = treasury_wsol_token.mint.eq(&wsol_mint.key())
+    )]
+    pub treasury_wsol_token: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = referrer_wsol_token.owner.eq(&referrer.key()),
+        constraint = referrer_wsol_token.mint.eq(&wsol_mint.key())
+    )]
+    pub referrer_wsol_token: Box<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+pub fn handler(ctx: Context<ClaimReferralRewards>) -> Result<()> {
+    let amount = ctx.accounts.referrer_account.claimable;
+
+    require!(
+        ctx.accounts.treasury_wsol_token.amount >= amount,
+        GameError::InsufficientTreasuryBalance
+    );
+
+    let treasury_bump = ctx.accounts.treasury_account.bump;
+    let signer_seeds: &[&[&[u8]]] = &[&[b"treasury", &[treasury_bump]]];
+
+    let treasury_wsol_token = ctx.accounts.treasury_wsol_token.key();
+    let treasury_wsol_ledger = ctx
+        .accounts
+        .treasury_account
+        .ledger_mut(&ctx.accounts.wsol_mint.key())?;
+    require!(
+        treasury_wsol_ledger.vault.eq(&treasury_wsol_token),
+        GameError::InvalidTreasuryTokenAccount
+    );
+    treasury_wsol_ledger.referral_rewards_paid = treasury_wsol_ledger
+        .referral_rewards_paid
+        .checked_add(amount)
+        .ok_or(GameError::MathOverflow)?;
+
+    let treasury_account = &mut ctx.accounts.treasury_account;
+    treasury_account.referral_rewards_reserved = treasury_account
+        .referral_rewards_reserved
+        .checked_sub(amount)
+        .ok_or(GameError::ReferralRewardsNotReserved)?;
+
+    let referrer_account = &mut ctx.accounts.referrer_account;
+    referrer_account.claimable = 0;
+    referrer_account.total_claimed = referrer_account
+        .total_claimed
+        .checked_add(amount)
+        .ok_or(GameError::MathOverflow)?;
+
+    msg!("Claiming {} WSOL referral rewards", amount);
+
+    transfer(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.treasury_wsol_token.to_account_info(),
+                to: ctx.accounts.referrer_wsol_token.to_account_info(),
+                authority: ctx.accounts.treasury_account.to_account_info(),
+            },
+            signer_seeds,
+        ),
+        amount,
+    )?;
+
+    Ok(())
+}
@@ -6,7 +6,7 @@ use anchor_spl::token::{
 use crate::error::GameError;
 use crate::utils::*;
 
-use crate::state::LobbyState;
+use crate::state::{LobbyState, TreasuryState};
 
 #[derive(Accounts)]
 pub struct CloseLobby<'info> {
@@ -14,9 +14,11 @@ pub struct CloseLobby<'info> {
     pub track_holder: Signer<'info>,
 
     #[account(
-        address = get_treasury_address(),
+        mut,
+        seeds = [b"treasury"],
+        bump = treasury_account.bump
     )]
-    pub treasury_account: SystemAccount<'info>,
+    pub treasury_account: Box<Account<'info, TreasuryState>>,
 
     #[account(
         mut,
@@ -61,8 +63,8 @@ pub struct CloseLobby<'info> {
 
     #[account(
         mut,
-        constraint = lobby_wsol_token.mint.eq(&wsol_mint.key()),
-        constraint = treasury_wsol_token.owner.eq(&treasury_account.key())
+        constraint = treasury_wsol_token.mint.eq(&wsol_mint.key()),
+        constraint = treasury_wsol_token.owner.eq(&treasury_account.key()) @ GameError::InvalidTreasuryTokenAccount
     )]
     pub treasury_wsol_token: Box<Account<'info, TokenAccount>>,
 
@@ -166,6 +168,20 @@ pub fn handler(ctx: Context<CloseLobby>) -> Result<()> {
             ctx.accounts.transfer_residual_sol_ctx(signer_seeds),
             lobby_sol_balance,
         )?;
+
+        let treasury_wsol_token = ctx.accounts.treasury_wsol_token.key();
+        let treasury_wsol_ledger = ctx
+            .accounts
+            .treasury_account
+            .ledger_mut(&ctx.accounts.wsol_mint.key())?;
+        require!(
+            treasury_wsol_ledger.vault.eq(&treasury_wsol_token),
+            GameError::InvalidTreasuryTokenAccount
+        );
+        treasury_wsol_ledger.residual_swept = treasury_wsol_ledger
+            .residual_swept
+            .checked_add(lobby_sol_balance)
+            .ok_or(GameError::MathOverflow)?;
     }
 
     msg!("Closing Lobby entry fee token vault");
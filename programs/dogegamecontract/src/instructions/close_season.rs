@@ -0,0 +1,169 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::get_associated_token_address,
+    token::{transfer, Token, TokenAccount, Transfer},
+};
+
+use crate::{
+    error::GameError,
+    state::{SeasonState, TreasuryState},
+    utils::*,
+};
+
+#[derive(Accounts)]
+pub struct CloseSeason<'info> {
+    #[account(
+        mut,
+        constraint = !season_account.closed @ GameError::SeasonClosed,
+        seeds = [b"season", season_account.season_id.to_le_bytes().as_ref()],
+        bump = season_account.bump
+    )]
+    pub season_account: Box<Account<'info, SeasonState>>,
+
+    #[account(
+        mut,
+        address = season_account.prize_vault
+    )]
+    pub prize_vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"treasury"],
+        bump = treasury_account.bump
+    )]
+    pub treasury_account: Box<Account<'info, TreasuryState>>,
+
+    #[account(
+        mut,
+        constraint = treasury_prize_token.mint.eq(&season_account.prize_mint),
+        constraint = treasury_prize_token.owner.eq(&treasury_account.key()) @ GameError::InvalidTreasuryTokenAccount
+    )]
+    pub treasury_prize_token: Box<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+// Permissionless once the season ended. Remaining accounts are the prize mint ATAs of
+// the leaderboard's doge holders, in leaderboard order. Holders without one are skipped,
+// their prize, the rounding dust and anything else left in the vault go to the treasury
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, CloseSeason<'info>>) -> Result<()> {
+    let season_account = &mut ctx.accounts.season_account;
+    let prize_vault = &ctx.accounts.prize_vault;
+
+    require!(
+        (Clock::get()?.unix_timestamp as u64).ge(&season_account.end_time),
+        GameError::SeasonNotEnded
+    );
+    require!(
+        ctx.remaining_accounts.len() == season_account.leaderboard.len(),
+        GameError::InvalidSeasonAccount
+    );
+
+    let season_id_seed = season_account.season_id.to_le_bytes();
+    let signer_seeds: &[&[&[u8]]] = &[&[b"season", &season_id_seed, &[season_account.bump]]];
+    let prize_pool = prize_vault.amount;
+
+    msg!(
+        "Closing season {} with a prize pool of {}",
+        season_account.season_id,
+        prize_pool
+    );
+
+    let prizes = season_prizes(
+        prize_pool,
+        &season_account.prize_split_bps,
+        season_account.leaderboard.len(),
+    )?;
+    let mut prizes_paid = 0u64;
+
+    for ((entry, prize), holder_token) in season_account
+        .leaderboard
+        .iter()
+        .zip(prizes)
+        .zip(ctx.remaining_accounts.iter())
+    {
+        require!(
+            holder_token.key().eq(&get_associated_token_address(
+                &entry.doge_holder,
+                &season_account.prize_mint
+            )),
+            GameError::InvalidSeasonAccount
+        );
+        if prize == 0 {
+            continue;
+        }
+        if check_valid_ata(
+            &UncheckedAccount::try_from(holder_token.clone()),
+            &entry.doge_holder,
+            &season_account.prize_mint,
+        )
+        .is_err()
+        {
+            msg!(
+                "Skipping doge racer {}, its holder has no prize token account",
+                entry.doge_racer.to_string()
+            );
+            continue;
+        }
+
+        msg!(
+            "Paying {} to doge racer {} with {} points",
+            prize,
+            entry.doge_racer.to_string(),
+            entry.points
+        );
+        transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    authority: season_account.to_account_info(),
+                    from: prize_vault.to_account_info(),
+                    to: holder_token.clone(),
+                },
+                signer_seeds,
+            ),
+            prize,
+        )?;
+        prizes_paid = prizes_paid
+            .checked_add(prize)
+            .ok_or(GameError::MathOverflow)?;
+    }
+
+    let residual = prize_pool
+        .checked_sub(prizes_paid)
+        .ok_or(GameError::MathOverflow)?;
+    if residual > 0 {
+        msg!("Sweeping {} left in the prize vault to treasury", residual);
+        transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    authority: season_account.to_account_info(),
+                    from: prize_vault.to_account_info(),
+                    to: ctx.accounts.treasury_prize_token.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            residual,
+        )?;
+
+        let treasury_prize_token = ctx.accounts.treasury_prize_token.key();
+        let treasury_prize_ledger = ctx
+            .accounts
+            .treasury_account
+            .ledger_mut(&season_account.prize_mint)?;
+        require!(
+            treasury_prize_ledger.vault.eq(&treasury_prize_token),
+            GameError::InvalidTreasuryTokenAccount
+        );
+        treasury_prize_ledger.residual_swept = treasury_prize_ledger
+            .residual_swept
+            .checked_add(residual)
+            .ok_or(GameError::MathOverflow)?;
+    }
+
+    season_account.prize_pool = prize_pool;
+    season_account.closed = true;
+
+    Ok(())
+}
@@ -1,14 +1,17 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{transfer, Mint, Token, TokenAccount, Transfer};
+use anchor_spl::token::{burn, transfer, Burn, Mint, Token, TokenAccount, Transfer};
 use doge_o::{
-    cpi::{accounts::SetWinPercentage, set_win_percentage},
+    cpi::{
+        accounts::{AwardExperience, SetWinPercentage},
+        award_experience, set_win_percentage,
+    },
     get_authority,
     program::DogeO,
     DogeStats,
 };
 
 use crate::{
-    constants::{SOL_NETWORK_FEE, TRACK_OWNER_PCT},
+    constants::{LOBBY_STATE_VERSION, SOL_NETWORK_FEE},
     error::GameError,
     metadata::Metadata,
     state::*,
@@ -41,6 +44,7 @@ pub struct ConcludeRace<'info> {
     #[account(
         mut,
         constraint = lobby_account.race_started @ GameError::RaceNotStarted,
+        constraint = lobby_account.version == LOBBY_STATE_VERSION @ GameError::AccountNotMigrated,
         constraint = !is_lobby_empty(&lobby_account.racers) @ GameError::LobbyNotFull,
         seeds = [
             b"lobby", 
@@ -61,6 +65,7 @@ pub struct ConcludeRace<'info> {
     pub doge_mint: Box<Account<'info, Mint>>,
     pub doge_metadata: Account<'info, Metadata>,
     #[account(
+        mut,
         address = lobby_account.track_keys.entry_fee_mint
     )]
     pub entry_fee_mint: Box<Account<'info, Mint>>,
@@ -108,30 +113,101 @@ pub struct ConcludeRace<'info> {
     pub system_program: Program<'info, System>,
     pub doge_o_program: Program<'info, DogeO>,
 
+    /// CHECK: Doge O's config naming this program, validated by Doge O
+    pub game_config: UncheckedAccount<'info>,
+
     #[account(
         mut,
-        constraint = treasury_wsol_token.owner.eq(&treasury_address.key()),
+        constraint = treasury_wsol_token.owner.eq(&treasury_account.key()) @ GameError::InvalidTreasuryTokenAccount,
         constraint = treasury_wsol_token.mint.eq(&wsol_mint.key())
     )]
-    pub treasury_wsol_token: Account<'info, TokenAccount>,
+    pub treasury_wsol_token: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"treasury"],
+        bump = treasury_account.bump
+    )]
+    pub treasury_account: Box<Account<'info, TreasuryState>>,
+
+    #[account(
+        mut,
+        constraint = treasury_entry_fee_token.owner.eq(&treasury_account.key()) @ GameError::InvalidTreasuryTokenAccount,
+        constraint = treasury_entry_fee_token.mint.eq(&entry_fee_mint.key())
+    )]
+    pub treasury_entry_fee_token: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        seeds = [b"feeschedule"],
+        bump = fee_schedule_account.bump
+    )]
+    pub fee_schedule_account: Box<Account<'info, FeeScheduleState>>,
+
+    #[account(
+        seeds = [b"referralconfig"],
+        bump = referral_config_account.bump
+    )]
+    pub referral_config_account: Box<Account<'info, ReferralConfigState>>,
+
+    #[account(mut)]
+    /// CHECK: Only used when the racer has a referrer, validated in the handler
+    pub referrer_account: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"stakeconfig"],
+        bump = stake_config_account.bump
+    )]
+    pub stake_config_account: Box<Account<'info, StakeConfigState>>,
+
+    /// CHECK: Doge holder's stake position PDA, validated in the handler. May be uninitialized
+    pub doge_holder_stake_account: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"streakconfig"],
+        bump = streak_config_account.bump
+    )]
+    pub streak_config_account: Box<Account<'info, StreakConfigState>>,
+
+    /// CHECK: Season to score the race in, validated in the handler. Ignored when not an active season
+    #[account(mut)]
+    pub season_account: UncheckedAccount<'info>,
+
+    /// CHECK: Season prize vault, validated against the season in the handler
+    #[account(mut)]
+    pub season_prize_vault: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"race",
+            lobby_account.key().as_ref(),
+            lobby_account.race_count.to_le_bytes().as_ref(),
+        ],
+        bump = race_data_state.bump
+    )]
+    pub race_data_state: Box<Account<'info, RaceState>>,
 
-    #[account(address = get_treasury_address())]
-    pub treasury_address: SystemAccount<'info>,
     #[account(address = lobby_account.track_keys.track_holder)]
     pub track_holder: SystemAccount<'info>,
     pub doge_holder: SystemAccount<'info>,
 }
 
-pub fn handler(ctx: Context<ConcludeRace>, is_winner: bool, new_win_pct: u8) -> Result<()> {
+// Settles one racer of a race written out by record_race, at the placement it holds. The racer's
+// result has to be recorded first
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, ConcludeRace<'info>>) -> Result<()> {
     //require!(!MAINTENANCE_MODE, GameError::GameInMaintenance);
 
     // All accounts
     let treasury_wsol_token = &mut ctx.accounts.treasury_wsol_token;
+    let treasury_account = &mut ctx.accounts.treasury_account;
+    let treasury_entry_fee_token = &ctx.accounts.treasury_entry_fee_token;
 
     let lobby_account = &mut ctx.accounts.lobby_account;
+    let race_data_state = &mut ctx.accounts.race_data_state;
     let doge_racer_account = &mut ctx.accounts.doge_racer_account;
     let track_mint = ctx.accounts.track_mint.key();
     let entry_fee_mint = &ctx.accounts.entry_fee_mint;
+    let wsol_mint = &ctx.accounts.wsol_mint;
 
     let lobby_entry_fee_token = &mut ctx.accounts.lobby_entry_fee_token;
     let lobby_wsol_token = &mut ctx.accounts.lobby_wsol_token;
@@ -154,6 +230,28 @@ pub fn handler(ctx: Context<ConcludeRace>, is_winner: bool, new_win_pct: u8) ->
 
     require!(is_valid_racer, GameError::UnauthorizedRacer);
 
+    let placement = race_data_state
+        .results
+        .iter()
+        .position(|result| result.doge_racer.eq(&doge_racer_account.key()))
+        .ok_or(GameError::InvalidFinishingOrder)?;
+    let total_racers = race_data_state.results.len();
+    require!(
+        race_data_state.results[placement].recorded,
+        GameError::RacerResultNotRecorded
+    );
+    require!(
+        !race_data_state.results[placement].settled,
+        GameError::RacerResultAlreadySettled
+    );
+    let is_winner = placement == 0;
+    let mut payout: u64 = 0;
+
+    let mut active_season = load_active_season(
+        &ctx.accounts.season_account.to_account_info(),
+        Clock::get()?.unix_timestamp as u64,
+    )?;
+
     require!(
         is_doge_stats_valid(
             &ctx.accounts.init_authority.key(),
@@ -184,11 +282,10 @@ pub fn handler(ctx: Context<ConcludeRace>, is_winner: bool, new_win_pct: u8) ->
             &ctx.accounts.doge_holder.key().to_string()
         );
 
-        let total_entry_fee = lobby_account
-            .lobby_data
-            .entry_fee
-            .checked_mul(lobby_account.max_players.into())
-            .ok_or(GameError::MathOverflow)?;
+        let total_entry_fee =
+            get_lobby_entry_fee(&lobby_account.lobby_data, entry_fee_mint.decimals)?
+                .checked_mul(lobby_account.max_players.into())
+                .ok_or(GameError::MathOverflow)?;
 
         let total_network_fee = SOL_NETWORK_FEE
             .checked_mul(lobby_account.max_players.into())
@@ -218,15 +315,77 @@ pub fn handler(ctx: Context<ConcludeRace>, is_winner: bool, new_win_pct: u8) ->
             &[lobby_account.bump],
         ]];
 
-        // 1. Transferring Entry Fee Token to track owner (20%)
         let pool_share = if entry_fee_mint.key().eq(&get_wsol_mint()) {
             lobby_entry_fee_token_balance - total_network_fee
         } else {
             lobby_entry_fee_token_balance
         };
 
-        let track_owner_transfer_amount = percentage_of(pool_share, TRACK_OWNER_PCT)?;
+        let mut pool_split = split_pool(
+            pool_share,
+            &ctx.accounts.fee_schedule_account.fee_schedule,
+            entry_fee_mint.key().eq(&get_dtrk_mint()),
+        )?;
+
+        // Staked DTRK discounts the protocol rake in favour of the winner
+        if let Some(stake_position) = load_stake_position(
+            &ctx.accounts.doge_holder_stake_account.to_account_info(),
+            &doge_holder,
+        )? {
+            let rake_discount_bps = get_stake_rake_discount(
+                &stake_position,
+                &ctx.accounts.stake_config_account.tiers,
+                Clock::get()?.unix_timestamp as u64,
+            );
+
+            if rake_discount_bps > 0 {
+                let rake_discount = apply_rake_discount(&mut pool_split, rake_discount_bps)?;
+                msg!(
+                    "Applying {} Entry Fee Token stake rake discount",
+                    rake_discount
+                );
+            }
+        }
+
+        // Win streak milestones are paid out of a slice of the remaining protocol rake
+        let win_streak = doge_racer_account
+            .current_win_streak
+            .checked_add(1)
+            .ok_or(GameError::MathOverflow)?;
+        let streak_config = &ctx.accounts.streak_config_account.streak_config;
+
+        if is_streak_milestone(streak_config, win_streak) {
+            let streak_bonus = apply_rake_discount(&mut pool_split, streak_config.rake_share_bps)?;
+            msg!(
+                "Paying {} Entry Fee Token bonus for a {} race win streak",
+                streak_bonus,
+                win_streak
+            );
+        }
+
+        // Seasons in this entry fee mint take their slice of the remaining protocol rake
+        let season_share = match active_season.as_ref() {
+            Some(season) if season.prize_mint.eq(&entry_fee_mint.key()) => {
+                let season_share = bps_of(pool_split.treasury, season.rake_share_bps)?;
+                pool_split.treasury = pool_split
+                    .treasury
+                    .checked_sub(season_share)
+                    .ok_or(GameError::MathOverflow)?;
+                season_share
+            }
+            _ => 0,
+        };
+
+        msg!(
+            "Splitting pool of {} Entry Fee Token. Track owner {}, Treasury {}, Burn {}, Winner {}",
+            pool_share,
+            pool_split.track_owner,
+            pool_split.treasury,
+            pool_split.burn,
+            pool_split.winner
+        );
 
+        // 1. Transferring Entry Fee Token to track owner
         transfer(
             CpiContext::new_with_signer(
                 token_program.to_account_info(),
@@ -237,18 +396,10 @@ pub fn handler(ctx: Context<ConcludeRace>, is_winner: bool, new_win_pct: u8) ->
                 },
                 signer_seeds,
             ),
-            track_owner_transfer_amount,
+            pool_split.track_owner,
         )?;
 
-        let winner_transfer_amount = pool_share.saturating_sub(track_owner_transfer_amount);
-
-        msg!(
-            "Transferring {} Entry Fee Token to track owner and {} Entry Fee Token to winner",
-            track_owner_transfer_amount,
-            winner_transfer_amount
-        );
-
-        // 2. Transferring entry fee mint to winner (80%)
+        // 2. Transferring entry fee mint to winner
         transfer(
             CpiContext::new_with_signer(
                 token_program.to_account_info(),
@@ -259,9 +410,83 @@ pub fn handler(ctx: Context<ConcludeRace>, is_winner: bool, new_win_pct: u8) ->
                 },
                 signer_seeds,
             ),
-            winner_transfer_amount,
+            pool_split.winner,
         )?;
 
+        // 2a. Transferring protocol rake to treasury
+        if pool_split.treasury > 0 {
+            transfer(
+                CpiContext::new_with_signer(
+                    token_program.to_account_info(),
+                    Transfer {
+                        authority: lobby_account.to_account_info(),
+                        from: lobby_entry_fee_token.to_account_info(),
+                        to: treasury_entry_fee_token.to_account_info(),
+                    },
+                    signer_seeds,
+                ),
+                pool_split.treasury,
+            )?;
+
+            let treasury_entry_fee_ledger = treasury_account.ledger_mut(&entry_fee_mint.key())?;
+            require!(
+                treasury_entry_fee_ledger
+                    .vault
+                    .eq(&treasury_entry_fee_token.key()),
+                GameError::InvalidTreasuryTokenAccount
+            );
+            treasury_entry_fee_ledger.rake_collected = treasury_entry_fee_ledger
+                .rake_collected
+                .checked_add(pool_split.treasury)
+                .ok_or(GameError::MathOverflow)?;
+        }
+
+        // 2b. Burning DTRK share
+        if pool_split.burn > 0 {
+            burn(
+                CpiContext::new_with_signer(
+                    token_program.to_account_info(),
+                    Burn {
+                        authority: lobby_account.to_account_info(),
+                        from: lobby_entry_fee_token.to_account_info(),
+                        mint: entry_fee_mint.to_account_info(),
+                    },
+                    signer_seeds,
+                ),
+                pool_split.burn,
+            )?;
+        }
+
+        // 2c. Funding the season prize vault
+        if let (Some(season), true) = (active_season.as_mut(), season_share > 0) {
+            require!(
+                ctx.accounts
+                    .season_prize_vault
+                    .key()
+                    .eq(&season.prize_vault),
+                GameError::InvalidSeasonAccount
+            );
+
+            transfer(
+                CpiContext::new_with_signer(
+                    token_program.to_account_info(),
+                    Transfer {
+                        authority: lobby_account.to_account_info(),
+                        from: lobby_entry_fee_token.to_account_info(),
+                        to: ctx.accounts.season_prize_vault.to_account_info(),
+                    },
+                    signer_seeds,
+                ),
+                season_share,
+            )?;
+
+            season.prize_pool = season
+                .prize_pool
+                .checked_add(season_share)
+                .ok_or(GameError::MathOverflow)?;
+            season.exit(ctx.program_id)?;
+        }
+
         // 3. Transfer race WSOL fees to treasury
         msg!("Transferring network fees to treasury");
         transfer(
@@ -277,21 +502,85 @@ pub fn handler(ctx: Context<ConcludeRace>, is_winner: bool, new_win_pct: u8) ->
             total_network_fee,
         )?;
 
+        let treasury_wsol_ledger = treasury_account.ledger_mut(&wsol_mint.key())?;
+        require!(
+            treasury_wsol_ledger.vault.eq(&treasury_wsol_token.key()),
+            GameError::InvalidTreasuryTokenAccount
+        );
+        treasury_wsol_ledger.fees_collected = treasury_wsol_ledger
+            .fees_collected
+            .checked_add(total_network_fee)
+            .ok_or(GameError::MathOverflow)?;
+
+        // 3a. Completing the race record with where the pool went
+        race_data_state.results[0].payout = pool_split.winner;
+        race_data_state.track_owner_fee = pool_split.track_owner;
+        race_data_state.treasury_fee = pool_split.treasury;
+        race_data_state.burn_amount = pool_split.burn;
+        race_data_state.network_fee = total_network_fee;
+
+        payout = pool_split.winner;
         doge_racer_account.total_wins += 1;
     } else {
         doge_racer_account.total_losses += 1;
     }
 
-    if let Some(racer_index) =
-        find_racer_index(&lobby_account.racers, Some(doge_racer_account.key()))
-    {
-        msg!("Racer Index {}", racer_index);
+    doge_racer_account.record_streak(is_winner);
 
-        if racer_index == (lobby_account.max_players - 1).into() {
-            msg!("Resetting lobby {}!", lobby_account.key().to_string());
-            lobby_account.race_started = false;
-            lobby_account.racers = fill_empty_racers(lobby_account.max_players);
-        }
+    doge_racer_account.record_track_result(&lobby_account.lobby_data.track_type, placement);
+
+    let current_timestamp = Clock::get()?.unix_timestamp as u64;
+    doge_racer_account.push_recent_result(RecentRaceResult {
+        lobby: lobby_account.key(),
+        race_number: lobby_account.race_count,
+        placement: placement as u8,
+        payout,
+        timestamp: current_timestamp,
+    });
+
+    // Accruing referral rewards out of the racer's network fee
+    let referral_config = &ctx.accounts.referral_config_account.referral_config;
+
+    if is_referral_active(doge_racer_account, referral_config, current_timestamp) {
+        let referral_reward = bps_of(SOL_NETWORK_FEE, referral_config.share_bps)?;
+
+        let mut referrer_state = load_referrer_account(
+            &ctx.accounts.referrer_account.to_account_info(),
+            &doge_racer_account.referrer,
+        )?;
+
+        msg!(
+            "Accruing {} WSOL referral reward to {}",
+            referral_reward,
+            doge_racer_account.referrer.to_string()
+        );
+        referrer_state.claimable = referrer_state
+            .claimable
+            .checked_add(referral_reward)
+            .ok_or(GameError::MathOverflow)?;
+        referrer_state.total_earned = referrer_state
+            .total_earned
+            .checked_add(referral_reward)
+            .ok_or(GameError::MathOverflow)?;
+        referrer_state.exit(ctx.program_id)?;
+
+        treasury_account.referral_rewards_reserved = treasury_account
+            .referral_rewards_reserved
+            .checked_add(referral_reward)
+            .ok_or(GameError::MathOverflow)?;
+    }
+
+    // The lobby only moves on to its next race once every racer of this one has settled
+    race_data_state.results[placement].settled = true;
+
+    if race_data_state.results.iter().all(|result| result.settled) {
+        msg!("Resetting lobby {}!", lobby_account.key().to_string());
+        lobby_account.race_count = lobby_account
+            .race_count
+            .checked_add(1)
+            .ok_or(GameError::MathOverflow)?;
+        lobby_account.race_started = false;
+        lobby_account.racers = fill_empty_racers(lobby_account.max_players);
     }
 
     // 4. Clearing out racer's and lobby's data
@@ -300,18 +589,37 @@ pub fn handler(ctx: Context<ConcludeRace>, is_winner: bool, new_win_pct: u8) ->
     doge_racer_account.current_lobby_race = Pubkey::default();
     doge_racer_account.doge_holder_entry_fee_token = Pubkey::default();
 
-    // 5. Updating corresponding doge o stats
-    set_win_percentage(
+    // 5. Updating corresponding doge o stats. Doge O reads the win percentage off the racer
+    // record, so it has to be written out first
+    doge_racer_account.exit(ctx.program_id)?;
+    set_win_percentage(CpiContext::new(
+        ctx.accounts.doge_o_program.to_account_info(),
+        SetWinPercentage {
+            doge_mint: ctx.accounts.doge_mint.to_account_info(),
+            doge_stats: ctx.accounts.doge_o_pda.to_account_info(),
+            authority: ctx.accounts.authority.to_account_info(),
+            init_authority: ctx.accounts.init_authority.to_account_info(),
+            game_config: ctx.accounts.game_config.to_account_info(),
+            doge_racer: doge_racer_account.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+        },
+    ))?;
+
+    // 6. Awarding experience for the racer's placement
+    award_experience(
         CpiContext::new(
             ctx.accounts.doge_o_program.to_account_info(),
-            SetWinPercentage {
+            AwardExperience {
                 doge_mint: ctx.accounts.doge_mint.to_account_info(),
                 doge_stats: ctx.accounts.doge_o_pda.to_account_info(),
                 authority: ctx.accounts.authority.to_account_info(),
                 init_authority: ctx.accounts.init_authority.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
             },
         ),
-        new_win_pct,
+        placement as u8,
+        total_racers as u8,
+        lobby_account.lobby_data.min_class,
     )?;
 
     Ok(())
@@ -5,6 +5,7 @@ use crate::state::{
 use crate::utils::*;
 use crate::{constants::*, error::GameError};
 use anchor_lang::{prelude::*, solana_program::program_pack::IsInitialized};
+use doge_o::rating::MAX_RATED_RACERS;
 use anchor_spl::associated_token::{create, get_associated_token_address, Create};
 use anchor_spl::{
     associated_token::AssociatedToken,
@@ -52,7 +53,8 @@ pub struct CreateLobby<'info> {
             b"entryfeerequirements",
             entry_fee_mint.key().as_ref(),
         ],
-        bump = entry_fee_requirements_account.bump
+        bump = entry_fee_requirements_account.bump,
+        constraint = entry_fee_requirements_account.version == ENTRY_FEE_REQUIREMENTS_VERSION @ GameError::AccountNotMigrated
     )]
     pub entry_fee_requirements_account: Box<Account<'info, EntryFeeRequirementsState>>,
 
@@ -104,6 +106,7 @@ impl<'info> CreateLobby<'info> {
             is_lobby_metadata_valid(lobby_metadata, entry_fee_requirements)?,
             GameError::InvalidLobbyMetadata
         );
+        get_lobby_entry_fee(lobby_metadata, self.entry_fee_mint.decimals)?;
 
         let lobby_entry_fee_token =
             get_associated_token_address(&self.lobby_account.key(), &self.entry_fee_mint.key());
@@ -161,7 +164,11 @@ pub fn handler(
     max_players: u8,
 ) -> Result<()> {
     //require!(!MAINTENANCE_MODE, GameError::GameInMaintenance);
-    require!(max_players >= 2, GameError::InvalidMaxPlayersCount);
+    // Every race is rated, so lobbies can't outgrow what a rating update takes
+    require!(
+        (2..=MAX_RATED_RACERS).contains(&usize::from(max_players)),
+        GameError::InvalidMaxPlayersCount
+    );
 
     let entry_fee_requirements = &ctx
         .accounts
@@ -209,6 +216,8 @@ pub fn handler(
     lobby_state_account.max_players = max_players;
     lobby_state_account.race_started = false;
     lobby_state_account.lobby_data = lobby_metadata;
+    lobby_state_account.version = LOBBY_STATE_VERSION;
+    lobby_state_account.race_count = 0;
 
     lobby_state_account.racers = fill_empty_racers(max_players);
     lobby_state_account.track_keys = TrackKeys {
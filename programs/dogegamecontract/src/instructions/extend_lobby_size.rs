@@ -3,9 +3,11 @@ use anchor_lang::{
     solana_program::{program::invoke, system_instruction::transfer},
 };
 use anchor_spl::token::Mint;
-use doge_o::get_authority;
+use doge_o::{get_authority, rating::MAX_RATED_RACERS};
 
-use crate::{constants::lobby_account_size, state::LobbyState, utils::get_dtrk_mint};
+use crate::{
+    constants::lobby_account_size, error::GameError, state::LobbyState, utils::get_dtrk_mint,
+};
 
 #[derive(Accounts, Clone)]
 pub struct ExtendLobbySize<'info> {
@@ -31,6 +33,11 @@ pub fn handler(ctx: Context<ExtendLobbySize>) -> Result<()> {
     let lobby_account = &mut ctx.accounts.lobby_account;
     let lobby_account_info = lobby_account.to_account_info();
 
+    require!(
+        usize::from(lobby_account.max_players) <= MAX_RATED_RACERS,
+        GameError::InvalidMaxPlayersCount
+    );
+
     let lobby_data_size = lobby_account_info.data.borrow().len();
     msg!("Lobby data size {}", lobby_data_size);
 
@@ -3,7 +3,7 @@ use anchor_spl::token::{transfer, Mint, Token, TokenAccount, Transfer as TokenTr
 use doge_o::{get_authority, DogeStats};
 
 use crate::{
-    constants::{SOL_NETWORK_FEE, STALE_RACERS_FLUSH_COOLDOWN},
+    constants::{LOBBY_STATE_VERSION, SOL_NETWORK_FEE, STALE_RACERS_FLUSH_COOLDOWN},
     error::GameError,
     metadata::Metadata,
     state::{DogeRacerState, LobbyState},
@@ -47,7 +47,8 @@ pub struct FlushStaleRacer<'info> {
             track_mint.key().as_ref(),
         ],
         bump = lobby_account.bump,
-        constraint = lobby_account.race_started.eq(&false) @ GameError::RaceAlreadyStarted
+        constraint = lobby_account.race_started.eq(&false) @ GameError::RaceAlreadyStarted,
+        constraint = lobby_account.version == LOBBY_STATE_VERSION @ GameError::AccountNotMigrated
     )]
     pub lobby_account: Box<Account<'info, LobbyState>>,
 
@@ -100,7 +101,6 @@ pub struct FlushStaleRacer<'info> {
     #[account(
         mut,
         constraint = lobby_entry_fee_token.key().eq(&lobby_account.track_keys.lobby_entry_fee_token) @ GameError::InvalidLobbyTokenAccount,
-        constraint = lobby_entry_fee_token.amount.ge(&lobby_account.lobby_data.entry_fee) @ GameError::InsufficientEntryFeeTokenBalance,
     )]
     pub lobby_entry_fee_token: Account<'info, TokenAccount>,
 
@@ -140,6 +140,16 @@ pub fn handler(ctx: Context<FlushStaleRacer>) -> Result<()> {
         GameError::InvalidDogeStats
     );
 
+    let entry_fee_token_joining_fee = get_lobby_entry_fee(
+        &lobby_account.lobby_data,
+        ctx.accounts.entry_fee_mint.decimals,
+    )?;
+
+    require!(
+        lobby_entry_fee_token.amount >= entry_fee_token_joining_fee,
+        GameError::InsufficientEntryFeeTokenBalance
+    );
+
     if let Some(racer_index) =
         find_racer_index(&lobby_account.racers, Some(doge_racer_account.key()))
     {
@@ -177,8 +187,6 @@ pub fn handler(ctx: Context<FlushStaleRacer>) -> Result<()> {
         )?;
 
         // 2. Entry fee token transfer
-        let entry_fee_token_joining_fee = lobby_account.lobby_data.entry_fee;
-
         transfer(
             CpiContext::new_with_signer(
                 ctx.accounts.token_program.to_account_info(),
@@ -1,4 +1,9 @@
-use crate::{constants::*, error::GameError, state::EntryFeeRequirements, utils::get_wsol_mint};
+use crate::{
+    constants::*,
+    error::GameError,
+    state::EntryFeeRequirements,
+    utils::{get_wsol_mint, is_entry_fee_requirements_representable},
+};
 use anchor_lang::prelude::*;
 use anchor_spl::token::Mint;
 use doge_o::get_authority;
@@ -30,17 +35,24 @@ pub struct InitEntryFeeRequirements<'info> {
 
 pub fn handler(
     ctx: Context<InitEntryFeeRequirements>,
-    EntryFeeRequirements {
+    entry_fee_requirements: EntryFeeRequirements,
+) -> Result<()> {
+    let entry_fee_requirements_state = &mut ctx.accounts.entry_fee_requirements_account;
+    let entry_fee_mint = &ctx.accounts.entry_fee_mint;
+
+    require!(
+        is_entry_fee_requirements_representable(&entry_fee_requirements, entry_fee_mint.decimals),
+        GameError::InvalidEntryFeePrecision
+    );
+
+    let EntryFeeRequirements {
         min_fee,
         max_class_1_fee,
         max_class_2_fee,
         max_class_3_fee,
         max_class_4_fee,
         max_class_5_fee,
-    }: EntryFeeRequirements,
-) -> Result<()> {
-    let entry_fee_requirements_state = &mut ctx.accounts.entry_fee_requirements_account;
-    let entry_fee_mint = &ctx.accounts.entry_fee_mint;
+    } = entry_fee_requirements;
 
     if entry_fee_mint.key().ne(&get_wsol_mint()) && entry_fee_mint.supply <= 1 {
         return err!(GameError::CannotAcceptNFTAsEntryFee);
@@ -48,6 +60,7 @@ pub fn handler(
 
     entry_fee_requirements_state.bump = *ctx.bumps.get("entry_fee_requirements_account").unwrap();
     entry_fee_requirements_state.entry_fee_mint = entry_fee_mint.key();
+    entry_fee_requirements_state.version = ENTRY_FEE_REQUIREMENTS_VERSION;
 
     require!(min_fee > 0, GameError::InvalidEntryFeeRequirement);
     entry_fee_requirements_state.entry_fee_requirements.min_fee = min_fee;
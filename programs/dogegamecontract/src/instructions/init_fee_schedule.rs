@@ -0,0 +1,42 @@
+use anchor_lang::prelude::*;
+use doge_o::get_authority;
+
+use crate::{
+    constants::FEE_SCHEDULE_SIZE,
+    error::GameError,
+    state::{FeeSchedule, FeeScheduleState},
+    utils::is_fee_schedule_valid,
+};
+
+#[derive(Accounts)]
+pub struct InitFeeSchedule<'info> {
+    #[account(
+        mut,
+        address = get_authority()
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + FEE_SCHEDULE_SIZE,
+        seeds = [b"feeschedule"],
+        bump
+    )]
+    pub fee_schedule_account: Account<'info, FeeScheduleState>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<InitFeeSchedule>, fee_schedule: FeeSchedule) -> Result<()> {
+    require!(
+        is_fee_schedule_valid(&fee_schedule),
+        GameError::InvalidFeeSchedule
+    );
+
+    let fee_schedule_account = &mut ctx.accounts.fee_schedule_account;
+
+    fee_schedule_account.bump = *ctx.bumps.get("fee_schedule_account").unwrap();
+    fee_schedule_account.fee_schedule = fee_schedule;
+
+    Ok(())
+}
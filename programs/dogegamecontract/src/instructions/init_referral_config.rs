@@ -0,0 +1,42 @@
+use anchor_lang::prelude::*;
+use doge_o::get_authority;
+
+use crate::{
+    constants::REFERRAL_CONFIG_SIZE,
+    error::GameError,
+    state::{ReferralConfig, ReferralConfigState},
+    utils::is_referral_config_valid,
+};
+
+#[derive(Accounts)]
+pub struct InitReferralConfig<'info> {
+    #[account(
+        mut,
+        address = get_authority()
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + REFERRAL_CONFIG_SIZE,
+        seeds = [b"referralconfig"],
+        bump
+    )]
+    pub referral_config_account: Account<'info, ReferralConfigState>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<InitReferralConfig>, referral_config: ReferralConfig) -> Result<()> {
+    require!(
+        is_referral_config_valid(&referral_config),
+        GameError::InvalidReferralConfig
+    );
+
+    let referral_config_account = &mut ctx.accounts.referral_config_account;
+
+    referral_config_account.bump = *ctx.bumps.get("referral_config_account").unwrap();
+    referral_config_account.referral_config = referral_config;
+
+    Ok(())
+}
@@ -0,0 +1,31 @@
+use anchor_lang::prelude::*;
+
+use crate::{constants::REFERRER_SIZE, state::ReferrerState};
+
+#[derive(Accounts)]
+pub struct InitReferrer<'info> {
+    #[account(mut)]
+    pub referrer: Signer<'info>,
+
+    #[account(
+        init,
+        payer = referrer,
+        space = 8 + REFERRER_SIZE,
+        seeds = [
+            b"referrer",
+            referrer.key().as_ref(),
+        ],
+        bump
+    )]
+    pub referrer_account: Account<'info, ReferrerState>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<InitReferrer>) -> Result<()> {
+    let referrer_account = &mut ctx.accounts.referrer_account;
+
+    referrer_account.bump = *ctx.bumps.get("referrer_account").unwrap();
+    referrer_account.referrer = ctx.accounts.referrer.key();
+
+    Ok(())
+}
@@ -0,0 +1,33 @@
+use anchor_lang::prelude::*;
+use doge_o::get_authority;
+
+use crate::{constants::SEASONS_STATE_SIZE, state::SeasonsState};
+
+#[derive(Accounts)]
+pub struct InitSeasons<'info> {
+    #[account(
+        mut,
+        address = get_authority()
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + SEASONS_STATE_SIZE,
+        seeds = [b"seasons"],
+        bump
+    )]
+    pub seasons_account: Account<'info, SeasonsState>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<InitSeasons>) -> Result<()> {
+    let seasons_account = &mut ctx.accounts.seasons_account;
+
+    seasons_account.bump = *ctx.bumps.get("seasons_account").unwrap();
+    seasons_account.season_count = 0;
+    seasons_account.last_end_time = 0;
+
+    Ok(())
+}
@@ -0,0 +1,40 @@
+use anchor_lang::prelude::*;
+use doge_o::get_authority;
+
+use crate::{
+    constants::STAKE_CONFIG_SIZE,
+    error::GameError,
+    state::{StakeConfigState, StakeTier},
+    utils::is_stake_config_valid,
+};
+
+#[derive(Accounts)]
+pub struct InitStakeConfig<'info> {
+    #[account(
+        mut,
+        address = get_authority()
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + STAKE_CONFIG_SIZE,
+        seeds = [b"stakeconfig"],
+        bump
+    )]
+    pub stake_config_account: Account<'info, StakeConfigState>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<InitStakeConfig>, tiers: Vec<StakeTier>) -> Result<()> {
+    require!(is_stake_config_valid(&tiers), GameError::InvalidStakeConfig);
+
+    let stake_config_account = &mut ctx.accounts.stake_config_account;
+
+    stake_config_account.bump = *ctx.bumps.get("stake_config_account").unwrap();
+    stake_config_account.total_staked = 0;
+    stake_config_account.tiers = tiers;
+
+    Ok(())
+}
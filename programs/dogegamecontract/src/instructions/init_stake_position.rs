@@ -0,0 +1,70 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::{create, get_associated_token_address, AssociatedToken, Create},
+    token::{Mint, Token},
+};
+
+use crate::{constants::STAKE_POSITION_SIZE, state::StakePosition, utils::get_dtrk_mint};
+
+#[derive(Accounts)]
+pub struct InitStakePosition<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + STAKE_POSITION_SIZE,
+        seeds = [
+            b"stake",
+            owner.key().as_ref(),
+        ],
+        bump
+    )]
+    pub stake_account: Account<'info, StakePosition>,
+
+    #[account(address = get_dtrk_mint())]
+    pub dtrk_mint: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        address = get_associated_token_address(&stake_account.key(), &dtrk_mint.key())
+    )]
+    /// CHECK: Address is validated against the stake position ATA
+    pub stake_vault: UncheckedAccount<'info>,
+
+    // Sysvar
+    pub rent: Sysvar<'info, Rent>,
+
+    // Programs
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<InitStakePosition>) -> Result<()> {
+    msg!("Creating Stake vault");
+    create(CpiContext::new(
+        ctx.accounts.associated_token_program.to_account_info(),
+        Create {
+            payer: ctx.accounts.owner.to_account_info(),
+            associated_token: ctx.accounts.stake_vault.to_account_info(),
+            authority: ctx.accounts.stake_account.to_account_info(),
+            mint: ctx.accounts.dtrk_mint.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+            rent: ctx.accounts.rent.to_account_info(),
+        },
+    ))?;
+
+    let stake_account = &mut ctx.accounts.stake_account;
+
+    stake_account.bump = *ctx.bumps.get("stake_account").unwrap();
+    stake_account.owner = ctx.accounts.owner.key();
+    stake_account.vault = ctx.accounts.stake_vault.key();
+    stake_account.amount = 0;
+    stake_account.locked_at = 0;
+    stake_account.unlock_at = 0;
+
+    Ok(())
+}
@@ -0,0 +1,42 @@
+use anchor_lang::prelude::*;
+use doge_o::get_authority;
+
+use crate::{
+    constants::STREAK_CONFIG_SIZE,
+    error::GameError,
+    state::{StreakConfig, StreakConfigState},
+    utils::is_streak_config_valid,
+};
+
+#[derive(Accounts)]
+pub struct InitStreakConfig<'info> {
+    #[account(
+        mut,
+        address = get_authority()
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + STREAK_CONFIG_SIZE,
+        seeds = [b"streakconfig"],
+        bump
+    )]
+    pub streak_config_account: Account<'info, StreakConfigState>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<InitStreakConfig>, streak_config: StreakConfig) -> Result<()> {
+    require!(
+        is_streak_config_valid(&streak_config),
+        GameError::InvalidStreakConfig
+    );
+
+    let streak_config_account = &mut ctx.accounts.streak_config_account;
+
+    streak_config_account.bump = *ctx.bumps.get("streak_config_account").unwrap();
+    streak_config_account.streak_config = streak_config;
+
+    Ok(())
+}
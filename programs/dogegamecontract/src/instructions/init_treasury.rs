@@ -0,0 +1,34 @@
+use anchor_lang::prelude::*;
+use doge_o::get_authority;
+
+use crate::{constants::TREASURY_STATE_SIZE, state::TreasuryState};
+
+#[derive(Accounts)]
+pub struct InitTreasury<'info> {
+    #[account(
+        mut,
+        address = get_authority()
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + TREASURY_STATE_SIZE,
+        seeds = [b"treasury"],
+        bump
+    )]
+    pub treasury_account: Account<'info, TreasuryState>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<InitTreasury>) -> Result<()> {
+    let treasury_account = &mut ctx.accounts.treasury_account;
+
+    treasury_account.bump = *ctx.bumps.get("treasury_account").unwrap();
+    treasury_account.ledgers = vec![];
+
+    msg!("Treasury initialized at {}", treasury_account.key().to_string());
+
+    Ok(())
+}
@@ -3,7 +3,7 @@ use anchor_spl::token::{transfer, Mint, Token, TokenAccount, Transfer as TokenTr
 use doge_o::DogeStats;
 
 use crate::{
-    constants::{SOL_NETWORK_FEE},
+    constants::{LOBBY_STATE_VERSION, SOL_NETWORK_FEE},
     error::GameError,
     metadata::Metadata,
     state::{DogeRacerState, LobbyState},
@@ -45,6 +45,7 @@ pub struct JoinRace<'info> {
         ],
         bump = lobby_account.bump,
         constraint = lobby_account.race_started.eq(&false) @ GameError::RaceAlreadyStarted,
+        constraint = lobby_account.version == LOBBY_STATE_VERSION @ GameError::AccountNotMigrated,
         constraint = is_lobby_empty(&lobby_account.racers).eq(&true) @ GameError::LobbyFull
     )]
     pub lobby_account: Box<Account<'info, LobbyState>>,
@@ -96,7 +97,6 @@ pub struct JoinRace<'info> {
         mut,
         constraint = doge_holder_entry_fee_token.owner.eq(&doge_holder.key()),
         constraint = doge_holder_entry_fee_token.mint.eq(&entry_fee_mint.key()),
-        constraint = doge_holder_entry_fee_token.amount.ge(&lobby_account.lobby_data.entry_fee) @ GameError::InsufficientEntryFeeTokenBalance,
     )]
     pub doge_holder_entry_fee_token: Box<Account<'info, TokenAccount>>,
 
@@ -132,6 +132,16 @@ pub fn handler(ctx: Context<JoinRace>) -> Result<()> {
         GameError::InvalidDogeStats
     );
 
+    let entry_fee_mint_joining_fee = get_lobby_entry_fee(
+        &lobby_account.lobby_data,
+        ctx.accounts.entry_fee_mint.decimals,
+    )?;
+
+    require!(
+        doge_holder_entry_fee_token.amount >= entry_fee_mint_joining_fee,
+        GameError::InsufficientEntryFeeTokenBalance
+    );
+
     if let Some(racer_index) = find_racer_index(&lobby_account.racers, None) {
         // State changes
         // 1. Adding to lobby account racers
@@ -164,8 +174,6 @@ pub fn handler(ctx: Context<JoinRace>) -> Result<()> {
         )?;
 
         // Transferring Entry Fee Mint
-        let entry_fee_mint_joining_fee = lobby_account.lobby_data.entry_fee;
-
         transfer(
             CpiContext::new(
                 ctx.accounts.token_program.to_account_info(),
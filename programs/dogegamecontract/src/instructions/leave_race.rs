@@ -3,7 +3,7 @@ use anchor_spl::token::{transfer, Mint, Token, TokenAccount, Transfer as TokenTr
 use doge_o::DogeStats;
 
 use crate::{
-    constants::{SOL_NETWORK_FEE},
+    constants::{LOBBY_STATE_VERSION, SOL_NETWORK_FEE},
     error::GameError,
     metadata::Metadata,
     state::{DogeRacerState, LobbyState},
@@ -44,7 +44,8 @@ pub struct LeaveRace<'info> {
             track_mint.key().as_ref(),
         ],
         bump = lobby_account.bump,
-        constraint = lobby_account.race_started.eq(&false) @ GameError::RaceAlreadyStarted
+        constraint = lobby_account.race_started.eq(&false) @ GameError::RaceAlreadyStarted,
+        constraint = lobby_account.version == LOBBY_STATE_VERSION @ GameError::AccountNotMigrated
     )]
     pub lobby_account: Box<Account<'info, LobbyState>>,
 
@@ -104,7 +105,6 @@ pub struct LeaveRace<'info> {
     #[account(
         mut,
         address = lobby_account.track_keys.lobby_entry_fee_token @ GameError::InvalidLobbyTokenAccount,
-        constraint = lobby_entry_fee_token.amount.ge(&lobby_account.lobby_data.entry_fee) @ GameError::InsufficientEntryFeeTokenBalance,
     )]
     pub lobby_entry_fee_token: Account<'info, TokenAccount>,
 
@@ -135,6 +135,16 @@ pub fn handler(ctx: Context<LeaveRace>) -> Result<()> {
         GameError::InvalidDogeStats
     );
 
+    let entry_fee_token_joining_fee = get_lobby_entry_fee(
+        &lobby_account.lobby_data,
+        ctx.accounts.entry_fee_mint.decimals,
+    )?;
+
+    require!(
+        lobby_entry_fee_token.amount >= entry_fee_token_joining_fee,
+        GameError::InsufficientEntryFeeTokenBalance
+    );
+
     if let Some(racer_index) =
         find_racer_index(&lobby_account.racers, Some(doge_racer_account.key()))
     {
@@ -170,8 +180,6 @@ pub fn handler(ctx: Context<LeaveRace>) -> Result<()> {
         )?;
 
         // 2. Entry fee token transfer
-        let entry_fee_token_joining_fee = lobby_account.lobby_data.entry_fee;
-
         transfer(
             CpiContext::new_with_signer(
                 ctx.accounts.token_program.to_account_info(),
@@ -0,0 +1,37 @@
+use anchor_lang::prelude::*;
+use doge_o::get_authority;
+
+use crate::{
+    constants::DOGE_RACER_SIZE,
+    state::DogeRacerState,
+    utils::{check_discriminator, grow_account},
+};
+
+#[derive(Accounts)]
+pub struct MigrateDogeRacer<'info> {
+    #[account(mut, address = get_authority())]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        owner = crate::id()
+    )]
+    /// CHECK: Discriminator is validated in the handler, the account may not fit the current layout yet
+    pub doge_racer_account: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+// Grows doge racer accounts created with an older layout to the current size.
+// New fields are appended at the end and zero initialized.
+pub fn handler(ctx: Context<MigrateDogeRacer>) -> Result<()> {
+    let doge_racer_account_info = ctx.accounts.doge_racer_account.to_account_info();
+
+    check_discriminator::<DogeRacerState>(&doge_racer_account_info)?;
+
+    grow_account(
+        &doge_racer_account_info,
+        &ctx.accounts.authority.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        8 + DOGE_RACER_SIZE,
+    )
+}
@@ -0,0 +1,62 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::Mint;
+use doge_o::get_authority;
+
+use crate::{
+    constants::ENTRY_FEE_REQUIREMENTS_VERSION,
+    error::GameError,
+    state::{EntryFeeRequirements, EntryFeeRequirementsState},
+    utils::convert_to_entry_fee_without_decimals,
+};
+
+#[derive(Accounts)]
+pub struct MigrateEntryFeeRequirements<'info> {
+    #[account(
+        address = get_authority()
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = entry_fee_requirements_account.version == 0 @ GameError::InvalidEntryFeeRequirement,
+        seeds = [
+            b"entryfeerequirements",
+            entry_fee_mint.key().as_ref(),
+        ],
+        bump = entry_fee_requirements_account.bump,
+    )]
+    pub entry_fee_requirements_account: Account<'info, EntryFeeRequirementsState>,
+    pub entry_fee_mint: Account<'info, Mint>,
+}
+
+// Converts legacy base unit requirements to ENTRY_FEE_DECIMALS precision
+pub fn handler(ctx: Context<MigrateEntryFeeRequirements>) -> Result<()> {
+    let decimals = ctx.accounts.entry_fee_mint.decimals;
+    let entry_fee_requirements_state = &mut ctx.accounts.entry_fee_requirements_account;
+
+    let convert = |fee: u64| {
+        convert_to_entry_fee_without_decimals(fee, decimals)
+            .map_err(|_| error!(GameError::MathOverflow))
+    };
+
+    let EntryFeeRequirements {
+        min_fee,
+        max_class_1_fee,
+        max_class_2_fee,
+        max_class_3_fee,
+        max_class_4_fee,
+        max_class_5_fee,
+    } = entry_fee_requirements_state.entry_fee_requirements;
+
+    entry_fee_requirements_state.entry_fee_requirements = EntryFeeRequirements {
+        min_fee: convert(min_fee)?,
+        max_class_1_fee: convert(max_class_1_fee)?,
+        max_class_2_fee: convert(max_class_2_fee)?,
+        max_class_3_fee: convert(max_class_3_fee)?,
+        max_class_4_fee: convert(max_class_4_fee)?,
+        max_class_5_fee: convert(max_class_5_fee)?,
+    };
+    entry_fee_requirements_state.version = ENTRY_FEE_REQUIREMENTS_VERSION;
+
+    Ok(())
+}
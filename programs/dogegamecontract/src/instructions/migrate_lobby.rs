@@ -0,0 +1,75 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::Mint;
+use doge_o::get_authority;
+
+use crate::{
+    constants::{lobby_account_size, LOBBY_STATE_VERSION},
+    error::GameError,
+    state::LobbyState,
+    utils::{check_discriminator, convert_to_entry_fee_without_decimals, grow_account},
+};
+
+#[derive(Accounts)]
+pub struct MigrateLobby<'info> {
+    #[account(mut, address = get_authority())]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        owner = crate::id()
+    )]
+    /// CHECK: Discriminator is validated in the handler, the account may not fit the current layout yet
+    pub lobby_account: UncheckedAccount<'info>,
+
+    pub entry_fee_mint: Account<'info, Mint>,
+    pub system_program: Program<'info, System>,
+}
+
+// Grows lobby accounts to the current layout and converts the legacy base unit
+// entry fee to ENTRY_FEE_DECIMALS precision. Race counts of grown lobbies start at 0,
+// earlier races stay under their legacy `racedata` addresses
+pub fn handler(ctx: Context<MigrateLobby>) -> Result<()> {
+    let lobby_account_info = ctx.accounts.lobby_account.to_account_info();
+
+    check_discriminator::<LobbyState>(&lobby_account_info)?;
+
+    // Discriminator (8) + bump (1) precede max_players
+    let max_players = lobby_account_info.try_borrow_data()?[9];
+
+    grow_account(
+        &lobby_account_info,
+        &ctx.accounts.authority.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        8 + lobby_account_size(max_players),
+    )?;
+
+    let mut lobby_account = Account::<LobbyState>::try_from(&lobby_account_info)?;
+
+    require!(
+        lobby_account
+            .track_keys
+            .entry_fee_mint
+            .eq(&ctx.accounts.entry_fee_mint.key()),
+        GameError::InvalidEntryFeeMint
+    );
+
+    if lobby_account.version == 0 {
+        let entry_fee = convert_to_entry_fee_without_decimals(
+            lobby_account.lobby_data.entry_fee,
+            ctx.accounts.entry_fee_mint.decimals,
+        )
+        .map_err(|_| error!(GameError::MathOverflow))?;
+
+        msg!(
+            "Converting lobby entry fee {} to {}",
+            lobby_account.lobby_data.entry_fee,
+            entry_fee
+        );
+        lobby_account.lobby_data.entry_fee = entry_fee;
+    }
+
+    lobby_account.version = LOBBY_STATE_VERSION;
+    lobby_account.exit(ctx.program_id)?;
+
+    Ok(())
+}
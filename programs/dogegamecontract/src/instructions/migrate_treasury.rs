@@ -0,0 +1,77 @@
+use anchor_lang::prelude::*;
+use doge_o::get_authority;
+
+use crate::{
+    constants::TREASURY_STATE_SIZE,
+    error::GameError,
+    state::{ReferrerState, TreasuryState},
+    utils::{check_discriminator, grow_account},
+};
+
+#[derive(Accounts)]
+pub struct MigrateTreasury<'info> {
+    #[account(mut, address = get_authority())]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"treasury"],
+        bump
+    )]
+    /// CHECK: Discriminator is validated in the handler, the account may not fit the current layout yet
+    pub treasury_account: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+// Grows the treasury to the current layout and reserves the WSOL referrers can still claim, which
+// the treasury did not track before. Remaining accounts are referrer accounts, passed in batches
+// when they don't fit one transaction. The first batch replaces the reservation, later ones add
+// to it
+pub fn handler<'info>(
+    ctx: Context<'_, '_, '_, 'info, MigrateTreasury<'info>>,
+    first_batch: bool,
+) -> Result<()> {
+    let treasury_account_info = ctx.accounts.treasury_account.to_account_info();
+
+    check_discriminator::<TreasuryState>(&treasury_account_info)?;
+
+    grow_account(
+        &treasury_account_info,
+        &ctx.accounts.authority.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        8 + TREASURY_STATE_SIZE,
+    )?;
+
+    let mut treasury_account = Account::<TreasuryState>::try_from(&treasury_account_info)?;
+
+    let mut referrers: Vec<Pubkey> = Vec::new();
+    let mut referral_rewards_reserved = if first_batch {
+        0
+    } else {
+        treasury_account.referral_rewards_reserved
+    };
+
+    for referrer_account_info in ctx.remaining_accounts {
+        require!(
+            !referrers.contains(referrer_account_info.key),
+            GameError::InvalidReferrer
+        );
+        referrers.push(referrer_account_info.key());
+
+        let referrer_account = Account::<ReferrerState>::try_from(referrer_account_info)?;
+        referral_rewards_reserved = referral_rewards_reserved
+            .checked_add(referrer_account.claimable)
+            .ok_or(GameError::MathOverflow)?;
+    }
+
+    msg!(
+        "Reserving {} WSOL of referral rewards, {} before, counting {} referrers",
+        referral_rewards_reserved,
+        treasury_account.referral_rewards_reserved,
+        referrers.len()
+    );
+    treasury_account.referral_rewards_reserved = referral_rewards_reserved;
+    treasury_account.exit(ctx.program_id)?;
+
+    Ok(())
+}
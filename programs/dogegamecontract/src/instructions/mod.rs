@@ -1,31 +1,79 @@
+pub mod add_treasury_vault;
 pub mod admin_close_doge_racer;
 pub mod admin_close_entry_fee_requirments;
 pub mod admin_close_lobby;
 pub mod admin_close_race_state;
-pub mod cache_race;
+pub mod claim_referral_rewards;
 pub mod close_lobby;
+pub mod close_season;
 pub mod conclude_race;
 pub mod create_lobby;
 pub mod extend_lobby_size;
 pub mod flush_stale_racer;
 pub mod init_entry_fee_requirements;
+pub mod init_fee_schedule;
+pub mod init_referral_config;
+pub mod init_referrer;
+pub mod init_seasons;
+pub mod init_stake_config;
+pub mod init_stake_position;
+pub mod init_streak_config;
+pub mod init_treasury;
 pub mod join_race;
 pub mod leave_race;
+pub mod migrate_doge_racer;
+pub mod migrate_entry_fee_requirements;
+pub mod migrate_lobby;
+pub mod migrate_treasury;
+pub mod open_season;
+pub mod record_race;
+pub mod record_racer_result;
 pub mod register_doge_racer;
+pub mod stake_dtrk;
+pub mod treasury_withdraw;
+pub mod unstake_dtrk;
 pub mod update_entry_fee_requirements;
+pub mod update_fee_schedule;
+pub mod update_referral_config;
+pub mod update_stake_config;
+pub mod update_streak_config;
 
+pub use add_treasury_vault::*;
 pub use admin_close_doge_racer::*;
 pub use admin_close_entry_fee_requirments::*;
 pub use admin_close_lobby::*;
 pub use admin_close_race_state::*;
-pub use cache_race::*;
+pub use claim_referral_rewards::*;
 pub use close_lobby::*;
+pub use close_season::*;
 pub use conclude_race::*;
 pub use create_lobby::*;
 pub use extend_lobby_size::*;
 pub use flush_stale_racer::*;
 pub use init_entry_fee_requirements::*;
+pub use init_fee_schedule::*;
+pub use init_referral_config::*;
+pub use init_referrer::*;
+pub use init_seasons::*;
+pub use init_stake_config::*;
+pub use init_stake_position::*;
+pub use init_streak_config::*;
+pub use init_treasury::*;
 pub use join_race::*;
 pub use leave_race::*;
+pub use migrate_doge_racer::*;
+pub use migrate_entry_fee_requirements::*;
+pub use migrate_lobby::*;
+pub use migrate_treasury::*;
+pub use open_season::*;
+pub use record_race::*;
+pub use record_racer_result::*;
 pub use register_doge_racer::*;
+pub use stake_dtrk::*;
+pub use treasury_withdraw::*;
+pub use unstake_dtrk::*;
 pub use update_entry_fee_requirements::*;
+pub use update_fee_schedule::*;
+pub use update_referral_config::*;
+pub use update_stake_config::*;
+pub use update_streak_config::*;
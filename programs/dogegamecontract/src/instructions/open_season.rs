@@ -0,0 +1,137 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::{create, get_associated_token_address, AssociatedToken, Create},
+    token::{Mint, Token},
+};
+use doge_o::get_authority;
+
+use crate::{
+    constants::{MAX_BPS, SEASON_STATE_SIZE},
+    error::GameError,
+    state::{SeasonState, SeasonsState, TreasuryState},
+    utils::is_prize_split_valid,
+};
+
+#[derive(Accounts)]
+pub struct OpenSeason<'info> {
+    #[account(
+        mut,
+        address = get_authority()
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"seasons"],
+        bump = seasons_account.bump
+    )]
+    pub seasons_account: Account<'info, SeasonsState>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + SEASON_STATE_SIZE,
+        seeds = [b"season", seasons_account.season_count.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub season_account: Box<Account<'info, SeasonState>>,
+
+    #[account(
+        seeds = [b"treasury"],
+        bump = treasury_account.bump
+    )]
+    pub treasury_account: Box<Account<'info, TreasuryState>>,
+
+    pub prize_mint: Account<'info, Mint>,
+
+    #[account(mut)]
+    /// CHECK: Proper ATA validation is done
+    pub prize_vault: UncheckedAccount<'info>,
+
+    // Sysvar
+    pub rent: Sysvar<'info, Rent>,
+
+    // Programs
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(
+    ctx: Context<OpenSeason>,
+    start_time: u64,
+    end_time: u64,
+    rake_share_bps: u16,
+    prize_split_bps: Vec<u16>,
+) -> Result<()> {
+    let seasons_account = &mut ctx.accounts.seasons_account;
+    let season_account = &mut ctx.accounts.season_account;
+    let prize_mint = ctx.accounts.prize_mint.key();
+    let prize_vault = &ctx.accounts.prize_vault;
+
+    require!(
+        start_time.lt(&end_time)
+            && start_time.ge(&seasons_account.last_end_time)
+            && (rake_share_bps as u64).le(&MAX_BPS),
+        GameError::InvalidSeason
+    );
+    require!(
+        is_prize_split_valid(&prize_split_bps),
+        GameError::InvalidSeason
+    );
+    // Prizes nobody could be paid are swept to the treasury at close, so it has to hold the mint
+    require!(
+        ctx.accounts
+            .treasury_account
+            .ledgers
+            .iter()
+            .any(|ledger| ledger.mint.eq(&prize_mint)),
+        GameError::TreasuryVaultNotFound
+    );
+    require!(
+        prize_vault.key().eq(&get_associated_token_address(
+            &season_account.key(),
+            &prize_mint
+        )),
+        GameError::InvalidSeasonAccount
+    );
+
+    msg!(
+        "Opening season {} from {} to {}",
+        seasons_account.season_count,
+        start_time,
+        end_time
+    );
+    create(CpiContext::new(
+        ctx.accounts.associated_token_program.to_account_info(),
+        Create {
+            payer: ctx.accounts.authority.to_account_info(),
+            associated_token: prize_vault.to_account_info(),
+            authority: season_account.to_account_info(),
+            mint: ctx.accounts.prize_mint.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+            rent: ctx.accounts.rent.to_account_info(),
+        },
+    ))?;
+
+    season_account.bump = *ctx.bumps.get("season_account").unwrap();
+    season_account.season_id = seasons_account.season_count;
+    season_account.start_time = start_time;
+    season_account.end_time = end_time;
+    season_account.prize_mint = prize_mint;
+    season_account.prize_vault = prize_vault.key();
+    season_account.rake_share_bps = rake_share_bps;
+    season_account.prize_split_bps = prize_split_bps;
+    season_account.leaderboard = vec![];
+    season_account.prize_pool = 0;
+    season_account.closed = false;
+
+    seasons_account.season_count = seasons_account
+        .season_count
+        .checked_add(1)
+        .ok_or(GameError::MathOverflow)?;
+    seasons_account.last_end_time = end_time;
+
+    Ok(())
+}
@@ -0,0 +1,211 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::Mint;
+use doge_o::{
+    cpi::{accounts::UpdateRatings, update_ratings},
+    get_authority,
+    program::DogeO,
+    rating::MAX_RATED_RACERS,
+    DogeStats,
+};
+
+use crate::{
+    constants::{race_state_size, LOBBY_STATE_VERSION},
+    error::GameError,
+    state::*,
+    utils::*,
+};
+
+#[derive(Accounts)]
+pub struct RecordRace<'info> {
+    #[account(
+        mut,
+        address = get_authority()
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(
+        constraint = lobby_account.race_started @ GameError::RaceNotStarted,
+        constraint = lobby_account.version == LOBBY_STATE_VERSION @ GameError::AccountNotMigrated,
+        constraint = !is_lobby_empty(&lobby_account.racers) @ GameError::LobbyNotFull,
+        seeds = [
+            b"lobby",
+            track_holder.key().as_ref(),
+            track_mint.key().as_ref(),
+        ],
+        bump = lobby_account.bump,
+    )]
+    pub lobby_account: Box<Account<'info, LobbyState>>,
+
+    #[account(
+        address = lobby_account.track_keys.entry_fee_mint
+    )]
+    pub entry_fee_mint: Box<Account<'info, Mint>>,
+
+    #[account(
+        constraint = track_mint.key().eq(&lobby_account.track_keys.track_mint) @ GameError::UnauthorizedTrackMint
+    )]
+    pub track_mint: Box<Account<'info, Mint>>,
+
+    #[account(address = lobby_account.track_keys.track_holder)]
+    pub track_holder: SystemAccount<'info>,
+
+    /// CHECK: Race record PDA, created in the handler
+    #[account(mut)]
+    pub race_data_state: UncheckedAccount<'info>,
+
+    /// CHECK: Track stats PDA, created in the handler when the track hosts its first race
+    #[account(mut)]
+    pub track_stats_account: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub doge_o_program: Program<'info, DogeO>,
+}
+
+// Records the lobby's current race once its results are in, before any racer settles. Settlements
+// read placements off this record. Remaining accounts are every racer's (doge racer, doge stats)
+// pair in finishing order, which is all the finishing order the instruction takes
+pub fn handler<'info>(
+    ctx: Context<'_, '_, '_, 'info, RecordRace<'info>>,
+    race_started: u64,
+    winning_time: u64,
+) -> Result<()> {
+    let lobby_account = &ctx.accounts.lobby_account;
+    let entry_fee_mint = &ctx.accounts.entry_fee_mint;
+    let track_mint = ctx.accounts.track_mint.key();
+    let race_data_info = ctx.accounts.race_data_state.to_account_info();
+
+    let finishing_order: Vec<Pubkey> = ctx
+        .remaining_accounts
+        .chunks(2)
+        .map(|pair| pair[0].key())
+        .collect();
+
+    require!(
+        is_finishing_order_valid(&lobby_account.racers, &finishing_order),
+        GameError::InvalidFinishingOrder
+    );
+
+    let lobby_key = lobby_account.key();
+    let winner_key = finishing_order[0];
+    let race_number = lobby_account.race_count;
+    let race_number_seed = race_number.to_le_bytes();
+    let (race_data_address, race_data_bump) = get_race_data_address(&lobby_key, race_number);
+    require!(
+        race_data_info.key().eq(&race_data_address) && race_data_info.data_is_empty(),
+        GameError::InvalidRaceData
+    );
+
+    // 1. Writing the race record with the stats every doge raced with
+    let doge_stats_infos =
+        get_finishing_order_doge_stats(ctx.remaining_accounts, &finishing_order)?;
+
+    let results = finishing_order
+        .iter()
+        .zip(doge_stats_infos.iter())
+        .map(|(doge_racer, doge_stats_info)| {
+            let doge_stats = Account::<DogeStats>::try_from(doge_stats_info)?;
+            Ok(RaceResult {
+                doge_racer: *doge_racer,
+                doge_stats: doge_stats_info.key(),
+                payout: 0,
+                endurance: doge_stats.endurance,
+                speed: doge_stats.speed,
+                agility: doge_stats.agility,
+                rating: doge_stats.rating,
+                recorded: false,
+                settled: false,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    msg!(
+        "Recording lobby {} race {}, won by {}",
+        lobby_key.to_string(),
+        race_number,
+        winner_key.to_string()
+    );
+    create_pda_account(
+        &race_data_info,
+        &ctx.accounts.authority.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        8 + race_state_size(lobby_account.max_players),
+        &[
+            b"race",
+            lobby_key.as_ref(),
+            &race_number_seed,
+            &[race_data_bump],
+        ],
+    )?;
+
+    let mut race_data_state = Account::<RaceState>::try_from_unchecked(&race_data_info)?;
+    race_data_state.bump = race_data_bump;
+    race_data_state.race_number = race_number;
+    race_data_state.lobby_account = lobby_key;
+    race_data_state.entry_fee_mint = entry_fee_mint.key();
+    race_data_state.track_mint = track_mint;
+    race_data_state.track_holder = ctx.accounts.track_holder.key();
+    race_data_state.winner_doge_racer_account = winner_key;
+    race_data_state.race_started_at = race_started;
+    race_data_state.entry_fee_token_fee = lobby_account.lobby_data.entry_fee;
+    race_data_state.doge_racers = lobby_account.racers.clone();
+    race_data_state.track_type = lobby_account.lobby_data.track_type.clone();
+    race_data_state.total_laps = lobby_account.lobby_data.total_laps;
+    race_data_state.settled_at = Clock::get()?.unix_timestamp as u64;
+    race_data_state.results = results;
+    race_data_state.exit(ctx.program_id)?;
+
+    // 2. Updating every participant's rating from the finishing order. Lobbies created
+    // before sizes were capped can be too big to rate, their races settle unrated
+    if finishing_order.len() <= MAX_RATED_RACERS {
+        update_ratings(
+            CpiContext::new(
+                ctx.accounts.doge_o_program.to_account_info(),
+                UpdateRatings {
+                    authority: ctx.accounts.authority.to_account_info(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                },
+            )
+            .with_remaining_accounts(doge_stats_infos),
+        )?;
+    } else {
+        msg!(
+            "Skipping ratings for a race of {} racers",
+            finishing_order.len()
+        );
+    }
+
+    // 3. Recording the race on the track, along with any new best time
+    let mut track_stats = load_or_create_track_stats(
+        &ctx.accounts.track_stats_account.to_account_info(),
+        &ctx.accounts.authority.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        &track_mint,
+    )?;
+
+    let total_entry_fee = get_lobby_entry_fee(&lobby_account.lobby_data, entry_fee_mint.decimals)?
+        .checked_mul(lobby_account.max_players.into())
+        .ok_or(GameError::MathOverflow)?;
+
+    track_stats.races_hosted = track_stats
+        .races_hosted
+        .checked_add(1)
+        .ok_or(GameError::MathOverflow)?;
+    track_stats.add_entry_fee_volume(&entry_fee_mint.key(), total_entry_fee)?;
+
+    if winning_time > 0
+        && (track_stats.best_race_time == 0 || winning_time < track_stats.best_race_time)
+    {
+        msg!(
+            "New best time of {}ms on track {}",
+            winning_time,
+            track_mint.to_string()
+        );
+        track_stats.best_race_time = winning_time;
+        track_stats.best_race_doge_racer = winner_key;
+        track_stats.best_race_number = race_number;
+    }
+
+    track_stats.exit(ctx.program_id)?;
+
+    Ok(())
+}
@@ -0,0 +1,152 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::TokenAccount;
+use doge_o::get_authority;
+
+use crate::{error::GameError, state::*, utils::*};
+
+#[derive(Accounts)]
+pub struct RecordRacerResult<'info> {
+    #[account(
+        mut,
+        address = get_authority()
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(
+        constraint = doge_racer_account.last_joined_timestamp.gt(&0) @ GameError::UnauthorizedRacer,
+        constraint = doge_racer_account.current_lobby_race.eq(&lobby_account.key()) @ GameError::UnauthorizedRacer,
+    )]
+    pub doge_racer_account: Box<Account<'info, DogeRacerState>>,
+
+    // Owned by the doge holder the racer joined with
+    #[account(
+        address = doge_racer_account.doge_holder_entry_fee_token
+    )]
+    pub doge_holder_entry_fee_token: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        address = race_data_state.lobby_account
+    )]
+    pub lobby_account: Box<Account<'info, LobbyState>>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"race",
+            lobby_account.key().as_ref(),
+            lobby_account.race_count.to_le_bytes().as_ref(),
+        ],
+        bump = race_data_state.bump
+    )]
+    pub race_data_state: Box<Account<'info, RaceState>>,
+
+    #[account(
+        mut,
+        seeds = [b"trackstats", race_data_state.track_mint.as_ref()],
+        bump = track_stats_account.bump
+    )]
+    pub track_stats_account: Box<Account<'info, TrackStatsState>>,
+
+    /// CHECK: Racer's record on this track, created in the handler on their first race here
+    #[account(mut)]
+    pub track_racer_account: UncheckedAccount<'info>,
+
+    /// CHECK: Season to score the race in, validated in the handler. Ignored when not an active season
+    #[account(mut)]
+    pub season_account: UncheckedAccount<'info>,
+
+    /// CHECK: Racer's season record PDA, created in the handler on the first race of a season
+    #[account(mut)]
+    pub season_racer_account: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// Counts one racer's result of the recorded race towards their season and track records. Runs
+// once per racer, before they settle
+pub fn handler(ctx: Context<RecordRacerResult>) -> Result<()> {
+    let race_data_state = &mut ctx.accounts.race_data_state;
+    let track_stats = &mut ctx.accounts.track_stats_account;
+    let doge_racer = ctx.accounts.doge_racer_account.key();
+    let doge_holder = ctx.accounts.doge_holder_entry_fee_token.owner;
+    let track_mint = race_data_state.track_mint;
+    let total_racers = race_data_state.results.len();
+
+    let placement = race_data_state
+        .results
+        .iter()
+        .position(|result| result.doge_racer.eq(&doge_racer))
+        .ok_or(GameError::InvalidFinishingOrder)?;
+    let result = &mut race_data_state.results[placement];
+    require!(!result.recorded, GameError::RacerResultAlreadyRecorded);
+    result.recorded = true;
+
+    // Scoring the race in the active season
+    if let Some(mut season) = load_active_season(
+        &ctx.accounts.season_account.to_account_info(),
+        Clock::get()?.unix_timestamp as u64,
+    )? {
+        let mut season_racer = load_or_create_season_racer(
+            &ctx.accounts.season_racer_account.to_account_info(),
+            &ctx.accounts.authority.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            &season.key(),
+            &doge_racer,
+        )?;
+
+        let season_points = get_season_points(placement, total_racers);
+        season_racer.points = season_racer
+            .points
+            .checked_add(season_points)
+            .ok_or(GameError::MathOverflow)?;
+        season_racer.races_run += 1;
+        if placement == 0 {
+            season_racer.wins += 1;
+        }
+        season_racer.doge_holder = doge_holder;
+
+        msg!(
+            "Awarding {} season points, total {}",
+            season_points,
+            season_racer.points
+        );
+
+        let leaderboard_size = season.prize_split_bps.len();
+        update_leaderboard(
+            &mut season.leaderboard,
+            LeaderboardEntry {
+                doge_racer: season_racer.doge_racer,
+                doge_holder: season_racer.doge_holder,
+                points: season_racer.points,
+            },
+            leaderboard_size,
+        );
+
+        season_racer.exit(ctx.program_id)?;
+        season.exit(ctx.program_id)?;
+    }
+
+    // Counting the racer towards the track's unique racers on their first race here
+    let (mut track_racer, is_new_track_racer) = load_or_create_track_racer(
+        &ctx.accounts.track_racer_account.to_account_info(),
+        &ctx.accounts.authority.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        &track_mint,
+        &doge_racer,
+    )?;
+
+    if is_new_track_racer {
+        track_stats.unique_racers = track_stats
+            .unique_racers
+            .checked_add(1)
+            .ok_or(GameError::MathOverflow)?;
+    }
+    track_racer.races_run = track_racer
+        .races_run
+        .checked_add(1)
+        .ok_or(GameError::MathOverflow)?;
+
+    track_racer.exit(ctx.program_id)?;
+
+    Ok(())
+}
@@ -4,7 +4,7 @@ use doge_o::DogeStats;
 
 use crate::{
     constants::DOGE_RACER_SIZE, error::GameError, metadata::Metadata, state::DogeRacerState,
-    utils::is_doge_stats_valid,
+    utils::{is_doge_stats_valid, load_referrer_account},
 };
 
 #[derive(Accounts)]
@@ -44,7 +44,8 @@ pub struct RegisterDogeRacer<'info> {
     pub system_program: Program<'info, System>,
 }
 
-pub fn handler(ctx: Context<RegisterDogeRacer>) -> Result<()> {
+// With a referrer, their referrer PDA is passed as the only remaining account
+pub fn handler(ctx: Context<RegisterDogeRacer>, referrer: Option<Pubkey>) -> Result<()> {
     //require!(!MAINTENANCE_MODE, GameError::GameInMaintenance);
     require!(
         is_doge_stats_valid(
@@ -62,6 +63,31 @@ pub fn handler(ctx: Context<RegisterDogeRacer>) -> Result<()> {
     doge_racer_account.doge_holder_entry_fee_token = Pubkey::default();
     doge_racer_account.last_joined_timestamp = 0u64;
     doge_racer_account.doge_o_pda = ctx.accounts.doge_o_pda.key();
+    doge_racer_account.registered_at = Clock::get()?.unix_timestamp as u64;
+
+    if let Some(referrer) = referrer {
+        require!(
+            referrer.ne(&ctx.accounts.doge_holder.key()),
+            GameError::InvalidReferrer
+        );
+
+        let referrer_account = ctx
+            .remaining_accounts
+            .first()
+            .ok_or(GameError::InvalidReferrer)?;
+        require!(referrer_account.is_writable, GameError::InvalidReferrer);
+
+        let mut referrer_state = load_referrer_account(referrer_account, &referrer)?;
+
+        msg!("Registering doge racer referred by {}", referrer.to_string());
+        referrer_state.referred_racers = referrer_state
+            .referred_racers
+            .checked_add(1)
+            .ok_or(GameError::MathOverflow)?;
+        referrer_state.exit(ctx.program_id)?;
+
+        doge_racer_account.referrer = referrer;
+    }
 
     Ok(())
 }
@@ -0,0 +1,94 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{transfer, Mint, Token, TokenAccount, Transfer};
+
+use crate::{
+    constants::MAX_STAKE_LOCK_DURATION,
+    error::GameError,
+    state::{StakeConfigState, StakePosition},
+    utils::{get_dtrk_mint, lock_stake_position},
+};
+
+#[derive(Accounts)]
+#[instruction(amount: u64, lock_duration: u64)]
+pub struct StakeDtrk<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [
+            b"stake",
+            owner.key().as_ref(),
+        ],
+        bump = stake_account.bump
+    )]
+    pub stake_account: Account<'info, StakePosition>,
+
+    #[account(
+        mut,
+        seeds = [b"stakeconfig"],
+        bump = stake_config_account.bump
+    )]
+    pub stake_config_account: Account<'info, StakeConfigState>,
+
+    #[account(address = get_dtrk_mint())]
+    pub dtrk_mint: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        constraint = owner_dtrk_token.owner.eq(&owner.key()),
+        constraint = owner_dtrk_token.mint.eq(&dtrk_mint.key()),
+        constraint = owner_dtrk_token.amount.ge(&amount) @ GameError::InsufficientEntryFeeTokenBalance
+    )]
+    pub owner_dtrk_token: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        address = stake_account.vault
+    )]
+    pub stake_vault: Box<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+pub fn handler(ctx: Context<StakeDtrk>, amount: u64, lock_duration: u64) -> Result<()> {
+    require!(
+        lock_duration.le(&MAX_STAKE_LOCK_DURATION),
+        GameError::InvalidStakeLockDuration
+    );
+
+    let current_timestamp = Clock::get()?.unix_timestamp as u64;
+    let stake_account = &mut ctx.accounts.stake_account;
+
+    lock_stake_position(stake_account, lock_duration, current_timestamp)?;
+    stake_account.amount = stake_account
+        .amount
+        .checked_add(amount)
+        .ok_or(GameError::MathOverflow)?;
+
+    let stake_config_account = &mut ctx.accounts.stake_config_account;
+    stake_config_account.total_staked = stake_config_account
+        .total_staked
+        .checked_add(amount)
+        .ok_or(GameError::MathOverflow)?;
+
+    msg!(
+        "Staking {} DTRK, locked until {}",
+        amount,
+        stake_account.unlock_at
+    );
+
+    transfer(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.owner_dtrk_token.to_account_info(),
+                to: ctx.accounts.stake_vault.to_account_info(),
+                authority: ctx.accounts.owner.to_account_info(),
+            },
+        ),
+        amount,
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,93 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{transfer, Mint, Token, TokenAccount, Transfer};
+use doge_o::get_authority;
+
+use crate::{error::GameError, state::TreasuryState, utils::get_wsol_mint};
+
+#[derive(Accounts)]
+pub struct TreasuryWithdraw<'info> {
+    #[account(
+        address = get_authority()
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"treasury"],
+        bump = treasury_account.bump
+    )]
+    pub treasury_account: Account<'info, TreasuryState>,
+
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = treasury_token.mint.eq(&mint.key()),
+        constraint = treasury_token.owner.eq(&treasury_account.key()) @ GameError::InvalidTreasuryTokenAccount,
+    )]
+    pub treasury_token: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = destination_token.mint.eq(&mint.key())
+    )]
+    pub destination_token: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+pub fn handler(ctx: Context<TreasuryWithdraw>, amount: u64) -> Result<()> {
+    let treasury_token = &ctx.accounts.treasury_token;
+
+    // Unclaimed referral rewards are owed out of the WSOL vault
+    let reserved = if ctx.accounts.mint.key().eq(&get_wsol_mint()) {
+        ctx.accounts.treasury_account.referral_rewards_reserved
+    } else {
+        0
+    };
+    let available = treasury_token.amount.saturating_sub(reserved);
+
+    require!(
+        amount > 0 && available >= amount,
+        GameError::InsufficientTreasuryBalance
+    );
+
+    let treasury_bump = ctx.accounts.treasury_account.bump;
+    let signer_seeds: &[&[&[u8]]] = &[&[b"treasury", &[treasury_bump]]];
+
+    let ledger = ctx
+        .accounts
+        .treasury_account
+        .ledger_mut(&ctx.accounts.mint.key())?;
+
+    require!(
+        ledger.vault.eq(&treasury_token.key()),
+        GameError::InvalidTreasuryTokenAccount
+    );
+
+    ledger.withdrawn = ledger
+        .withdrawn
+        .checked_add(amount)
+        .ok_or(GameError::MathOverflow)?;
+
+    msg!(
+        "Withdrawing {} from Treasury to {}",
+        amount,
+        ctx.accounts.destination_token.key().to_string()
+    );
+
+    transfer(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: treasury_token.to_account_info(),
+                to: ctx.accounts.destination_token.to_account_info(),
+                authority: ctx.accounts.treasury_account.to_account_info(),
+            },
+            signer_seeds,
+        ),
+        amount,
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,92 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{transfer, Mint, Token, TokenAccount, Transfer};
+
+use crate::{
+    error::GameError,
+    state::{StakeConfigState, StakePosition},
+    utils::get_dtrk_mint,
+};
+
+#[derive(Accounts)]
+#[instruction(amount: u64)]
+pub struct UnstakeDtrk<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = owner,
+        constraint = stake_account.amount.ge(&amount) @ GameError::InsufficientStake,
+        seeds = [
+            b"stake",
+            owner.key().as_ref(),
+        ],
+        bump = stake_account.bump
+    )]
+    pub stake_account: Account<'info, StakePosition>,
+
+    #[account(
+        mut,
+        seeds = [b"stakeconfig"],
+        bump = stake_config_account.bump
+    )]
+    pub stake_config_account: Account<'info, StakeConfigState>,
+
+    #[account(address = get_dtrk_mint())]
+    pub dtrk_mint: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        constraint = owner_dtrk_token.owner.eq(&owner.key()),
+        constraint = owner_dtrk_token.mint.eq(&dtrk_mint.key())
+    )]
+    pub owner_dtrk_token: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        address = stake_account.vault
+    )]
+    pub stake_vault: Box<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+pub fn handler(ctx: Context<UnstakeDtrk>, amount: u64) -> Result<()> {
+    let current_timestamp = Clock::get()?.unix_timestamp as u64;
+    let owner = ctx.accounts.owner.key();
+    let stake_account = &mut ctx.accounts.stake_account;
+
+    require!(
+        current_timestamp >= stake_account.unlock_at,
+        GameError::StakeLocked
+    );
+
+    stake_account.amount = stake_account
+        .amount
+        .checked_sub(amount)
+        .ok_or(GameError::InsufficientStake)?;
+
+    let stake_config_account = &mut ctx.accounts.stake_config_account;
+    stake_config_account.total_staked = stake_config_account
+        .total_staked
+        .checked_sub(amount)
+        .ok_or(GameError::MathOverflow)?;
+
+    let signer_seeds: &[&[&[u8]]] = &[&[b"stake", owner.as_ref(), &[stake_account.bump]]];
+
+    msg!("Unstaking {} DTRK", amount);
+
+    transfer(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.stake_vault.to_account_info(),
+                to: ctx.accounts.owner_dtrk_token.to_account_info(),
+                authority: stake_account.to_account_info(),
+            },
+            signer_seeds,
+        ),
+        amount,
+    )?;
+
+    Ok(())
+}
@@ -1,4 +1,7 @@
-use crate::{error::GameError, state::EntryFeeRequirements};
+use crate::{
+    constants::ENTRY_FEE_REQUIREMENTS_VERSION, error::GameError, state::EntryFeeRequirements,
+    utils::is_entry_fee_requirements_representable,
+};
 use anchor_lang::prelude::*;
 use anchor_spl::token::Mint;
 use doge_o::get_authority;
@@ -27,16 +30,26 @@ pub struct UpdateEntryFeeRequirements<'info> {
 
 pub fn handler(
     ctx: Context<UpdateEntryFeeRequirements>,
-    EntryFeeRequirements {
+    new_entry_fee_requirements: EntryFeeRequirements,
+) -> Result<()> {
+    let entry_fee_requirements_state = &mut ctx.accounts.entry_fee_requirements_account;
+
+    require!(
+        is_entry_fee_requirements_representable(
+            &new_entry_fee_requirements,
+            ctx.accounts.entry_fee_mint.decimals
+        ),
+        GameError::InvalidEntryFeePrecision
+    );
+
+    let EntryFeeRequirements {
         min_fee,
         max_class_1_fee,
         max_class_2_fee,
         max_class_3_fee,
         max_class_4_fee,
         max_class_5_fee,
-    }: EntryFeeRequirements,
-) -> Result<()> {
-    let entry_fee_requirements_state = &mut ctx.accounts.entry_fee_requirements_account;
+    } = new_entry_fee_requirements;
 
     require!(min_fee > 0, GameError::InvalidEntryFeeRequirement);
     entry_fee_requirements_state.entry_fee_requirements.min_fee = min_fee;
@@ -81,5 +94,7 @@ pub fn handler(
         .entry_fee_requirements
         .max_class_5_fee = max_class_5_fee;
 
+    entry_fee_requirements_state.version = ENTRY_FEE_REQUIREMENTS_VERSION;
+
     Ok(())
 }
@@ -0,0 +1,34 @@
+use anchor_lang::prelude::*;
+use doge_o::get_authority;
+
+use crate::{
+    error::GameError,
+    state::{FeeSchedule, FeeScheduleState},
+    utils::is_fee_schedule_valid,
+};
+
+#[derive(Accounts)]
+pub struct UpdateFeeSchedule<'info> {
+    #[account(
+        address = get_authority()
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"feeschedule"],
+        bump = fee_schedule_account.bump
+    )]
+    pub fee_schedule_account: Account<'info, FeeScheduleState>,
+}
+
+pub fn handler(ctx: Context<UpdateFeeSchedule>, new_fee_schedule: FeeSchedule) -> Result<()> {
+    require!(
+        is_fee_schedule_valid(&new_fee_schedule),
+        GameError::InvalidFeeSchedule
+    );
+
+    ctx.accounts.fee_schedule_account.fee_schedule = new_fee_schedule;
+
+    Ok(())
+}
@@ -0,0 +1,37 @@
+use anchor_lang::prelude::*;
+use doge_o::get_authority;
+
+use crate::{
+    error::GameError,
+    state::{ReferralConfig, ReferralConfigState},
+    utils::is_referral_config_valid,
+};
+
+#[derive(Accounts)]
+pub struct UpdateReferralConfig<'info> {
+    #[account(
+        address = get_authority()
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"referralconfig"],
+        bump = referral_config_account.bump
+    )]
+    pub referral_config_account: Account<'info, ReferralConfigState>,
+}
+
+pub fn handler(
+    ctx: Context<UpdateReferralConfig>,
+    new_referral_config: ReferralConfig,
+) -> Result<()> {
+    require!(
+        is_referral_config_valid(&new_referral_config),
+        GameError::InvalidReferralConfig
+    );
+
+    ctx.accounts.referral_config_account.referral_config = new_referral_config;
+
+    Ok(())
+}
@@ -0,0 +1,34 @@
+use anchor_lang::prelude::*;
+use doge_o::get_authority;
+
+use crate::{
+    error::GameError,
+    state::{StakeConfigState, StakeTier},
+    utils::is_stake_config_valid,
+};
+
+#[derive(Accounts)]
+pub struct UpdateStakeConfig<'info> {
+    #[account(
+        address = get_authority()
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"stakeconfig"],
+        bump = stake_config_account.bump
+    )]
+    pub stake_config_account: Account<'info, StakeConfigState>,
+}
+
+pub fn handler(ctx: Context<UpdateStakeConfig>, new_tiers: Vec<StakeTier>) -> Result<()> {
+    require!(
+        is_stake_config_valid(&new_tiers),
+        GameError::InvalidStakeConfig
+    );
+
+    ctx.accounts.stake_config_account.tiers = new_tiers;
+
+    Ok(())
+}
@@ -0,0 +1,34 @@
+use anchor_lang::prelude::*;
+use doge_o::get_authority;
+
+use crate::{
+    error::GameError,
+    state::{StreakConfig, StreakConfigState},
+    utils::is_streak_config_valid,
+};
+
+#[derive(Accounts)]
+pub struct UpdateStreakConfig<'info> {
+    #[account(
+        address = get_authority()
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"streakconfig"],
+        bump = streak_config_account.bump
+    )]
+    pub streak_config_account: Account<'info, StreakConfigState>,
+}
+
+pub fn handler(ctx: Context<UpdateStreakConfig>, new_streak_config: StreakConfig) -> Result<()> {
+    require!(
+        is_streak_config_valid(&new_streak_config),
+        GameError::InvalidStreakConfig
+    );
+
+    ctx.accounts.streak_config_account.streak_config = new_streak_config;
+
+    Ok(())
+}
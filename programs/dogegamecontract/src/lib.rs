@@ -28,8 +28,11 @@ pub mod dogegamecontract {
         instructions::close_lobby::handler(ctx)
     }
 
-    pub fn register_doge_racer(ctx: Context<RegisterDogeRacer>) -> Result<()> {
-        instructions::register_doge_racer::handler(ctx)
+    pub fn register_doge_racer(
+        ctx: Context<RegisterDogeRacer>,
+        referrer: Option<Pubkey>,
+    ) -> Result<()> {
+        instructions::register_doge_racer::handler(ctx, referrer)
     }
 
     pub fn join_race(ctx: Context<JoinRace>) -> Result<()> {
@@ -44,16 +47,20 @@ pub mod dogegamecontract {
         instructions::flush_stale_racer::handler(ctx)
     }
 
-    pub fn conclude_race(
-        ctx: Context<ConcludeRace>,
-        is_winner: bool,
-        new_win_pct: u8,
+    pub fn record_race<'info>(
+        ctx: Context<'_, '_, '_, 'info, RecordRace<'info>>,
+        race_started: u64,
+        winning_time: u64,
     ) -> Result<()> {
-        instructions::conclude_race::handler(ctx, is_winner, new_win_pct)
+        instructions::record_race::handler(ctx, race_started, winning_time)
+    }
+
+    pub fn record_racer_result(ctx: Context<RecordRacerResult>) -> Result<()> {
+        instructions::record_racer_result::handler(ctx)
     }
 
-    pub fn cache_race(ctx: Context<CacheRace>, race_started: u64) -> Result<()> {
-        instructions::cache_race::handler(ctx, race_started)
+    pub fn conclude_race<'info>(ctx: Context<'_, '_, '_, 'info, ConcludeRace<'info>>) -> Result<()> {
+        instructions::conclude_race::handler(ctx)
     }
 
     pub fn init_entry_fee_requirements(
@@ -70,6 +77,117 @@ pub mod dogegamecontract {
         instructions::update_entry_fee_requirements::handler(ctx, new_entry_fee_requirements)
     }
 
+    pub fn init_fee_schedule(
+        ctx: Context<InitFeeSchedule>,
+        fee_schedule: FeeSchedule,
+    ) -> Result<()> {
+        instructions::init_fee_schedule::handler(ctx, fee_schedule)
+    }
+
+    pub fn update_fee_schedule(
+        ctx: Context<UpdateFeeSchedule>,
+        new_fee_schedule: FeeSchedule,
+    ) -> Result<()> {
+        instructions::update_fee_schedule::handler(ctx, new_fee_schedule)
+    }
+
+    pub fn init_referral_config(
+        ctx: Context<InitReferralConfig>,
+        referral_config: ReferralConfig,
+    ) -> Result<()> {
+        instructions::init_referral_config::handler(ctx, referral_config)
+    }
+
+    pub fn update_referral_config(
+        ctx: Context<UpdateReferralConfig>,
+        new_referral_config: ReferralConfig,
+    ) -> Result<()> {
+        instructions::update_referral_config::handler(ctx, new_referral_config)
+    }
+
+    pub fn init_referrer(ctx: Context<InitReferrer>) -> Result<()> {
+        instructions::init_referrer::handler(ctx)
+    }
+
+    pub fn claim_referral_rewards(ctx: Context<ClaimReferralRewards>) -> Result<()> {
+        instructions::claim_referral_rewards::handler(ctx)
+    }
+
+    pub fn init_stake_config(ctx: Context<InitStakeConfig>, tiers: Vec<StakeTier>) -> Result<()> {
+        instructions::init_stake_config::handler(ctx, tiers)
+    }
+
+    pub fn update_stake_config(
+        ctx: Context<UpdateStakeConfig>,
+        new_tiers: Vec<StakeTier>,
+    ) -> Result<()> {
+        instructions::update_stake_config::handler(ctx, new_tiers)
+    }
+
+    pub fn init_streak_config(
+        ctx: Context<InitStreakConfig>,
+        streak_config: StreakConfig,
+    ) -> Result<()> {
+        instructions::init_streak_config::handler(ctx, streak_config)
+    }
+
+    pub fn update_streak_config(
+        ctx: Context<UpdateStreakConfig>,
+        new_streak_config: StreakConfig,
+    ) -> Result<()> {
+        instructions::update_streak_config::handler(ctx, new_streak_config)
+    }
+
+    pub fn init_seasons(ctx: Context<InitSeasons>) -> Result<()> {
+        instructions::init_seasons::handler(ctx)
+    }
+
+    pub fn open_season(
+        ctx: Context<OpenSeason>,
+        start_time: u64,
+        end_time: u64,
+        rake_share_bps: u16,
+        prize_split_bps: Vec<u16>,
+    ) -> Result<()> {
+        instructions::open_season::handler(
+            ctx,
+            start_time,
+            end_time,
+            rake_share_bps,
+            prize_split_bps,
+        )
+    }
+
+    pub fn close_season<'info>(
+        ctx: Context<'_, '_, '_, 'info, CloseSeason<'info>>,
+    ) -> Result<()> {
+        instructions::close_season::handler(ctx)
+    }
+
+    pub fn init_stake_position(ctx: Context<InitStakePosition>) -> Result<()> {
+        instructions::init_stake_position::handler(ctx)
+    }
+
+    pub fn stake_dtrk(ctx: Context<StakeDtrk>, amount: u64, lock_duration: u64) -> Result<()> {
+        instructions::stake_dtrk::handler(ctx, amount, lock_duration)
+    }
+
+    pub fn unstake_dtrk(ctx: Context<UnstakeDtrk>, amount: u64) -> Result<()> {
+        instructions::unstake_dtrk::handler(ctx, amount)
+    }
+
+    pub fn init_treasury(ctx: Context<InitTreasury>) -> Result<()> {
+        instructions::init_treasury::handler(ctx)
+    }
+
+    pub fn add_treasury_vault(ctx: Context<AddTreasuryVault>) -> Result<()> {
+        instructions::add_treasury_vault::handler(ctx)
+    }
+
+    pub fn treasury_withdraw(ctx: Context<TreasuryWithdraw>, amount: u64) -> Result<()> {
+        instructions::treasury_withdraw::handler(ctx, amount)
+    }
+
     /* Maintenance mode instructions */
     pub fn admin_close_lobby(ctx: Context<AdminCloseLobby>) -> Result<()> {
         instructions::admin_close_lobby::handler(ctx)
@@ -79,11 +197,29 @@ pub mod dogegamecontract {
         instructions::admin_close_doge_racer::handler(ctx)
     }
 
-    pub fn admin_close_race_state(
-        ctx: Context<AdminCloseRaceState>,
-        race_started: u64,
+    pub fn admin_close_race_state(ctx: Context<AdminCloseRaceState>) -> Result<()> {
+        instructions::admin_close_race_state::handler(ctx)
+    }
+
+    pub fn migrate_lobby(ctx: Context<MigrateLobby>) -> Result<()> {
+        instructions::migrate_lobby::handler(ctx)
+    }
+
+    pub fn migrate_entry_fee_requirements(
+        ctx: Context<MigrateEntryFeeRequirements>,
+    ) -> Result<()> {
+        instructions::migrate_entry_fee_requirements::handler(ctx)
+    }
+
+    pub fn migrate_doge_racer(ctx: Context<MigrateDogeRacer>) -> Result<()> {
+        instructions::migrate_doge_racer::handler(ctx)
+    }
+
+    pub fn migrate_treasury<'info>(
+        ctx: Context<'_, '_, '_, 'info, MigrateTreasury<'info>>,
+        first_batch: bool,
     ) -> Result<()> {
-        instructions::admin_close_race_state::handler(ctx, race_started)
+        instructions::migrate_treasury::handler(ctx, first_batch)
     }
 
     pub fn admin_close_entry_fee_requirements(
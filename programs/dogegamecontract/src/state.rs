@@ -1,5 +1,7 @@
 use anchor_lang::prelude::*;
 
+use crate::constants::{MAX_TRACK_FEE_MINTS, PODIUM_PLACES, RECENT_RESULTS_LEN};
+
 #[account]
 #[derive(Default)]
 pub struct LobbyState {
@@ -10,6 +12,9 @@ pub struct LobbyState {
     pub track_keys: TrackKeys,
     pub racers: Vec<Pubkey>,
     pub lobby_data: LobbyData,
+    pub version: u8,
+    // Races settled in this lobby, seeds the next race record
+    pub race_count: u64,
 }
 
 #[account]
@@ -24,6 +29,34 @@ pub struct RaceState {
     pub race_started_at: u64,
     pub entry_fee_token_fee: u64,
     pub doge_racers: Vec<Pubkey>,
+    pub race_number: u64,
+    pub track_type: TrackType,
+    pub total_laps: u8,
+    pub settled_at: u64,
+    pub track_owner_fee: u64,
+    pub treasury_fee: u64,
+    pub burn_amount: u64,
+    pub network_fee: u64,
+    // One entry per racer, in finishing order
+    pub results: Vec<RaceResult>,
+}
+
+// Stats are the snapshot the doge raced with, before settlement updated them
+#[repr(C)]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default)]
+pub struct RaceResult {
+    pub doge_racer: Pubkey,
+    pub doge_stats: Pubkey,
+    // Entry fee tokens the racer won. Only the winner is paid, every other placement keeps 0
+    pub payout: u64,
+    pub endurance: u8,
+    pub speed: u8,
+    pub agility: u8,
+    pub rating: u16,
+    // Set once the result counted towards the racer's season and track records
+    pub recorded: bool,
+    // Set once the racer settled, the lobby resets when every result has
+    pub settled: bool,
 }
 
 #[repr(C)]
@@ -31,6 +64,7 @@ pub struct RaceState {
 pub struct LobbyData {
     pub total_laps: u8,
     pub min_class: u8,
+    // Entry fee in ENTRY_FEE_DECIMALS precision, normalised with the mint decimals on-chain
     pub entry_fee: u64,
     pub name: String,
     pub location: String,
@@ -59,6 +93,18 @@ pub enum TrackType {
     Sand,
 }
 
+impl TrackType {
+    // Position of the track type in per track type stats
+    pub fn index(&self) -> usize {
+        match self {
+            TrackType::Dirt => 0,
+            TrackType::Space => 1,
+            TrackType::Pavement => 2,
+            TrackType::Sand => 3,
+        }
+    }
+}
+
 impl Default for TrackType {
     fn default() -> Self {
         TrackType::Sand
@@ -76,6 +122,70 @@ pub struct DogeRacerState {
     pub doge_holder_entry_fee_token: Pubkey,
     pub total_wins: u64,
     pub total_losses: u64,
+    pub referrer: Pubkey,
+    pub registered_at: u64,
+    // Indexed by `TrackType::index`
+    pub track_type_stats: [TrackTypeStats; 4],
+    // Ring buffer of the last RECENT_RESULTS_LEN races, `recent_results_head` is the next slot
+    pub recent_results: [RecentRaceResult; 10],
+    pub recent_results_head: u8,
+    pub current_win_streak: u64,
+    pub best_win_streak: u64,
+    pub current_loss_streak: u64,
+}
+
+impl DogeRacerState {
+    pub fn record_track_result(&mut self, track_type: &TrackType, placement: usize) {
+        let track_type_stats = &mut self.track_type_stats[track_type.index()];
+
+        track_type_stats.races_run += 1;
+        if placement == 0 {
+            track_type_stats.wins += 1;
+        } else {
+            track_type_stats.losses += 1;
+        }
+        if placement < PODIUM_PLACES {
+            track_type_stats.podiums += 1;
+        }
+    }
+
+    pub fn record_streak(&mut self, is_winner: bool) {
+        if is_winner {
+            self.current_win_streak += 1;
+            self.current_loss_streak = 0;
+            self.best_win_streak = self.best_win_streak.max(self.current_win_streak);
+        } else {
+            self.current_win_streak = 0;
+            self.current_loss_streak += 1;
+        }
+    }
+
+    pub fn push_recent_result(&mut self, result: RecentRaceResult) {
+        let head = self.recent_results_head as usize % RECENT_RESULTS_LEN;
+
+        self.recent_results[head] = result;
+        self.recent_results_head = ((head + 1) % RECENT_RESULTS_LEN) as u8;
+    }
+}
+
+// Empty slots have a default lobby
+#[repr(C)]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default)]
+pub struct RecentRaceResult {
+    pub lobby: Pubkey,
+    pub race_number: u64,
+    pub placement: u8,
+    pub payout: u64,
+    pub timestamp: u64,
+}
+
+#[repr(C)]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default)]
+pub struct TrackTypeStats {
+    pub races_run: u64,
+    pub wins: u64,
+    pub losses: u64,
+    pub podiums: u64,
 }
 
 #[account]
@@ -84,6 +194,7 @@ pub struct EntryFeeRequirementsState {
     pub bump: u8,
     pub entry_fee_mint: Pubkey,
     pub entry_fee_requirements: EntryFeeRequirements,
+    pub version: u8,
 }
 
 #[repr(C)]
@@ -96,3 +207,245 @@ pub struct EntryFeeRequirements {
     pub max_class_4_fee: u64,
     pub max_class_5_fee: u64,
 }
+
+#[account]
+#[derive(Default)]
+pub struct TreasuryState {
+    pub bump: u8,
+    pub ledgers: Vec<TreasuryLedger>,
+    // WSOL accrued to referrers and not claimed yet, withdrawals can't touch it
+    pub referral_rewards_reserved: u64,
+}
+
+#[repr(C)]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default)]
+pub struct TreasuryLedger {
+    pub mint: Pubkey,
+    pub vault: Pubkey,
+    pub fees_collected: u64,
+    pub rake_collected: u64,
+    pub residual_swept: u64,
+    pub withdrawn: u64,
+    pub referral_rewards_paid: u64,
+}
+
+impl TreasuryState {
+    pub fn ledger_mut(&mut self, mint: &Pubkey) -> Result<&mut TreasuryLedger> {
+        self.ledgers
+            .iter_mut()
+            .find(|ledger| ledger.mint.eq(mint))
+            .ok_or_else(|| error!(crate::error::GameError::TreasuryVaultNotFound))
+    }
+}
+
+#[account]
+#[derive(Default)]
+pub struct FeeScheduleState {
+    pub bump: u8,
+    pub fee_schedule: FeeSchedule,
+}
+
+#[repr(C)]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default)]
+pub struct FeeSchedule {
+    pub track_owner_bps: u16,
+    pub treasury_bps: u16,
+    pub burn_bps: u16,
+    pub dust_recipient: DustRecipient,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub enum DustRecipient {
+    Winner,
+    TrackOwner,
+    Treasury,
+}
+
+impl Default for DustRecipient {
+    fn default() -> Self {
+        DustRecipient::Winner
+    }
+}
+
+#[account]
+#[derive(Default)]
+pub struct ReferralConfigState {
+    pub bump: u8,
+    pub referral_config: ReferralConfig,
+}
+
+#[repr(C)]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default)]
+pub struct ReferralConfig {
+    // Share of a referred racer's network fee accrued to the referrer
+    pub share_bps: u16,
+    // Races a referred racer runs that accrue rewards to their referrer, counting from
+    // registration. Only the direct referrer is rewarded, there is no referral chain
+    pub max_referred_races: u64,
+    // Seconds since the racer registered during which rewards accrue
+    pub duration: u64,
+}
+
+#[account]
+#[derive(Default)]
+pub struct ReferrerState {
+    pub bump: u8,
+    pub referrer: Pubkey,
+    pub referred_racers: u64,
+    pub claimable: u64,
+    pub total_earned: u64,
+    pub total_claimed: u64,
+}
+
+#[account]
+#[derive(Default)]
+pub struct TrackStatsState {
+    pub bump: u8,
+    pub track_mint: Pubkey,
+    pub races_hosted: u64,
+    pub unique_racers: u64,
+    // Entry fees collected per entry fee mint, in base units
+    pub entry_fee_volume: Vec<MintVolume>,
+    // Fastest winning time in milliseconds, 0 until the first race settles
+    pub best_race_time: u64,
+    pub best_race_doge_racer: Pubkey,
+    pub best_race_number: u64,
+}
+
+#[repr(C)]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default)]
+pub struct MintVolume {
+    pub mint: Pubkey,
+    pub volume: u64,
+}
+
+impl TrackStatsState {
+    pub fn add_entry_fee_volume(&mut self, mint: &Pubkey, amount: u64) -> Result<()> {
+        match self
+            .entry_fee_volume
+            .iter_mut()
+            .find(|mint_volume| mint_volume.mint.eq(mint))
+        {
+            Some(mint_volume) => {
+                mint_volume.volume = mint_volume
+                    .volume
+                    .checked_add(amount)
+                    .ok_or(crate::error::GameError::MathOverflow)?;
+            }
+            None => {
+                require!(
+                    self.entry_fee_volume.len() < MAX_TRACK_FEE_MINTS,
+                    crate::error::GameError::TrackStatsFull
+                );
+                self.entry_fee_volume.push(MintVolume {
+                    mint: *mint,
+                    volume: amount,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Marks a doge as having raced on a track, so unique racers are only counted once
+#[account]
+#[derive(Default)]
+pub struct TrackRacerState {
+    pub bump: u8,
+    pub track_mint: Pubkey,
+    pub doge_racer: Pubkey,
+    pub races_run: u64,
+}
+
+#[account]
+#[derive(Default)]
+pub struct SeasonsState {
+    pub bump: u8,
+    pub season_count: u64,
+    // Seasons can't overlap, a new one starts at or after this
+    pub last_end_time: u64,
+}
+
+#[account]
+#[derive(Default)]
+pub struct SeasonState {
+    pub bump: u8,
+    pub season_id: u64,
+    pub start_time: u64,
+    pub end_time: u64,
+    pub prize_mint: Pubkey,
+    pub prize_vault: Pubkey,
+    // Share of the protocol rake in the prize mint diverted to the prize vault
+    pub rake_share_bps: u16,
+    // Prize share per leaderboard rank, also sets the leaderboard size
+    pub prize_split_bps: Vec<u16>,
+    // Sorted by points, highest first
+    pub leaderboard: Vec<LeaderboardEntry>,
+    pub prize_pool: u64,
+    pub closed: bool,
+}
+
+#[repr(C)]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default)]
+pub struct LeaderboardEntry {
+    pub doge_racer: Pubkey,
+    pub doge_holder: Pubkey,
+    pub points: u64,
+}
+
+#[account]
+#[derive(Default)]
+pub struct SeasonRacerState {
+    pub bump: u8,
+    pub season: Pubkey,
+    pub doge_racer: Pubkey,
+    // Holder at the racer's latest settlement this season, receives any prize
+    pub doge_holder: Pubkey,
+    pub points: u64,
+    pub races_run: u64,
+    pub wins: u64,
+}
+
+#[account]
+#[derive(Default)]
+pub struct StreakConfigState {
+    pub bump: u8,
+    pub streak_config: StreakConfig,
+}
+
+// Winners reaching a win streak listed in `milestones` get `rake_share_bps` of the
+// protocol rake on top of their share
+#[repr(C)]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default)]
+pub struct StreakConfig {
+    pub rake_share_bps: u16,
+    pub milestones: Vec<u64>,
+}
+
+#[account]
+#[derive(Default)]
+pub struct StakeConfigState {
+    pub bump: u8,
+    pub total_staked: u64,
+    pub tiers: Vec<StakeTier>,
+}
+
+#[repr(C)]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default)]
+pub struct StakeTier {
+    pub min_amount: u64,
+    pub min_lock_duration: u64,
+    pub rake_discount_bps: u16,
+}
+
+#[account]
+#[derive(Default)]
+pub struct StakePosition {
+    pub bump: u8,
+    pub owner: Pubkey,
+    pub vault: Pubkey,
+    pub amount: u64,
+    pub locked_at: u64,
+    pub unlock_at: u64,
+}
@@ -1,14 +1,26 @@
-use crate::{constants::*, state::EntryFeeRequirements, state::LobbyData};
+use crate::{
+    constants::*,
+    error::GameError,
+    state::{
+        DogeRacerState, DustRecipient, EntryFeeRequirements, FeeSchedule, LobbyData,
+        LeaderboardEntry, ReferralConfig, ReferrerState, SeasonRacerState, SeasonState,
+        StakePosition, StakeTier, StreakConfig, TrackRacerState, TrackStatsState,
+    },
+};
 use anchor_lang::{
     prelude::*,
     solana_program::{
+        program::invoke,
         program_memory::sol_memcmp,
         program_pack::{IsInitialized, Pack},
+        program::invoke_signed,
+        system_instruction::{allocate, assign, create_account, transfer},
     },
+    Discriminator,
 };
 use anchor_spl::{
     associated_token::get_associated_token_address,
-    token::spl_token::{id as token_program_id, state::Account},
+    token::spl_token::{id as token_program_id, state::Account as SplTokenAccount},
 };
 use mpl_token_metadata::state::Data;
 use std::{result::Result as StdResult, str::FromStr};
@@ -25,10 +37,6 @@ pub fn get_wsol_mint() -> Pubkey {
     Pubkey::from_str(NATIVE_MINT).unwrap()
 }
 
-pub fn get_treasury_address() -> Pubkey {
-    Pubkey::from_str(TREASURY_ADDRESS).unwrap()
-}
-
 pub fn is_doge_stats_valid(
     init_authority: &Pubkey,
     doge_mint: &Pubkey,
@@ -70,17 +78,107 @@ pub fn find_racer_index(racers: &[Pubkey], racer_to_find: Option<Pubkey>) -> Opt
         .position(|&racer| racer.eq(&racer_to_find.unwrap_or_default()))
 }
 
-pub fn percentage_of(value: u64, pct: u64) -> StdResult<u64, ProgramError> {
-    value
-        .checked_mul(pct)
-        .and_then(|product| product.checked_div(100u64))
+pub fn bps_of(value: u64, bps: u16) -> StdResult<u64, ProgramError> {
+    (value as u128)
+        .checked_mul(bps.into())
+        .and_then(|product| product.checked_div(MAX_BPS.into()))
+        .and_then(|share| u64::try_from(share).ok())
         .ok_or(ProgramError::InvalidAccountData)
 }
 
+pub fn is_fee_schedule_valid(fee_schedule: &FeeSchedule) -> bool {
+    let total_bps = fee_schedule.track_owner_bps as u64
+        + fee_schedule.treasury_bps as u64
+        + fee_schedule.burn_bps as u64;
+
+    total_bps.le(&MAX_BPS)
+}
+
+// Splits the reward pool as per the fee schedule. Every share is rounded down and
+// whatever is left after rounding (dust) goes to the fee schedule's dust recipient,
+// so the shares always add up to the pool. The burn share only applies to DTRK
+// pools, otherwise it stays with the winner.
+pub fn split_pool(pool: u64, fee_schedule: &FeeSchedule, burn_enabled: bool) -> Result<PoolSplit> {
+    let burn_bps = if burn_enabled {
+        fee_schedule.burn_bps
+    } else {
+        0
+    };
+    let winner_bps = (MAX_BPS as u16)
+        .checked_sub(fee_schedule.track_owner_bps)
+        .and_then(|bps| bps.checked_sub(fee_schedule.treasury_bps))
+        .and_then(|bps| bps.checked_sub(burn_bps))
+        .ok_or(GameError::InvalidFeeSchedule)?;
+
+    let mut split = PoolSplit {
+        track_owner: bps_of(pool, fee_schedule.track_owner_bps)?,
+        treasury: bps_of(pool, fee_schedule.treasury_bps)?,
+        burn: bps_of(pool, burn_bps)?,
+        winner: bps_of(pool, winner_bps)?,
+    };
+
+    let dust = pool
+        .checked_sub(split.total().ok_or(GameError::MathOverflow)?)
+        .ok_or(GameError::InvalidPoolSplit)?;
+
+    match fee_schedule.dust_recipient {
+        DustRecipient::Winner => split.winner += dust,
+        DustRecipient::TrackOwner => split.track_owner += dust,
+        DustRecipient::Treasury => split.treasury += dust,
+    }
+
+    require!(
+        split.total().eq(&Some(pool)),
+        GameError::InvalidPoolSplit
+    );
+
+    Ok(split)
+}
+
+// Moves the discounted part of the protocol rake over to the winner
+pub fn apply_rake_discount(split: &mut PoolSplit, rake_discount_bps: u16) -> Result<u64> {
+    let rake_discount = bps_of(split.treasury, rake_discount_bps)?;
+
+    split.treasury = split
+        .treasury
+        .checked_sub(rake_discount)
+        .ok_or(GameError::MathOverflow)?;
+    split.winner = split
+        .winner
+        .checked_add(rake_discount)
+        .ok_or(GameError::MathOverflow)?;
+
+    Ok(rake_discount)
+}
+
+pub struct PoolSplit {
+    pub track_owner: u64,
+    pub treasury: u64,
+    pub burn: u64,
+    pub winner: u64,
+}
+
+impl PoolSplit {
+    pub fn total(&self) -> Option<u64> {
+        self.track_owner
+            .checked_add(self.treasury)
+            .and_then(|total| total.checked_add(self.burn))
+            .and_then(|total| total.checked_add(self.winner))
+    }
+}
+
 pub fn is_lobby_empty(racers: &[Pubkey]) -> bool {
     racers.iter().any(|&racer| racer.eq(&Pubkey::default()))
 }
 
+// Finishing order must list every lobby racer exactly once
+pub fn is_finishing_order_valid(racers: &[Pubkey], finishing_order: &[Pubkey]) -> bool {
+    finishing_order.len() == racers.len()
+        && racers
+            .iter()
+            .all(|racer| finishing_order.iter().filter(|&finisher| finisher.eq(racer)).count() == 1)
+}
+
 pub fn is_lobby_metadata_valid(
     metadata: &LobbyData,
     entry_fee_requirements: &EntryFeeRequirements,
@@ -134,29 +232,89 @@ pub fn get_entry_fee_bounds(
     }
 }
 
+// Converts an entry fee in ENTRY_FEE_DECIMALS precision to mint base units
 pub fn convert_to_entry_fee(amount: u64, decimals: u8) -> StdResult<u64, ProgramError> {
-    amount
-        .checked_mul(
-            10u64
-                .checked_pow(decimals.into())
-                .ok_or(ProgramError::InvalidArgument)?,
-        )
-        .ok_or(ProgramError::InvalidArgument)
+    if decimals >= ENTRY_FEE_DECIMALS {
+        amount
+            .checked_mul(
+                10u64
+                    .checked_pow((decimals - ENTRY_FEE_DECIMALS).into())
+                    .ok_or(ProgramError::InvalidArgument)?,
+            )
+            .ok_or(ProgramError::InvalidArgument)
+    } else {
+        convert_exact(amount, ENTRY_FEE_DECIMALS - decimals)
+    }
 }
 
+// Converts mint base units back to an entry fee in ENTRY_FEE_DECIMALS precision. Base units
+// below that precision are rounded down, so legacy amounts always convert
 pub fn convert_to_entry_fee_without_decimals(
     amount_with_decimals: u64,
     decimals: u8,
 ) -> StdResult<u64, ProgramError> {
-    amount_with_decimals
-        .checked_div(
-            10u64
-                .checked_pow(decimals.into())
-                .ok_or(ProgramError::InvalidArgument)?,
-        )
+    if decimals >= ENTRY_FEE_DECIMALS {
+        amount_with_decimals
+            .checked_div(
+                10u64
+                    .checked_pow((decimals - ENTRY_FEE_DECIMALS).into())
+                    .ok_or(ProgramError::InvalidArgument)?,
+            )
+            .ok_or(ProgramError::InvalidArgument)
+    } else {
+        amount_with_decimals
+            .checked_mul(
+                10u64
+                    .checked_pow((ENTRY_FEE_DECIMALS - decimals).into())
+                    .ok_or(ProgramError::InvalidArgument)?,
+            )
+            .ok_or(ProgramError::InvalidArgument)
+    }
+}
+
+// Scales down by 10^exponent, refusing amounts that would lose precision
+fn convert_exact(amount: u64, exponent: u8) -> StdResult<u64, ProgramError> {
+    let divisor = 10u64
+        .checked_pow(exponent.into())
+        .ok_or(ProgramError::InvalidArgument)?;
+
+    if amount.checked_rem(divisor).ne(&Some(0)) {
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    amount
+        .checked_div(divisor)
         .ok_or(ProgramError::InvalidArgument)
 }
 
+pub fn get_lobby_entry_fee(lobby_data: &LobbyData, decimals: u8) -> Result<u64> {
+    convert_to_entry_fee(lobby_data.entry_fee, decimals)
+        .map_err(|_| error!(GameError::InvalidEntryFeePrecision))
+}
+
+pub fn is_entry_fee_requirements_representable(
+    EntryFeeRequirements {
+        min_fee,
+        max_class_1_fee,
+        max_class_2_fee,
+        max_class_3_fee,
+        max_class_4_fee,
+        max_class_5_fee,
+    }: &EntryFeeRequirements,
+    decimals: u8,
+) -> bool {
+    [
+        min_fee,
+        max_class_1_fee,
+        max_class_2_fee,
+        max_class_3_fee,
+        max_class_4_fee,
+        max_class_5_fee,
+    ]
+    .iter()
+    .all(|&&fee| convert_to_entry_fee(fee, decimals).is_ok())
+}
+
 pub fn check_valid_ata(
     token_account: &UncheckedAccount,
     owner: &Pubkey,
@@ -180,7 +338,7 @@ pub fn check_valid_ata(
         return err!(ErrorCode::InvalidProgramId);
     }
 
-    let account = Account::unpack_unchecked(&token_account.to_account_info().data.borrow())?;
+    let account = SplTokenAccount::unpack_unchecked(&token_account.to_account_info().data.borrow())?;
 
     if !account.is_initialized() {
         msg!("ATA not initialized");
@@ -199,3 +357,1039 @@ pub fn check_valid_ata(
 
     Ok(())
 }
+
+pub fn is_referral_config_valid(referral_config: &ReferralConfig) -> bool {
+    (referral_config.share_bps as u64).le(&MAX_BPS)
+}
+
+// Referral rewards only flow to the racer's direct referrer, and only for the first
+// `max_referred_races` races within `duration` seconds of the racer registering.
+pub fn is_referral_active(
+    doge_racer: &DogeRacerState,
+    referral_config: &ReferralConfig,
+    current_timestamp: u64,
+) -> bool {
+    let races_run = doge_racer.total_wins.saturating_add(doge_racer.total_losses);
+
+    doge_racer.referrer.ne(&Pubkey::default())
+        && races_run.le(&referral_config.max_referred_races)
+        && current_timestamp.le(&doge_racer
+            .registered_at
+            .saturating_add(referral_config.duration))
+}
+
+pub fn load_referrer_account<'info>(
+    referrer_account: &AccountInfo<'info>,
+    referrer: &Pubkey,
+) -> Result<Account<'info, ReferrerState>> {
+    let referrer_state = Account::<ReferrerState>::try_from(referrer_account)?;
+
+    let expected_address = Pubkey::create_program_address(
+        &[b"referrer", referrer.as_ref(), &[referrer_state.bump]],
+        &crate::id(),
+    )
+    .map_err(|_| error!(GameError::InvalidReferrer))?;
+
+    require!(
+        referrer_account.key.eq(&expected_address) && referrer_state.referrer.eq(referrer),
+        GameError::InvalidReferrer
+    );
+
+    Ok(referrer_state)
+}
+
+// Remaining accounts come in (doge racer, doge stats) pairs following the finishing order
+pub fn get_finishing_order_doge_stats<'info>(
+    remaining_accounts: &[AccountInfo<'info>],
+    finishing_order: &[Pubkey],
+) -> Result<Vec<AccountInfo<'info>>> {
+    require!(
+        remaining_accounts.len() == finishing_order.len() * 2,
+        GameError::InvalidFinishingOrder
+    );
+
+    remaining_accounts
+        .chunks(2)
+        .zip(finishing_order)
+        .map(|(pair, racer)| {
+            let (doge_racer_info, doge_stats_info) = (&pair[0], &pair[1]);
+            require!(
+                doge_racer_info.key.eq(racer),
+                GameError::InvalidFinishingOrder
+            );
+
+            let doge_racer = Account::<DogeRacerState>::try_from(doge_racer_info)?;
+            require!(
+                doge_stats_info.key.eq(&doge_racer.doge_o_pda),
+                GameError::InvalidDogeStats
+            );
+
+            Ok(doge_stats_info.clone())
+        })
+        .collect()
+}
+
+pub fn is_prize_split_valid(prize_split_bps: &[u16]) -> bool {
+    !prize_split_bps.is_empty()
+        && prize_split_bps.len().le(&MAX_LEADERBOARD_SIZE)
+        && prize_split_bps
+            .iter()
+            .map(|&bps| bps as u64)
+            .sum::<u64>()
+            .le(&MAX_BPS)
+}
+
+// Prize per ranked entry with the split normalized over the ranks actually filled, so a
+// short leaderboard still pays out the whole pool save for rounding dust
+pub fn season_prizes(prize_pool: u64, prize_split_bps: &[u16], entries: usize) -> Result<Vec<u64>> {
+    let split = &prize_split_bps[..entries.min(prize_split_bps.len())];
+    let total_bps: u128 = split.iter().map(|&bps| bps as u128).sum();
+
+    split
+        .iter()
+        .map(|&bps| {
+            if total_bps == 0 {
+                return Ok(0);
+            }
+            (prize_pool as u128)
+                .checked_mul(bps.into())
+                .map(|product| product / total_bps)
+                .and_then(|prize| u64::try_from(prize).ok())
+                .ok_or_else(|| error!(GameError::MathOverflow))
+        })
+        .collect()
+}
+
+// A season takes results while it is open and inside its time window
+pub fn load_active_season<'info>(
+    season_account: &AccountInfo<'info>,
+    now: u64,
+) -> Result<Option<Account<'info, SeasonState>>> {
+    if season_account.owner.ne(&crate::id()) {
+        return Ok(None);
+    }
+
+    let season = Account::<SeasonState>::try_from(season_account)?;
+    let is_active = !season.closed && now.ge(&season.start_time) && now.lt(&season.end_time);
+
+    Ok(if is_active { Some(season) } else { None })
+}
+
+// One point per doge beaten plus one for finishing
+pub fn get_season_points(placement: usize, total_racers: usize) -> u64 {
+    total_racers.saturating_sub(placement) as u64
+}
+
+// Re-ranks the racer's entry, keeping only the best `size` entries
+pub fn update_leaderboard(leaderboard: &mut Vec<LeaderboardEntry>, entry: LeaderboardEntry, size: usize) {
+    leaderboard.retain(|existing| existing.doge_racer.ne(&entry.doge_racer));
+
+    let position = leaderboard
+        .iter()
+        .position(|existing| existing.points.lt(&entry.points))
+        .unwrap_or(leaderboard.len());
+    leaderboard.insert(position, entry);
+    leaderboard.truncate(size);
+}
+
+// Loads the racer's season record, creating it on their first race of the season
+pub fn load_or_create_season_racer<'info>(
+    season_racer_account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    season: &Pubkey,
+    doge_racer: &Pubkey,
+) -> Result<Account<'info, SeasonRacerState>> {
+    let (expected_address, bump) = Pubkey::find_program_address(
+        &[b"seasonracer", season.as_ref(), doge_racer.as_ref()],
+        &crate::id(),
+    );
+    require!(
+        season_racer_account.key.eq(&expected_address),
+        GameError::InvalidSeasonAccount
+    );
+
+    if season_racer_account.owner.eq(&crate::id()) {
+        return Account::<SeasonRacerState>::try_from(season_racer_account);
+    }
+
+    create_pda_account(
+        season_racer_account,
+        payer,
+        system_program,
+        8 + SEASON_RACER_SIZE,
+        &[b"seasonracer", season.as_ref(), doge_racer.as_ref(), &[bump]],
+    )?;
+
+    let mut season_racer = Account::<SeasonRacerState>::try_from_unchecked(season_racer_account)?;
+    season_racer.bump = bump;
+    season_racer.season = *season;
+    season_racer.doge_racer = *doge_racer;
+
+    Ok(season_racer)
+}
+
+// Loads the track's stats, creating them when the track hosts its first race
+pub fn load_or_create_track_stats<'info>(
+    track_stats_account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    track_mint: &Pubkey,
+) -> Result<Account<'info, TrackStatsState>> {
+    let (expected_address, bump) =
+        Pubkey::find_program_address(&[b"trackstats", track_mint.as_ref()], &crate::id());
+    require!(
+        track_stats_account.key.eq(&expected_address),
+        GameError::InvalidTrackStatsAccount
+    );
+
+    if track_stats_account.owner.eq(&crate::id()) {
+        return Account::<TrackStatsState>::try_from(track_stats_account);
+    }
+
+    create_pda_account(
+        track_stats_account,
+        payer,
+        system_program,
+        8 + TRACK_STATS_SIZE,
+        &[b"trackstats", track_mint.as_ref(), &[bump]],
+    )?;
+
+    let mut track_stats = Account::<TrackStatsState>::try_from_unchecked(track_stats_account)?;
+    track_stats.bump = bump;
+    track_stats.track_mint = *track_mint;
+
+    Ok(track_stats)
+}
+
+// Loads the racer's record on a track. The flag is set when this is their first race there
+pub fn load_or_create_track_racer<'info>(
+    track_racer_account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    track_mint: &Pubkey,
+    doge_racer: &Pubkey,
+) -> Result<(Account<'info, TrackRacerState>, bool)> {
+    let (expected_address, bump) = Pubkey::find_program_address(
+        &[b"trackracer", track_mint.as_ref(), doge_racer.as_ref()],
+        &crate::id(),
+    );
+    require!(
+        track_racer_account.key.eq(&expected_address),
+        GameError::InvalidTrackStatsAccount
+    );
+
+    if track_racer_account.owner.eq(&crate::id()) {
+        return Ok((
+            Account::<TrackRacerState>::try_from(track_racer_account)?,
+            false,
+        ));
+    }
+
+    create_pda_account(
+        track_racer_account,
+        payer,
+        system_program,
+        8 + TRACK_RACER_SIZE,
+        &[b"trackracer", track_mint.as_ref(), doge_racer.as_ref(), &[bump]],
+    )?;
+
+    let mut track_racer = Account::<TrackRacerState>::try_from_unchecked(track_racer_account)?;
+    track_racer.bump = bump;
+    track_racer.track_mint = *track_mint;
+    track_racer.doge_racer = *doge_racer;
+
+    Ok((track_racer, true))
+}
+
+pub fn is_streak_config_valid(streak_config: &StreakConfig) -> bool {
+    (streak_config.rake_share_bps as u64).le(&MAX_BPS)
+        && streak_config.milestones.len().le(&MAX_STREAK_MILESTONES)
+        && streak_config.milestones.iter().all(|&milestone| milestone.gt(&0))
+        && streak_config
+            .milestones
+            .windows(2)
+            .all(|pair| pair[0].lt(&pair[1]))
+}
+
+pub fn is_streak_milestone(streak_config: &StreakConfig, win_streak: u64) -> bool {
+    streak_config.rake_share_bps.gt(&0) && streak_config.milestones.contains(&win_streak)
+}
+
+pub fn is_stake_config_valid(tiers: &[StakeTier]) -> bool {
+    tiers.len().le(&MAX_STAKE_TIERS)
+        && tiers
+            .iter()
+            .all(|tier| (tier.rake_discount_bps as u64).le(&MAX_BPS))
+        && tiers
+            .windows(2)
+            .all(|pair| pair[0].min_amount.lt(&pair[1].min_amount))
+}
+
+// Locks a position for at least `lock_duration` from now. Topping up a position that is still
+// locked keeps the time it was locked at, so the tier span only ever grows, and the lock can
+// never end before the previous unlock time
+pub fn lock_stake_position(
+    stake_position: &mut StakePosition,
+    lock_duration: u64,
+    current_timestamp: u64,
+) -> Result<()> {
+    let unlock_at = current_timestamp
+        .checked_add(lock_duration)
+        .ok_or(GameError::MathOverflow)?;
+
+    if stake_position.unlock_at.le(&current_timestamp) {
+        stake_position.locked_at = current_timestamp;
+    }
+    stake_position.unlock_at = stake_position.unlock_at.max(unlock_at);
+
+    Ok(())
+}
+
+// A position only earns a tier while it is locked, picking the best tier it qualifies for
+pub fn get_stake_rake_discount(
+    stake_position: &StakePosition,
+    tiers: &[StakeTier],
+    current_timestamp: u64,
+) -> u16 {
+    if stake_position.unlock_at.le(&current_timestamp) {
+        return 0;
+    }
+
+    let lock_duration = stake_position
+        .unlock_at
+        .saturating_sub(stake_position.locked_at);
+
+    tiers
+        .iter()
+        .filter(|tier| {
+            stake_position.amount.ge(&tier.min_amount)
+                && lock_duration.ge(&tier.min_lock_duration)
+        })
+        .map(|tier| tier.rake_discount_bps)
+        .max()
+        .unwrap_or_default()
+}
+
+// Stake positions are optional at settlement, an uninitialized position PDA simply
+// means the holder has not staked
+pub fn load_stake_position<'info>(
+    stake_account: &AccountInfo<'info>,
+    owner: &Pubkey,
+) -> Result<Option<Account<'info, StakePosition>>> {
+    let (expected_address, _) =
+        Pubkey::find_program_address(&[b"stake", owner.as_ref()], &crate::id());
+
+    require!(
+        stake_account.key.eq(&expected_address),
+        GameError::InvalidStakePosition
+    );
+
+    if stake_account.owner.ne(&crate::id()) {
+        return Ok(None);
+    }
+
+    Ok(Some(Account::<StakePosition>::try_from(stake_account)?))
+}
+
+pub fn check_discriminator<T: Discriminator>(account: &AccountInfo) -> Result<()> {
+    let data = account.try_borrow_data()?;
+
+    require!(
+        data.len() >= 8 && data[..8].eq(&T::discriminator()),
+        ErrorCode::AccountDiscriminatorMismatch
+    );
+
+    Ok(())
+}
+
+// Grows a program account to `new_size`, topping up rent from the payer.
+// New bytes are zero initialized.
+pub fn grow_account<'info>(
+    account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    new_size: usize,
+) -> Result<()> {
+    let account_size = account.data_len();
+    msg!("Account data size {}", account_size);
+
+    if account_size >= new_size {
+        return Ok(());
+    }
+
+    let rent = Rent::get()?;
+    let lamports_to_send = rent
+        .minimum_balance(new_size)
+        .saturating_sub(account.lamports());
+
+    if lamports_to_send > 0 {
+        invoke(
+            &transfer(payer.key, account.key, lamports_to_send),
+            &[payer.clone(), account.clone(), system_program.clone()],
+        )?;
+    }
+
+    account.realloc(new_size, true)?;
+
+    Ok(())
+}
+
+// Race records are keyed by the lobby's race count at settlement
+pub fn get_race_data_address(lobby: &Pubkey, race_count: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"race", lobby.as_ref(), &race_count.to_le_bytes()],
+        &crate::id(),
+    )
+}
+
+// Creates a program owned PDA of `size` bytes. Lamports already sitting on the address
+// are kept so a pre-funded address can't block the creation
+pub fn create_pda_account<'info>(
+    account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    size: usize,
+    signer_seeds: &[&[u8]],
+) -> Result<()> {
+    let rent_lamports = Rent::get()?.minimum_balance(size);
+
+    if account.lamports() == 0 {
+        invoke_signed(
+            &create_account(payer.key, account.key, rent_lamports, size as u64, &crate::id()),
+            &[payer.clone(), account.clone(), system_program.clone()],
+            &[signer_seeds],
+        )?;
+        return Ok(());
+    }
+
+    let lamports_to_send = rent_lamports.saturating_sub(account.lamports());
+    if lamports_to_send > 0 {
+        invoke(
+            &transfer(payer.key, account.key, lamports_to_send),
+            &[payer.clone(), account.clone(), system_program.clone()],
+        )?;
+    }
+
+    invoke_signed(
+        &allocate(account.key, size as u64),
+        &[account.clone(), system_program.clone()],
+        &[signer_seeds],
+    )?;
+    invoke_signed(
+        &assign(account.key, &crate::id()),
+        &[account.clone(), system_program.clone()],
+        &[signer_seeds],
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{RecentRaceResult, TrackType};
+
+    fn fee_schedule(
+        track_owner_bps: u16,
+        treasury_bps: u16,
+        burn_bps: u16,
+        dust_recipient: DustRecipient,
+    ) -> FeeSchedule {
+        FeeSchedule {
+            track_owner_bps,
+            treasury_bps,
+            burn_bps,
+            dust_recipient,
+        }
+    }
+
+    fn dust(pool: u64, fee_schedule: &FeeSchedule, burn_enabled: bool) -> u64 {
+        let burn_bps = if burn_enabled {
+            fee_schedule.burn_bps
+        } else {
+            0
+        };
+        let winner_bps =
+            MAX_BPS as u16 - fee_schedule.track_owner_bps - fee_schedule.treasury_bps - burn_bps;
+
+        pool - bps_of(pool, fee_schedule.track_owner_bps).unwrap()
+            - bps_of(pool, fee_schedule.treasury_bps).unwrap()
+            - bps_of(pool, burn_bps).unwrap()
+            - bps_of(pool, winner_bps).unwrap()
+    }
+
+    #[test]
+    fn split_accounts_for_the_whole_pool() {
+        let schedules = [
+            fee_schedule(500, 300, 200, DustRecipient::Winner),
+            fee_schedule(333, 333, 333, DustRecipient::TrackOwner),
+            fee_schedule(1, 1, 1, DustRecipient::Treasury),
+            fee_schedule(0, 0, 0, DustRecipient::Treasury),
+            fee_schedule(10_000, 0, 0, DustRecipient::Winner),
+            fee_schedule(0, 10_000, 0, DustRecipient::TrackOwner),
+            fee_schedule(0, 0, 10_000, DustRecipient::Treasury),
+        ];
+
+        for fee_schedule in &schedules {
+            for pool in [0, 1, 2, 3, 7, 999, 10_001, 123_456_789, u64::MAX] {
+                for burn_enabled in [false, true] {
+                    let split = split_pool(pool, fee_schedule, burn_enabled).unwrap();
+
+                    assert_eq!(split.total(), Some(pool));
+                    assert!(dust(pool, fee_schedule, burn_enabled) < 4);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn dust_goes_to_the_dust_recipient() {
+        // 333 bps of 1000 rounds 33.3 down to 33 for each share, leaving 1 unit of dust
+        let winner = split_pool(
+            1000,
+            &fee_schedule(333, 333, 333, DustRecipient::Winner),
+            true,
+        )
+        .unwrap();
+        assert_eq!(
+            (
+                winner.track_owner,
+                winner.treasury,
+                winner.burn,
+                winner.winner
+            ),
+            (33, 33, 33, 901)
+        );
+
+        let track_owner = split_pool(
+            1000,
+            &fee_schedule(333, 333, 333, DustRecipient::TrackOwner),
+            true,
+        )
+        .unwrap();
+        assert_eq!((track_owner.track_owner, track_owner.winner), (34, 900));
+
+        let treasury = split_pool(
+            1000,
+            &fee_schedule(333, 333, 333, DustRecipient::Treasury),
+            true,
+        )
+        .unwrap();
+        assert_eq!((treasury.treasury, treasury.winner), (34, 900));
+    }
+
+    #[test]
+    fn full_shares_take_the_whole_pool() {
+        let track_owner = split_pool(
+            999,
+            &fee_schedule(10_000, 0, 0, DustRecipient::Winner),
+            true,
+        )
+        .unwrap();
+        assert_eq!((track_owner.track_owner, track_owner.winner), (999, 0));
+
+        let treasury = split_pool(
+            999,
+            &fee_schedule(0, 10_000, 0, DustRecipient::Winner),
+            true,
+        )
+        .unwrap();
+        assert_eq!((treasury.treasury, treasury.winner), (999, 0));
+
+        let burn = split_pool(
+            999,
+            &fee_schedule(0, 0, 10_000, DustRecipient::Winner),
+            true,
+        )
+        .unwrap();
+        assert_eq!((burn.burn, burn.winner), (999, 0));
+
+        let nothing_burned = split_pool(
+            999,
+            &fee_schedule(0, 0, 10_000, DustRecipient::Winner),
+            false,
+        )
+        .unwrap();
+        assert_eq!((nothing_burned.burn, nothing_burned.winner), (0, 999));
+
+        let no_fees =
+            split_pool(999, &fee_schedule(0, 0, 0, DustRecipient::Treasury), true).unwrap();
+        assert_eq!((no_fees.treasury, no_fees.winner), (0, 999));
+    }
+
+    #[test]
+    fn rejects_shares_above_the_pool() {
+        assert!(split_pool(
+            1000,
+            &fee_schedule(6000, 5000, 0, DustRecipient::Winner),
+            true
+        )
+        .is_err());
+        assert!(split_pool(
+            1000,
+            &fee_schedule(0, 5000, 6000, DustRecipient::Winner),
+            true
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn season_prizes_pay_out_a_short_leaderboard() {
+        let prize_split_bps = [5_000, 3_000, 2_000];
+
+        assert_eq!(
+            season_prizes(1_000, &prize_split_bps, 3).unwrap(),
+            vec![500, 300, 200]
+        );
+        assert_eq!(
+            season_prizes(1_000, &prize_split_bps, 2).unwrap(),
+            vec![625, 375]
+        );
+        assert_eq!(
+            season_prizes(1_000, &prize_split_bps, 1).unwrap(),
+            vec![1_000]
+        );
+        assert!(season_prizes(1_000, &prize_split_bps, 0)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn season_prizes_never_exceed_the_pool() {
+        for (prize_pool, prize_split_bps) in [
+            (999, vec![3_333, 3_333, 3_333]),
+            (u64::MAX, vec![1, 2, 3, 4]),
+            (7, vec![10_000]),
+            (1_000, vec![100, 0]),
+        ] {
+            for entries in 1..=prize_split_bps.len() {
+                let prizes = season_prizes(prize_pool, &prize_split_bps, entries).unwrap();
+                let paid: u128 = prizes.iter().map(|&prize| prize as u128).sum();
+
+                assert!(paid <= prize_pool as u128);
+                assert!(prize_pool as u128 - paid < entries as u128);
+            }
+        }
+    }
+
+    #[test]
+    fn season_prizes_without_shares_pay_nothing() {
+        assert_eq!(season_prizes(1_000, &[0, 0], 2).unwrap(), vec![0, 0]);
+    }
+
+    fn stake_tiers() -> Vec<StakeTier> {
+        vec![
+            StakeTier {
+                min_amount: 1_000,
+                min_lock_duration: 100,
+                rake_discount_bps: 1_000,
+            },
+            StakeTier {
+                min_amount: 10_000,
+                min_lock_duration: 1_000,
+                rake_discount_bps: 2_500,
+            },
+        ]
+    }
+
+    fn stake_position(amount: u64, locked_at: u64, unlock_at: u64) -> StakePosition {
+        StakePosition {
+            amount,
+            locked_at,
+            unlock_at,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn stake_discount_picks_the_best_qualifying_tier() {
+        let tiers = stake_tiers();
+
+        assert_eq!(
+            get_stake_rake_discount(&stake_position(10_000, 0, 1_000), &tiers, 10),
+            2_500
+        );
+        // Enough stake for the top tier but not locked long enough
+        assert_eq!(
+            get_stake_rake_discount(&stake_position(10_000, 0, 999), &tiers, 10),
+            1_000
+        );
+        assert_eq!(
+            get_stake_rake_discount(&stake_position(999, 0, 1_000), &tiers, 10),
+            0
+        );
+    }
+
+    #[test]
+    fn stake_discount_ends_at_unlock() {
+        let tiers = stake_tiers();
+        let position = stake_position(10_000, 0, 1_000);
+
+        assert_eq!(get_stake_rake_discount(&position, &tiers, 999), 2_500);
+        assert_eq!(get_stake_rake_discount(&position, &tiers, 1_000), 0);
+        assert_eq!(get_stake_rake_discount(&position, &tiers, 5_000), 0);
+    }
+
+    #[test]
+    fn stake_top_ups_keep_the_tier_span() {
+        let tiers = stake_tiers();
+        let mut position = stake_position(0, 0, 0);
+
+        lock_stake_position(&mut position, 1_000, 50).unwrap();
+        position.amount = 10_000;
+        assert_eq!((position.locked_at, position.unlock_at), (50, 1_050));
+        assert_eq!(get_stake_rake_discount(&position, &tiers, 600), 2_500);
+
+        // A short top-up mid lock neither shortens the lock nor restarts it
+        lock_stake_position(&mut position, 100, 600).unwrap();
+        assert_eq!((position.locked_at, position.unlock_at), (50, 1_050));
+        assert_eq!(get_stake_rake_discount(&position, &tiers, 601), 2_500);
+
+        // Once the lock ran out, staking again starts a new one
+        lock_stake_position(&mut position, 200, 2_000).unwrap();
+        assert_eq!((position.locked_at, position.unlock_at), (2_000, 2_200));
+        assert_eq!(get_stake_rake_discount(&position, &tiers, 2_001), 1_000);
+
+        assert!(lock_stake_position(&mut position, u64::MAX, 1).is_err());
+    }
+
+    #[test]
+    fn stake_config_needs_ascending_tiers_within_bps() {
+        let mut tiers = stake_tiers();
+        assert!(is_stake_config_valid(&tiers));
+        assert!(is_stake_config_valid(&[]));
+
+        tiers[1].min_amount = tiers[0].min_amount;
+        assert!(!is_stake_config_valid(&tiers));
+
+        let mut tiers = stake_tiers();
+        tiers[0].rake_discount_bps = MAX_BPS as u16 + 1;
+        assert!(!is_stake_config_valid(&tiers));
+
+        let tiers: Vec<StakeTier> = (0..=MAX_STAKE_TIERS as u64)
+            .map(|min_amount| StakeTier {
+                min_amount,
+                ..Default::default()
+            })
+            .collect();
+        assert!(!is_stake_config_valid(&tiers));
+    }
+
+    #[test]
+    fn entry_fees_scale_to_the_mint_decimals() {
+        assert_eq!(convert_to_entry_fee(150, 9).unwrap(), 1_500_000_000);
+        assert_eq!(convert_to_entry_fee(150, ENTRY_FEE_DECIMALS).unwrap(), 150);
+        assert_eq!(convert_to_entry_fee(150, 1).unwrap(), 15);
+        assert_eq!(convert_to_entry_fee(100, 0).unwrap(), 1);
+
+        // Fees finer than the mint can hold are refused rather than rounded
+        assert!(convert_to_entry_fee(155, 1).is_err());
+        assert!(convert_to_entry_fee(150, 0).is_err());
+    }
+
+    #[test]
+    fn entry_fees_refuse_overflows() {
+        assert!(convert_to_entry_fee(u64::MAX, 9).is_err());
+        assert!(convert_to_entry_fee(1, 22).is_err());
+        assert_eq!(convert_to_entry_fee(1, 21).unwrap(), 10u64.pow(19));
+    }
+
+    #[test]
+    fn legacy_fees_round_down_to_the_entry_fee_precision() {
+        assert_eq!(
+            convert_to_entry_fee_without_decimals(1_500_000_000, 9).unwrap(),
+            150
+        );
+        assert_eq!(
+            convert_to_entry_fee_without_decimals(1_509_999_999, 9).unwrap(),
+            150
+        );
+        assert_eq!(
+            convert_to_entry_fee_without_decimals(9_999_999, 9).unwrap(),
+            0
+        );
+        assert_eq!(convert_to_entry_fee_without_decimals(15, 1).unwrap(), 150);
+        assert_eq!(convert_to_entry_fee_without_decimals(1, 0).unwrap(), 100);
+
+        assert_eq!(
+            convert_to_entry_fee_without_decimals(u64::MAX, 21).unwrap(),
+            1
+        );
+        assert!(convert_to_entry_fee_without_decimals(1, 22).is_err());
+        assert!(convert_to_entry_fee_without_decimals(u64::MAX, 0).is_err());
+    }
+
+    #[test]
+    fn legacy_fees_never_grow_on_a_round_trip() {
+        for amount in [0, 1, 9_999_999, 10_000_000, 1_234_567_891] {
+            let entry_fee = convert_to_entry_fee_without_decimals(amount, 9).unwrap();
+            assert!(convert_to_entry_fee(entry_fee, 9).unwrap() <= amount);
+        }
+    }
+
+    #[test]
+    fn lobby_entry_fees_use_the_mint_decimals() {
+        let lobby_data = LobbyData {
+            entry_fee: 250,
+            ..Default::default()
+        };
+
+        assert_eq!(get_lobby_entry_fee(&lobby_data, 6).unwrap(), 2_500_000);
+        assert_eq!(get_lobby_entry_fee(&lobby_data, 1).unwrap(), 25);
+        assert!(get_lobby_entry_fee(&lobby_data, 0).is_err());
+        assert!(get_lobby_entry_fee(&lobby_data, 22).is_err());
+    }
+
+    fn referred_racer(total_wins: u64, total_losses: u64) -> DogeRacerState {
+        DogeRacerState {
+            referrer: Pubkey::new_unique(),
+            registered_at: 1_000,
+            total_wins,
+            total_losses,
+            ..Default::default()
+        }
+    }
+
+    fn referral_config(max_referred_races: u64, duration: u64) -> ReferralConfig {
+        ReferralConfig {
+            max_referred_races,
+            duration,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn referrals_accrue_for_max_referred_races() {
+        // Settlement counts the race being settled before checking the referral
+        let config = referral_config(3, 500);
+
+        assert!(is_referral_active(&referred_racer(1, 0), &config, 1_000));
+        assert!(is_referral_active(&referred_racer(1, 2), &config, 1_000));
+        assert!(!is_referral_active(&referred_racer(2, 2), &config, 1_000));
+        assert!(!is_referral_active(
+            &referred_racer(1, 0),
+            &referral_config(0, 500),
+            1_000
+        ));
+    }
+
+    #[test]
+    fn referrals_expire_after_the_duration() {
+        let config = referral_config(10, 500);
+        let racer = referred_racer(1, 0);
+
+        assert!(is_referral_active(&racer, &config, 1_500));
+        assert!(!is_referral_active(&racer, &config, 1_501));
+
+        let forever = referral_config(10, u64::MAX);
+        assert!(is_referral_active(&racer, &forever, u64::MAX));
+    }
+
+    #[test]
+    fn racers_without_a_referrer_accrue_nothing() {
+        let racer = DogeRacerState {
+            total_wins: 1,
+            ..Default::default()
+        };
+
+        assert!(!is_referral_active(&racer, &referral_config(10, 500), 0));
+    }
+
+    #[test]
+    fn season_prizes_leave_skipped_ranks_to_the_sweep() {
+        let prize_split_bps = [5_000, 0, 3_000, 2_000];
+        let prizes = season_prizes(1_001, &prize_split_bps, 4).unwrap();
+        assert_eq!(prizes, vec![500, 0, 300, 200]);
+
+        // A rank without a share is skipped, the ranks after it keep theirs
+        assert_eq!(
+            season_prizes(1_000, &prize_split_bps, 3).unwrap(),
+            vec![625, 0, 375]
+        );
+
+        // Skipping the third rank at close leaves its prize and the dust to be swept
+        let paid: u64 = prizes
+            .iter()
+            .enumerate()
+            .filter(|&(rank, _)| rank != 2)
+            .map(|(_, prize)| prize)
+            .sum();
+        assert_eq!(1_001 - paid, 301);
+    }
+
+    #[test]
+    fn doge_racer_layout_matches_the_doge_o_offsets() {
+        use doge_o::limits::{DOGE_RACER_CURRENT_LOBBY_OFFSET, DOGE_RACER_TOTAL_WINS_OFFSET};
+
+        let current_lobby_race = Pubkey::new_unique();
+        let racer = DogeRacerState {
+            current_lobby_race,
+            total_wins: 7,
+            total_losses: 12,
+            ..Default::default()
+        };
+        let mut data = Vec::new();
+        racer.try_serialize(&mut data).unwrap();
+
+        assert_eq!(
+            &data[DOGE_RACER_CURRENT_LOBBY_OFFSET..DOGE_RACER_CURRENT_LOBBY_OFFSET + 32],
+            current_lobby_race.as_ref()
+        );
+        assert_eq!(
+            &data[DOGE_RACER_TOTAL_WINS_OFFSET..DOGE_RACER_TOTAL_WINS_OFFSET + 16],
+            [7u64.to_le_bytes(), 12u64.to_le_bytes()]
+                .concat()
+                .as_slice()
+        );
+    }
+
+    #[test]
+    fn track_results_count_per_track_type() {
+        let mut racer = DogeRacerState::default();
+
+        racer.record_track_result(&TrackType::Sand, 0);
+        racer.record_track_result(&TrackType::Sand, PODIUM_PLACES - 1);
+        racer.record_track_result(&TrackType::Sand, PODIUM_PLACES);
+        racer.record_track_result(&TrackType::Dirt, 1);
+
+        let sand = racer.track_type_stats[TrackType::Sand.index()];
+        assert_eq!(
+            (sand.races_run, sand.wins, sand.losses, sand.podiums),
+            (3, 1, 2, 2)
+        );
+        let dirt = racer.track_type_stats[TrackType::Dirt.index()];
+        assert_eq!(
+            (dirt.races_run, dirt.wins, dirt.losses, dirt.podiums),
+            (1, 0, 1, 1)
+        );
+        assert_eq!(
+            racer.track_type_stats[TrackType::Space.index()].races_run,
+            0
+        );
+    }
+
+    fn recent_result(race_number: u64) -> RecentRaceResult {
+        RecentRaceResult {
+            race_number,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn recent_results_wrap_around_the_ring_buffer() {
+        let mut racer = DogeRacerState::default();
+
+        for race_number in 1..=RECENT_RESULTS_LEN as u64 {
+            racer.push_recent_result(recent_result(race_number));
+        }
+        assert_eq!(racer.recent_results_head, 0);
+        assert_eq!(racer.recent_results[RECENT_RESULTS_LEN - 1].race_number, 10);
+
+        racer.push_recent_result(recent_result(11));
+        racer.push_recent_result(recent_result(12));
+
+        assert_eq!(racer.recent_results_head, 2);
+        let race_numbers: Vec<u64> = racer
+            .recent_results
+            .iter()
+            .map(|result| result.race_number)
+            .collect();
+        assert_eq!(race_numbers, vec![11, 12, 3, 4, 5, 6, 7, 8, 9, 10]);
+    }
+
+    #[test]
+    fn recent_results_recover_from_an_out_of_range_head() {
+        let mut racer = DogeRacerState {
+            recent_results_head: RECENT_RESULTS_LEN as u8 + 3,
+            ..Default::default()
+        };
+
+        racer.push_recent_result(recent_result(1));
+
+        assert_eq!(racer.recent_results[3].race_number, 1);
+        assert_eq!(racer.recent_results_head, 4);
+    }
+
+    #[test]
+    fn streaks_reset_on_the_other_outcome() {
+        let mut racer = DogeRacerState::default();
+
+        for is_winner in [true, true, true, false, false, true] {
+            racer.record_streak(is_winner);
+        }
+        assert_eq!(
+            (
+                racer.current_win_streak,
+                racer.best_win_streak,
+                racer.current_loss_streak
+            ),
+            (1, 3, 0)
+        );
+
+        racer.record_streak(false);
+        assert_eq!(
+            (
+                racer.current_win_streak,
+                racer.best_win_streak,
+                racer.current_loss_streak
+            ),
+            (0, 3, 1)
+        );
+    }
+
+    #[test]
+    fn best_win_streaks_only_grow() {
+        let mut racer = DogeRacerState {
+            best_win_streak: 2,
+            ..Default::default()
+        };
+
+        racer.record_streak(true);
+        assert_eq!(racer.best_win_streak, 2);
+        racer.record_streak(true);
+        racer.record_streak(true);
+        assert_eq!(racer.best_win_streak, 3);
+    }
+
+    #[test]
+    fn entry_fee_volume_adds_up_per_mint() {
+        let mut track_stats = TrackStatsState::default();
+        let first_mint = Pubkey::new_unique();
+        let second_mint = Pubkey::new_unique();
+
+        track_stats.add_entry_fee_volume(&first_mint, 100).unwrap();
+        track_stats.add_entry_fee_volume(&second_mint, 5).unwrap();
+        track_stats.add_entry_fee_volume(&first_mint, 250).unwrap();
+
+        let volumes: Vec<(Pubkey, u64)> = track_stats
+            .entry_fee_volume
+            .iter()
+            .map(|mint_volume| (mint_volume.mint, mint_volume.volume))
+            .collect();
+        assert_eq!(volumes, vec![(first_mint, 350), (second_mint, 5)]);
+
+        assert!(track_stats
+            .add_entry_fee_volume(&second_mint, u64::MAX)
+            .is_err());
+        assert_eq!(track_stats.entry_fee_volume[1].volume, 5);
+    }
+
+    #[test]
+    fn entry_fee_volume_holds_a_limited_number_of_mints() {
+        let mut track_stats = TrackStatsState::default();
+        for _ in 0..MAX_TRACK_FEE_MINTS {
+            track_stats
+                .add_entry_fee_volume(&Pubkey::new_unique(), 1)
+                .unwrap();
+        }
+
+        assert!(track_stats
+            .add_entry_fee_volume(&Pubkey::new_unique(), 1)
+            .is_err());
+        // Mints already tracked keep adding up
+        let known_mint = track_stats.entry_fee_volume[0].mint;
+        track_stats.add_entry_fee_volume(&known_mint, 1).unwrap();
+        assert_eq!(track_stats.entry_fee_volume.len(), MAX_TRACK_FEE_MINTS);
+    }
+}
@@ -0,0 +1,90 @@
+use anchor_lang::{AccountDeserialize, Discriminator, Result};
+use doge_o::DogeStats;
+use dogegamecontract::state::{DogeRacerState, LobbyState, RaceState};
+
+// Decodes an Anchor account, checking its discriminator
+pub fn decode<T: AccountDeserialize>(mut data: &[u8]) -> Result<T> {
+    T::try_deserialize(&mut data)
+}
+
+pub fn decode_lobby(data: &[u8]) -> Result<LobbyState> {
+    decode(data)
+}
+
+pub fn decode_doge_racer(data: &[u8]) -> Result<DogeRacerState> {
+    decode(data)
+}
+
+pub fn decode_race_state(data: &[u8]) -> Result<RaceState> {
+    decode(data)
+}
+
+pub fn decode_doge_stats(data: &[u8]) -> Result<DogeStats> {
+    decode(data)
+}
+
+// Whether raw account data holds a `T`, for filtering program account scans
+pub fn is_account<T: Discriminator>(data: &[u8]) -> bool {
+    data.len() >= 8 && data[..8].eq(&T::discriminator())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::{
+        prelude::{AccountInfo, Pubkey},
+        AccountSerialize,
+    };
+    use doge_o::limits::{get_doge_racer_record, is_doge_racing};
+
+    #[test]
+    fn decodes_serialized_lobby() {
+        let lobby = LobbyState {
+            max_players: 5,
+            race_started: true,
+            racers: vec![Pubkey::new_unique(); 5],
+            race_count: 42,
+            ..Default::default()
+        };
+        let mut data = Vec::new();
+        lobby.try_serialize(&mut data).unwrap();
+
+        let decoded = decode_lobby(&data).unwrap();
+
+        assert!(is_account::<LobbyState>(&data));
+        assert_eq!(decoded.racers, lobby.racers);
+        assert_eq!(decoded.race_count, 42);
+        assert!(decoded.race_started);
+    }
+
+    #[test]
+    fn rejects_other_accounts() {
+        let mut data = Vec::new();
+        DogeRacerState::default().try_serialize(&mut data).unwrap();
+
+        assert!(!is_account::<LobbyState>(&data));
+        assert!(decode_lobby(&data).is_err());
+        assert!(decode_doge_racer(&data).is_ok());
+    }
+
+    #[test]
+    fn doge_o_reads_the_racer_record() {
+        let doge_racer = DogeRacerState {
+            current_lobby_race: Pubkey::new_unique(),
+            total_wins: 7,
+            total_losses: 12,
+            ..Default::default()
+        };
+        let mut data = Vec::new();
+        doge_racer.try_serialize(&mut data).unwrap();
+
+        let key = Pubkey::new_unique();
+        let owner = dogegamecontract::id();
+        let mut lamports = 0;
+        let info = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+
+        assert_eq!(get_doge_racer_record(&info, &owner).unwrap(), (7, 12));
+        assert!(get_doge_racer_record(&info, &Pubkey::new_unique()).is_err());
+        assert!(is_doge_racing(&info, &owner).unwrap());
+    }
+}
@@ -0,0 +1,124 @@
+use anchor_lang::{AccountDeserialize, Discriminator};
+use anyhow::{anyhow, Result};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    rpc_client::RpcClient,
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::{Memcmp, MemcmpEncodedBytes, RpcFilterType},
+};
+use solana_sdk::{
+    account::{from_account, Account},
+    clock::Clock,
+    hash::Hash,
+    pubkey::Pubkey,
+    signature::Signature,
+    sysvar,
+    transaction::Transaction,
+};
+
+use crate::accounts::decode;
+
+pub struct Simulation {
+    pub error: Option<String>,
+    pub logs: Vec<String>,
+}
+
+// Everything the operator tooling needs from a cluster. Implemented for `RpcClient`, and by
+// tests over an in-process bank
+pub trait Rpc {
+    fn get_account(&self, address: &Pubkey) -> Result<Option<Account>>;
+
+    // Accounts owned by `program_id` whose data starts with `prefix`
+    fn get_program_accounts(
+        &self,
+        program_id: &Pubkey,
+        prefix: &[u8],
+    ) -> Result<Vec<(Pubkey, Account)>>;
+
+    fn get_latest_blockhash(&self) -> Result<Hash>;
+
+    // Sends the transaction and waits for it to be confirmed
+    fn send_transaction(&self, transaction: &Transaction) -> Result<Signature>;
+
+    fn simulate_transaction(&self, transaction: &Transaction) -> Result<Simulation>;
+
+    // Cluster time, which is what the programs compare cooldowns against
+    fn get_unix_timestamp(&self) -> Result<i64> {
+        let clock_account = self
+            .get_account(&sysvar::clock::id())?
+            .ok_or_else(|| anyhow!("Clock sysvar not found"))?;
+        let clock = from_account::<Clock, _>(&clock_account)
+            .ok_or_else(|| anyhow!("Invalid clock sysvar"))?;
+
+        Ok(clock.unix_timestamp)
+    }
+}
+
+impl Rpc for RpcClient {
+    fn get_account(&self, address: &Pubkey) -> Result<Option<Account>> {
+        Ok(self
+            .get_account_with_commitment(address, self.commitment())?
+            .value)
+    }
+
+    fn get_program_accounts(
+        &self,
+        program_id: &Pubkey,
+        prefix: &[u8],
+    ) -> Result<Vec<(Pubkey, Account)>> {
+        let config = RpcProgramAccountsConfig {
+            filters: Some(vec![RpcFilterType::Memcmp(Memcmp {
+                offset: 0,
+                bytes: MemcmpEncodedBytes::Base58(bs58::encode(prefix).into_string()),
+                encoding: None,
+            })]),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                ..RpcAccountInfoConfig::default()
+            },
+            with_context: None,
+        };
+
+        Ok(self.get_program_accounts_with_config(program_id, config)?)
+    }
+
+    fn get_latest_blockhash(&self) -> Result<Hash> {
+        Ok(RpcClient::get_latest_blockhash(self)?)
+    }
+
+    fn send_transaction(&self, transaction: &Transaction) -> Result<Signature> {
+        Ok(self.send_and_confirm_transaction(transaction)?)
+    }
+
+    fn simulate_transaction(&self, transaction: &Transaction) -> Result<Simulation> {
+        let result = RpcClient::simulate_transaction(self, transaction)?.value;
+
+        Ok(Simulation {
+            error: result.err.map(|err| err.to_string()),
+            logs: result.logs.unwrap_or_default(),
+        })
+    }
+}
+
+pub fn fetch<T: AccountDeserialize>(rpc: &dyn Rpc, address: &Pubkey) -> Result<Option<T>> {
+    rpc.get_account(address)?
+        .map(|account| decode::<T>(&account.data).map_err(|err| anyhow!("{}: {}", address, err)))
+        .transpose()
+}
+
+// Every `T` owned by `program_id`. Accounts that no longer decode, such as layouts awaiting a
+// migration, are skipped
+pub fn fetch_all<T: AccountDeserialize + Discriminator>(
+    rpc: &dyn Rpc,
+    program_id: &Pubkey,
+) -> Result<Vec<(Pubkey, T)>> {
+    Ok(rpc
+        .get_program_accounts(program_id, &T::discriminator())?
+        .into_iter()
+        .filter_map(|(address, account)| {
+            decode::<T>(&account.data)
+                .ok()
+                .map(|decoded| (address, decoded))
+        })
+        .collect())
+}
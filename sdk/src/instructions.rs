@@ -0,0 +1,506 @@
+use anchor_lang::{
+    prelude::{AccountMeta, Pubkey},
+    solana_program::instruction::Instruction,
+    system_program, InstructionData, ToAccountMetas,
+};
+use anchor_spl::{associated_token::get_associated_token_address, token};
+use dogegamecontract::{
+    state::{EntryFeeRequirements, LobbyState},
+    utils::get_wsol_mint,
+};
+
+use crate::{pda::*, DogeKeys};
+
+// Season a settlement is scored in
+#[derive(Clone, Copy, Debug)]
+pub struct SeasonKeys {
+    pub season_id: u64,
+    pub prize_mint: Pubkey,
+}
+
+pub struct ConcludeRaceArgs<'a> {
+    pub authority: Pubkey,
+    pub lobby: &'a LobbyState,
+    // Doge being settled by this instruction
+    pub doge: DogeKeys,
+    pub doge_holder: Pubkey,
+    // The racer's referrer, when they registered with one
+    pub referrer: Option<Pubkey>,
+    // Active season, if any
+    pub season: Option<SeasonKeys>,
+}
+
+pub fn lobby_key(lobby: &LobbyState) -> Pubkey {
+    lobby_address(&lobby.track_keys.track_holder, &lobby.track_keys.track_mint).0
+}
+
+// The doge holder pays entry fees and network fees from their associated token accounts
+pub fn join_race(doge_holder: &Pubkey, doge: &DogeKeys, lobby: &LobbyState) -> Instruction {
+    let track_keys = &lobby.track_keys;
+    let wsol_mint = get_wsol_mint();
+
+    let accounts = dogegamecontract::accounts::JoinRace {
+        doge_holder: *doge_holder,
+        init_authority: doge.init_authority,
+        track_holder: track_keys.track_holder,
+        doge_racer_account: doge.doge_racer(),
+        lobby_account: lobby_key(lobby),
+        doge_o_pda: doge.doge_stats(),
+        doge_token_account: get_associated_token_address(doge_holder, &doge.doge_mint),
+        track_mint: track_keys.track_mint,
+        doge_mint: doge.doge_mint,
+        doge_metadata: doge.doge_metadata(),
+        wsol_mint,
+        doge_holder_wsol: get_associated_token_address(doge_holder, &wsol_mint),
+        lobby_wsol_token: track_keys.lobby_wsol_token,
+        entry_fee_mint: track_keys.entry_fee_mint,
+        doge_holder_entry_fee_token: get_associated_token_address(
+            doge_holder,
+            &track_keys.entry_fee_mint,
+        ),
+        lobby_entry_fee_token: track_keys.lobby_entry_fee_token,
+        token_program: token::ID,
+        system_program: system_program::ID,
+    };
+
+    Instruction {
+        program_id: dogegamecontract::id(),
+        accounts: accounts.to_account_metas(None),
+        data: dogegamecontract::instruction::JoinRace {}.data(),
+    }
+}
+
+// Records the lobby's current race. The finishing order is carried as every racer's (doge racer,
+// doge stats) pair, in order. Has to land before any racer of the race is recorded or settled
+pub fn record_race(
+    authority: &Pubkey,
+    lobby: &LobbyState,
+    finishing_order: &[DogeKeys],
+    race_started: u64,
+    winning_time: u64,
+) -> Instruction {
+    let track_keys = &lobby.track_keys;
+    let lobby_account = lobby_key(lobby);
+
+    let accounts = dogegamecontract::accounts::RecordRace {
+        authority: *authority,
+        lobby_account,
+        entry_fee_mint: track_keys.entry_fee_mint,
+        track_mint: track_keys.track_mint,
+        track_holder: track_keys.track_holder,
+        race_data_state: race_data_address(&lobby_account, lobby.race_count).0,
+        track_stats_account: track_stats_address(&track_keys.track_mint).0,
+        system_program: system_program::ID,
+        doge_o_program: doge_o::id(),
+    };
+
+    let mut account_metas = accounts.to_account_metas(None);
+    account_metas.extend(finishing_order.iter().flat_map(|doge| {
+        [
+            AccountMeta::new_readonly(doge.doge_racer(), false),
+            AccountMeta::new(doge.doge_stats(), false),
+        ]
+    }));
+
+    Instruction {
+        program_id: dogegamecontract::id(),
+        accounts: account_metas,
+        data: dogegamecontract::instruction::RecordRace {
+            race_started,
+            winning_time,
+        }
+        .data(),
+    }
+}
+
+// Counts one racer's result of the recorded race towards their season and track records. Has
+// to land before the racer is settled
+pub fn record_racer_result(
+    authority: &Pubkey,
+    lobby: &LobbyState,
+    doge: &DogeKeys,
+    doge_holder_entry_fee_token: &Pubkey,
+    season: Option<SeasonKeys>,
+) -> Instruction {
+    let track_mint = lobby.track_keys.track_mint;
+    let lobby_account = lobby_key(lobby);
+    let doge_racer = doge.doge_racer();
+
+    // Without an active season the program ignores these, any uninitialized address will do
+    let season_account = season_address(season.map_or(u64::MAX, |season| season.season_id)).0;
+
+    let accounts = dogegamecontract::accounts::RecordRacerResult {
+        authority: *authority,
+        doge_racer_account: doge_racer,
+        doge_holder_entry_fee_token: *doge_holder_entry_fee_token,
+        lobby_account,
+        race_data_state: race_data_address(&lobby_account, lobby.race_count).0,
+        track_stats_account: track_stats_address(&track_mint).0,
+        track_racer_account: track_racer_address(&track_mint, &doge_racer).0,
+        season_account,
+        season_racer_account: season_racer_address(&season_account, &doge_racer).0,
+        system_program: system_program::ID,
+    };
+
+    Instruction {
+        program_id: dogegamecontract::id(),
+        accounts: accounts.to_account_metas(None),
+        data: dogegamecontract::instruction::RecordRacerResult {}.data(),
+    }
+}
+
+// Settles one racer at the placement record_race wrote down, once their result is recorded.
+// The lobby resets once every racer of the race has settled, in any order
+pub fn conclude_race(args: &ConcludeRaceArgs) -> Instruction {
+    let lobby = args.lobby;
+    let track_keys = &lobby.track_keys;
+    let lobby_account = lobby_key(lobby);
+    let entry_fee_mint = track_keys.entry_fee_mint;
+    let wsol_mint = get_wsol_mint();
+    let doge_racer = args.doge.doge_racer();
+
+    // Without an active season the program ignores these, any uninitialized address will do
+    let (season_account, prize_mint) = match args.season {
+        Some(season) => (season_address(season.season_id).0, season.prize_mint),
+        None => (season_address(u64::MAX).0, entry_fee_mint),
+    };
+
+    let accounts = dogegamecontract::accounts::ConcludeRace {
+        authority: args.authority,
+        init_authority: args.doge.init_authority,
+        doge_racer_account: doge_racer,
+        lobby_account,
+        doge_o_pda: args.doge.doge_stats(),
+        doge_mint: args.doge.doge_mint,
+        doge_metadata: args.doge.doge_metadata(),
+        entry_fee_mint,
+        wsol_mint,
+        track_mint: track_keys.track_mint,
+        doge_holder_entry_fee_token: get_associated_token_address(
+            &args.doge_holder,
+            &entry_fee_mint,
+        ),
+        track_holder_entry_fee_token: track_keys.track_holder_entry_fee_token,
+        lobby_entry_fee_token: track_keys.lobby_entry_fee_token,
+        lobby_wsol_token: track_keys.lobby_wsol_token,
+        token_program: token::ID,
+        system_program: system_program::ID,
+        doge_o_program: doge_o::id(),
+        game_config: game_config_address().0,
+        treasury_wsol_token: treasury_vault_address(&wsol_mint),
+        treasury_account: treasury_address().0,
+        treasury_entry_fee_token: treasury_vault_address(&entry_fee_mint),
+        fee_schedule_account: fee_schedule_address().0,
+        referral_config_account: referral_config_address().0,
+        referrer_account: referrer_address(&args.referrer.unwrap_or_default()).0,
+        stake_config_account: stake_config_address().0,
+        doge_holder_stake_account: stake_position_address(&args.doge_holder).0,
+        streak_config_account: streak_config_address().0,
+        season_account,
+        season_prize_vault: season_prize_vault_address(&season_account, &prize_mint),
+        race_data_state: race_data_address(&lobby_account, lobby.race_count).0,
+        track_holder: track_keys.track_holder,
+        doge_holder: args.doge_holder,
+    };
+
+    Instruction {
+        program_id: dogegamecontract::id(),
+        accounts: accounts.to_account_metas(None),
+        data: dogegamecontract::instruction::ConcludeRace {}.data(),
+    }
+}
+
+// Refunds a racer stuck in a lobby that never filled up
+pub fn flush_stale_racer(
+    authority: &Pubkey,
+    doge: &DogeKeys,
+    doge_holder: &Pubkey,
+    doge_holder_entry_fee_token: &Pubkey,
+    lobby: &LobbyState,
+) -> Instruction {
+    let track_keys = &lobby.track_keys;
+    let wsol_mint = get_wsol_mint();
+
+    let accounts = dogegamecontract::accounts::FlushStaleRacer {
+        doge_holder: *doge_holder,
+        authority: *authority,
+        init_authority: doge.init_authority,
+        track_holder: track_keys.track_holder,
+        doge_racer_account: doge.doge_racer(),
+        lobby_account: lobby_key(lobby),
+        doge_o_pda: doge.doge_stats(),
+        track_mint: track_keys.track_mint,
+        doge_mint: doge.doge_mint,
+        doge_metadata: doge.doge_metadata(),
+        wsol_mint,
+        doge_holder_wsol: get_associated_token_address(doge_holder, &wsol_mint),
+        lobby_wsol_token: track_keys.lobby_wsol_token,
+        entry_fee_mint: track_keys.entry_fee_mint,
+        doge_holder_entry_fee_token: *doge_holder_entry_fee_token,
+        lobby_entry_fee_token: track_keys.lobby_entry_fee_token,
+        token_program: token::ID,
+        system_program: system_program::ID,
+    };
+
+    Instruction {
+        program_id: dogegamecontract::id(),
+        accounts: accounts.to_account_metas(None),
+        data: dogegamecontract::instruction::FlushStaleRacer {}.data(),
+    }
+}
+
+pub fn init_entry_fee_requirements(
+    authority: &Pubkey,
+    entry_fee_mint: &Pubkey,
+    entry_fee_requirements: EntryFeeRequirements,
+) -> Instruction {
+    let accounts = dogegamecontract::accounts::InitEntryFeeRequirements {
+        authority: *authority,
+        entry_fee_requirements_account: entry_fee_requirements_address(entry_fee_mint).0,
+        entry_fee_mint: *entry_fee_mint,
+        system_program: system_program::ID,
+    };
+
+    Instruction {
+        program_id: dogegamecontract::id(),
+        accounts: accounts.to_account_metas(None),
+        data: dogegamecontract::instruction::InitEntryFeeRequirements {
+            entry_fee_requirements,
+        }
+        .data(),
+    }
+}
+
+pub fn update_entry_fee_requirements(
+    authority: &Pubkey,
+    entry_fee_mint: &Pubkey,
+    new_entry_fee_requirements: EntryFeeRequirements,
+) -> Instruction {
+    let accounts = dogegamecontract::accounts::UpdateEntryFeeRequirements {
+        authority: *authority,
+        entry_fee_requirements_account: entry_fee_requirements_address(entry_fee_mint).0,
+        entry_fee_mint: *entry_fee_mint,
+        system_program: system_program::ID,
+    };
+
+    Instruction {
+        program_id: dogegamecontract::id(),
+        accounts: accounts.to_account_metas(None),
+        data: dogegamecontract::instruction::UpdateEntryFeeRequirements {
+            new_entry_fee_requirements,
+        }
+        .data(),
+    }
+}
+
+pub fn admin_close_entry_fee_requirements(
+    authority: &Pubkey,
+    entry_fee_mint: &Pubkey,
+) -> Instruction {
+    let accounts = dogegamecontract::accounts::AdminCloseEntryFeeRequirements {
+        authority: *authority,
+        entry_fee_mint: *entry_fee_mint,
+        entry_fee_requirements_account: entry_fee_requirements_address(entry_fee_mint).0,
+    };
+
+    Instruction {
+        program_id: dogegamecontract::id(),
+        accounts: accounts.to_account_metas(None),
+        data: dogegamecontract::instruction::AdminCloseEntryFeeRequirements {}.data(),
+    }
+}
+
+// Returns the lobby's track and balances to the track holder and closes it
+pub fn admin_close_lobby(authority: &Pubkey, lobby: &LobbyState) -> Instruction {
+    let track_keys = &lobby.track_keys;
+    let wsol_mint = get_wsol_mint();
+
+    let accounts = dogegamecontract::accounts::AdminCloseLobby {
+        authority: *authority,
+        treasury_account: treasury_address().0,
+        track_holder: track_keys.track_holder,
+        lobby_account: lobby_key(lobby),
+        track_mint: track_keys.track_mint,
+        entry_fee_mint: track_keys.entry_fee_mint,
+        wsol_mint,
+        lobby_entry_fee_token: track_keys.lobby_entry_fee_token,
+        track_holder_entry_fee_token: track_keys.track_holder_entry_fee_token,
+        lobby_wsol_token: track_keys.lobby_wsol_token,
+        treasury_wsol_token: treasury_vault_address(&wsol_mint),
+        track_holder_token: track_keys.track_holder_token,
+        lobby_track_token: track_keys.lobby_track_token,
+        token_program: token::ID,
+        system_program: system_program::ID,
+    };
+
+    Instruction {
+        program_id: dogegamecontract::id(),
+        accounts: accounts.to_account_metas(None),
+        data: dogegamecontract::instruction::AdminCloseLobby {}.data(),
+    }
+}
+
+pub fn admin_close_doge_racer(authority: &Pubkey, doge_racer: &Pubkey) -> Instruction {
+    let accounts = dogegamecontract::accounts::CloseDogeRacer {
+        authority: *authority,
+        doge_racer_account: *doge_racer,
+    };
+
+    Instruction {
+        program_id: dogegamecontract::id(),
+        accounts: accounts.to_account_metas(None),
+        data: dogegamecontract::instruction::AdminCloseDogeRacer {}.data(),
+    }
+}
+
+// Closes a race record, current or legacy
+pub fn admin_close_race_state(authority: &Pubkey, race_data: &Pubkey) -> Instruction {
+    let accounts = dogegamecontract::accounts::AdminCloseRaceState {
+        authority: *authority,
+        race_data_state: *race_data,
+    };
+
+    Instruction {
+        program_id: dogegamecontract::id(),
+        accounts: accounts.to_account_metas(None),
+        data: dogegamecontract::instruction::AdminCloseRaceState {}.data(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::{solana_program::message::Message, AnchorDeserialize};
+    use doge_o::rating::MAX_RATED_RACERS;
+    use dogegamecontract::state::TrackKeys;
+
+    // Largest serialized transaction a validator accepts
+    const PACKET_DATA_SIZE: usize = 1280 - 40 - 8;
+
+    fn lobby(finishing_order: &[DogeKeys]) -> LobbyState {
+        let mut lobby = LobbyState {
+            max_players: finishing_order.len() as u8,
+            race_started: true,
+            racers: finishing_order.iter().map(DogeKeys::doge_racer).collect(),
+            race_count: 7,
+            ..Default::default()
+        };
+        lobby.track_keys = TrackKeys {
+            track_mint: Pubkey::new_unique(),
+            entry_fee_mint: Pubkey::new_unique(),
+            track_metadata: Pubkey::new_unique(),
+            lobby_entry_fee_token: Pubkey::new_unique(),
+            lobby_track_token: Pubkey::new_unique(),
+            lobby_wsol_token: Pubkey::new_unique(),
+            track_holder: Pubkey::new_unique(),
+            track_holder_token: Pubkey::new_unique(),
+            track_holder_entry_fee_token: Pubkey::new_unique(),
+        };
+        lobby
+    }
+
+    // Size on the wire of a transaction sending the instruction alone, signed by the payer
+    fn transaction_size(instruction: Instruction, payer: &Pubkey) -> usize {
+        let message = Message::new(&[instruction], Some(payer));
+        let signatures = message.header.num_required_signatures as usize;
+
+        1 + 64 * signatures + message.serialize().len()
+    }
+
+    fn finishing_order(racers: usize) -> Vec<DogeKeys> {
+        (0..racers)
+            .map(|_| DogeKeys::new(Pubkey::new_unique(), Pubkey::new_unique()))
+            .collect()
+    }
+
+    fn conclude_args(lobby: &LobbyState, doge: DogeKeys) -> ConcludeRaceArgs<'_> {
+        ConcludeRaceArgs {
+            authority: Pubkey::new_unique(),
+            lobby,
+            doge,
+            doge_holder: Pubkey::new_unique(),
+            referrer: None,
+            season: None,
+        }
+    }
+
+    #[test]
+    fn record_race_carries_the_finishing_order() {
+        let finishing_order = finishing_order(3);
+        let lobby = lobby(&finishing_order);
+        let instruction = record_race(
+            &Pubkey::new_unique(),
+            &lobby,
+            &finishing_order,
+            1_650_000_000,
+            61_250,
+        );
+
+        let remaining = &instruction.accounts[instruction.accounts.len() - 6..];
+        for (pair, doge) in remaining.chunks(2).zip(&finishing_order) {
+            assert_eq!(pair[0].pubkey, doge.doge_racer());
+            assert_eq!(pair[1].pubkey, doge.doge_stats());
+            assert!(pair[1].is_writable);
+        }
+
+        let race_data = race_data_address(&lobby_key(&lobby), lobby.race_count).0;
+        assert!(instruction
+            .accounts
+            .iter()
+            .any(|meta| meta.pubkey.eq(&race_data) && meta.is_writable));
+
+        let data =
+            dogegamecontract::instruction::RecordRace::try_from_slice(&instruction.data[8..])
+                .unwrap();
+        assert_eq!(data.race_started, 1_650_000_000);
+        assert_eq!(data.winning_time, 61_250);
+    }
+
+    #[test]
+    fn conclude_race_targets_the_next_race_record() {
+        let finishing_order = finishing_order(2);
+        let lobby = lobby(&finishing_order);
+        let winner = conclude_race(&conclude_args(&lobby, finishing_order[0]));
+        let loser = conclude_race(&conclude_args(&lobby, finishing_order[1]));
+
+        let race_data = race_data_address(&lobby_key(&lobby), lobby.race_count).0;
+        assert!(winner
+            .accounts
+            .iter()
+            .any(|meta| meta.pubkey.eq(&race_data) && meta.is_writable));
+        assert_eq!(winner.accounts.len(), loser.accounts.len());
+    }
+
+    #[test]
+    fn settling_the_largest_lobby_fits_in_transactions() {
+        let finishing_order = finishing_order(MAX_RATED_RACERS);
+        let lobby = lobby(&finishing_order);
+        let authority = Pubkey::new_unique();
+        let season = Some(SeasonKeys {
+            season_id: 3,
+            prize_mint: Pubkey::new_unique(),
+        });
+
+        let record = record_race(&authority, &lobby, &finishing_order, 1_650_000_000, 61_250);
+        let winner_result = record_racer_result(
+            &authority,
+            &lobby,
+            &finishing_order[0],
+            &Pubkey::new_unique(),
+            season,
+        );
+        let winner = conclude_race(&ConcludeRaceArgs {
+            authority,
+            lobby: &lobby,
+            doge: finishing_order[0],
+            doge_holder: Pubkey::new_unique(),
+            referrer: Some(Pubkey::new_unique()),
+            season,
+        });
+
+        for instruction in [record, winner_result, winner] {
+            let size = transaction_size(instruction, &authority);
+            assert!(size <= PACKET_DATA_SIZE, "{} byte transaction", size);
+        }
+    }
+}
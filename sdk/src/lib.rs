@@ -0,0 +1,42 @@
+//! Client helpers for the doge game and doge-o programs: PDA derivation, instruction
+//! builders that fill in every derived account, and account decoders.
+
+pub mod accounts;
+#[cfg(feature = "client")]
+pub mod client;
+pub mod instructions;
+pub mod pda;
+
+pub use doge_o;
+pub use dogegamecontract;
+
+pub use anchor_lang::solana_program::{instruction::Instruction, pubkey::Pubkey};
+
+// Keys identifying a registered doge. Its doge-o stats and racer PDAs derive from these
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DogeKeys {
+    pub doge_mint: Pubkey,
+    // Authority that initialized the doge's doge-o stats
+    pub init_authority: Pubkey,
+}
+
+impl DogeKeys {
+    pub fn new(doge_mint: Pubkey, init_authority: Pubkey) -> Self {
+        Self {
+            doge_mint,
+            init_authority,
+        }
+    }
+
+    pub fn doge_stats(&self) -> Pubkey {
+        pda::doge_stats_address(&self.init_authority, &self.doge_mint).0
+    }
+
+    pub fn doge_racer(&self) -> Pubkey {
+        pda::doge_racer_address(&self.doge_mint, &self.doge_stats()).0
+    }
+
+    pub fn doge_metadata(&self) -> Pubkey {
+        pda::metadata_address(&self.doge_mint).0
+    }
+}
@@ -0,0 +1,136 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_spl::associated_token::get_associated_token_address;
+use dogegamecontract::utils::get_race_data_address;
+
+pub fn lobby_address(track_holder: &Pubkey, track_mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"lobby", track_holder.as_ref(), track_mint.as_ref()],
+        &dogegamecontract::id(),
+    )
+}
+
+pub fn doge_racer_address(doge_mint: &Pubkey, doge_stats: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"dogeracer", doge_mint.as_ref(), doge_stats.as_ref()],
+        &dogegamecontract::id(),
+    )
+}
+
+// Race records are keyed by the lobby's race count at settlement
+pub fn race_data_address(lobby: &Pubkey, race_count: u64) -> (Pubkey, u8) {
+    get_race_data_address(lobby, race_count)
+}
+
+// Records cached before race counts existed, keyed by the client supplied start time (as a
+// decimal string) and the winner. The program no longer derives these, they can only be closed
+pub fn legacy_race_data_address(
+    race_started: u64,
+    lobby: &Pubkey,
+    winner_doge_racer: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"racedata",
+            race_started.to_string().as_bytes(),
+            lobby.as_ref(),
+            winner_doge_racer.as_ref(),
+        ],
+        &dogegamecontract::id(),
+    )
+}
+
+pub fn entry_fee_requirements_address(entry_fee_mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"entryfeerequirements", entry_fee_mint.as_ref()],
+        &dogegamecontract::id(),
+    )
+}
+
+pub fn treasury_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"treasury"], &dogegamecontract::id())
+}
+
+// Treasury vaults are the treasury PDA's associated token accounts
+pub fn treasury_vault_address(mint: &Pubkey) -> Pubkey {
+    get_associated_token_address(&treasury_address().0, mint)
+}
+
+pub fn fee_schedule_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"feeschedule"], &dogegamecontract::id())
+}
+
+pub fn referral_config_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"referralconfig"], &dogegamecontract::id())
+}
+
+pub fn referrer_address(referrer: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"referrer", referrer.as_ref()], &dogegamecontract::id())
+}
+
+pub fn stake_config_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"stakeconfig"], &dogegamecontract::id())
+}
+
+pub fn stake_position_address(owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"stake", owner.as_ref()], &dogegamecontract::id())
+}
+
+pub fn streak_config_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"streakconfig"], &dogegamecontract::id())
+}
+
+pub fn seasons_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"seasons"], &dogegamecontract::id())
+}
+
+pub fn season_address(season_id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"season", season_id.to_le_bytes().as_ref()],
+        &dogegamecontract::id(),
+    )
+}
+
+pub fn season_racer_address(season: &Pubkey, doge_racer: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"seasonracer", season.as_ref(), doge_racer.as_ref()],
+        &dogegamecontract::id(),
+    )
+}
+
+// Season prize vaults are the season PDA's associated token accounts
+pub fn season_prize_vault_address(season: &Pubkey, prize_mint: &Pubkey) -> Pubkey {
+    get_associated_token_address(season, prize_mint)
+}
+
+pub fn track_stats_address(track_mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"trackstats", track_mint.as_ref()],
+        &dogegamecontract::id(),
+    )
+}
+
+pub fn track_racer_address(track_mint: &Pubkey, doge_racer: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"trackracer", track_mint.as_ref(), doge_racer.as_ref()],
+        &dogegamecontract::id(),
+    )
+}
+
+pub fn doge_stats_address(init_authority: &Pubkey, doge_mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"dogeo", init_authority.as_ref(), doge_mint.as_ref()],
+        &doge_o::id(),
+    )
+}
+
+pub fn doge_rarity_address(doge_mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"rarity", doge_mint.as_ref()], &doge_o::id())
+}
+
+pub fn game_config_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"gameconfig"], &doge_o::id())
+}
+
+pub fn metadata_address(mint: &Pubkey) -> (Pubkey, u8) {
+    mpl_token_metadata::pda::find_metadata_account(mint)
+}